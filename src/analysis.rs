@@ -0,0 +1,494 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Quality analysis of denoised outputs relative to their input, or to a
+//! clean reference of it.
+
+use image_recovery::ndarray::{
+    s,
+    Array2,
+    Array3,
+    Axis,
+};
+
+use crate::region::Region;
+
+/// Side in pixels of the square patches used for patch statistics.
+const PATCH_SIZE: usize = 8;
+/// Radius of the box blur used to look past the noise in the input.
+const BLUR_RADIUS: usize = 3;
+/// Radius of the square windows SSIM is computed over.
+const SSIM_RADIUS: usize = 3;
+
+/// Quantifies TV staircasing: the tendency of the solver to turn smooth
+/// ramps into flat plateaus separated by jumps.
+///
+/// Ramps are found in a blurred copy of the input, as pixels whose gradient
+/// is small but clearly non-zero. Each patch mostly made of ramp pixels is
+/// counted as staircased if most of its pixels are flat in the output while
+/// the input slope says they should not be. Returns the fraction of ramp
+/// patches that are staircased, or `None` if the image has no ramps to
+/// judge by.
+pub fn staircasing(input: &Array3<f64>, output: &Array3<f64>) -> Option<f64> {
+    // gentle slopes, in 8-bit levels per pixel
+    const MIN_SLOPE: f64 = 0.5;
+    const MAX_SLOPE: f64 = 8.0;
+    // a pixel is flat if its slope dropped below this fraction of the input's
+    const FLAT_RATIO: f64 = 0.25;
+
+    let reference = gradient_magnitude(&box_blur(&luma(input), BLUR_RADIUS));
+    let result = gradient_magnitude(&luma(output));
+
+    let (width, height) = reference.dim();
+    let mut ramp_patches = 0;
+    let mut staircased_patches = 0;
+    for px in (0..width.saturating_sub(PATCH_SIZE - 1)).step_by(PATCH_SIZE) {
+        for py in (0..height.saturating_sub(PATCH_SIZE - 1)).step_by(PATCH_SIZE)
+        {
+            let mut ramp = 0;
+            let mut flat = 0;
+            for x in px..px + PATCH_SIZE {
+                for y in py..py + PATCH_SIZE {
+                    let slope = reference[[x, y]];
+                    if (MIN_SLOPE..MAX_SLOPE).contains(&slope) {
+                        ramp += 1;
+                        if result[[x, y]] < FLAT_RATIO * slope {
+                            flat += 1;
+                        }
+                    }
+                }
+            }
+            if 4 * ramp >= 3 * PATCH_SIZE * PATCH_SIZE {
+                ramp_patches += 1;
+                if 2 * flat >= ramp {
+                    staircased_patches += 1;
+                }
+            }
+        }
+    }
+
+    log::debug!(
+        "staircasing: {} of {} ramp patches",
+        staircased_patches,
+        ramp_patches
+    );
+    match ramp_patches {
+        0 => None,
+        n => Some(staircased_patches as f64 / n as f64),
+    }
+}
+
+/// Correlation between the gradient maps of `input` and `output`,
+/// restricted to the strong edges of the input (the top tenth of its
+/// gradient magnitudes, as found on a lightly blurred copy so that noise
+/// doesn't count as edges). Close to 1 when edges survive the denoising,
+/// lower as they get smoothed away. Returns `None` if the input has no
+/// edges or its edges are all equally strong.
+pub fn edge_preservation(
+    input: &Array3<f64>,
+    output: &Array3<f64>,
+) -> Option<f64> {
+    const EDGE_FRACTION: f64 = 0.1;
+
+    let input_luma = luma(input);
+    let strength = gradient_magnitude(&box_blur(&input_luma, 1));
+    let mut sorted: Vec<f64> = strength.iter().cloned().collect();
+    sorted.sort_by(f64::total_cmp);
+    let cutoff_index = ((1.0 - EDGE_FRACTION) * sorted.len() as f64) as usize;
+    let cutoff = *sorted.get(cutoff_index)?;
+    if cutoff <= 0.0 {
+        return None;
+    }
+
+    let before = gradient_magnitude(&input_luma);
+    let after = gradient_magnitude(&luma(output));
+    let pairs: Vec<(f64, f64)> = strength
+        .iter()
+        .zip(before.iter().zip(after.iter()))
+        .filter(|(&s, _)| s >= cutoff)
+        .map(|(_, (&b, &a))| (b, a))
+        .collect();
+
+    correlation(&pairs)
+}
+
+/// Estimates the standard deviation of gaussian noise in an image, in
+/// 8-bit levels, with the method of Immerkær, J. (1996), which responds
+/// little to image structure by convolving with a mask that cancels out
+/// first and second order variations.
+pub fn estimate_noise(array: &Array3<f64>) -> f64 {
+    const MASK: [[f64; 3]; 3] =
+        [[1.0, -2.0, 1.0], [-2.0, 4.0, -2.0], [1.0, -2.0, 1.0]];
+
+    let intensity = luma(array);
+    let (width, height) = intensity.dim();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let mut sum = 0_f64;
+    for x in 1..width - 1 {
+        for y in 1..height - 1 {
+            let mut convolved = 0_f64;
+            for (i, row) in MASK.iter().enumerate() {
+                for (j, weight) in row.iter().enumerate() {
+                    convolved += weight * intensity[[x + i - 1, y + j - 1]];
+                }
+            }
+            sum += convolved.abs();
+        }
+    }
+
+    (std::f64::consts::PI / 2.0).sqrt() * sum
+        / (6.0 * (width - 2) as f64 * (height - 2) as f64)
+}
+
+/// Estimates the standard deviation of the noise from a region that is
+/// known to be flat in the scene (e.g. a gray card or a defocused sky), as
+/// the standard deviation of the intensity inside it. Falls back to
+/// [`estimate_noise`] over the whole image when no region is given.
+pub fn measure_noise(array: &Array3<f64>, patch: Option<Region>) -> f64 {
+    let patch = match patch {
+        Some(patch) => patch,
+        None => return estimate_noise(array),
+    };
+
+    let intensity = luma(array);
+    let region = intensity.slice(s![patch.columns(), patch.rows()]);
+    let mean = region.mean().unwrap_or(0.0);
+    let variance = region.mapv(|v| (v - mean) * (v - mean)).mean();
+    variance.unwrap_or(0.0).sqrt()
+}
+
+/// Standard deviation of the noise of each sample (rather than of the
+/// intensity, in which the noise of the channels partly averages out),
+/// measured per channel as by [`measure_noise`] and combined in quadrature.
+pub fn measure_sample_noise(array: &Array3<f64>, patch: Option<Region>) -> f64 {
+    let channels = array.len_of(Axis(2));
+    let variance = array
+        .axis_iter(Axis(2))
+        .map(|channel| {
+            let channel = channel.insert_axis(Axis(2)).to_owned();
+            measure_noise(&channel, patch).powi(2)
+        })
+        .sum::<f64>()
+        / channels as f64;
+    variance.sqrt()
+}
+
+/// Peak signal-to-noise ratio of an output relative to a clean reference,
+/// in dB, with a peak of 255; infinite if they are the same.
+pub fn psnr(reference: &Array3<f64>, output: &Array3<f64>) -> f64 {
+    let difference = reference - output;
+    let mse = difference.mapv(|v| v * v).mean().unwrap_or(0.0);
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+/// Mean structural similarity index (Wang, Z. et al. (2004)) of the
+/// intensity of an output relative to a clean reference, over square
+/// windows of side `2 * SSIM_RADIUS + 1`; `1` if they are the same.
+pub fn ssim(reference: &Array3<f64>, output: &Array3<f64>) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let (x, y) = (luma(reference), luma(output));
+    let mean = |array: &Array2<f64>| box_blur(array, SSIM_RADIUS);
+    let (mean_x, mean_y) = (mean(&x), mean(&y));
+    let variance_x = mean(&(&x * &x)) - &mean_x * &mean_x;
+    let variance_y = mean(&(&y * &y)) - &mean_y * &mean_y;
+    let covariance = mean(&(&x * &y)) - &mean_x * &mean_y;
+
+    let numerator = (2.0 * &mean_x * &mean_y + C1) * (2.0 * covariance + C2);
+    let denominator = (&mean_x * &mean_x + &mean_y * &mean_y + C1)
+        * (variance_x + variance_y + C2);
+    (numerator / denominator).mean().unwrap_or(1.0)
+}
+
+/// Names of the channels of RGB images, in order.
+pub const CHANNELS: [&str; 3] = ["red", "green", "blue"];
+
+/// How one channel of an output compares to the same channel of a clean
+/// reference.
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct ChannelMetrics {
+    pub channel: &'static str,
+    pub psnr: f64,
+    pub ssim: f64,
+}
+
+/// [`psnr`] and [`ssim`] of each channel on its own, with the samples as
+/// they are denoised (gamma encoded, or linear for floating point images).
+/// Images with a single channel have it named gray.
+pub fn channel_metrics(
+    reference: &Array3<f64>,
+    output: &Array3<f64>,
+) -> Vec<ChannelMetrics> {
+    let channels = reference.len_of(Axis(2));
+    reference
+        .axis_iter(Axis(2))
+        .zip(output.axis_iter(Axis(2)))
+        .enumerate()
+        .map(|(index, (reference, output))| {
+            let reference = reference.insert_axis(Axis(2)).to_owned();
+            let output = output.insert_axis(Axis(2)).to_owned();
+            ChannelMetrics {
+                channel: match channels {
+                    1 => "gray",
+                    _ => CHANNELS.get(index).cloned().unwrap_or("other"),
+                },
+                psnr: psnr(&reference, &output),
+                ssim: ssim(&reference, &output),
+            }
+        })
+        .collect()
+}
+
+/// Statistics of the CIEDE2000 color difference (ΔE00) between the pixels
+/// of two images, where about 1 is the smallest difference an observer
+/// notices side by side.
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct ColorDifference {
+    pub mean: f64,
+    /// 95th percentile.
+    pub p95: f64,
+    pub max: f64,
+}
+
+/// CIEDE2000 color difference between the pixels of two images, as sRGB
+/// (or linear RGB with the sRGB primaries, if `linear`) under a D65 white.
+/// Images with a single channel are taken as gray.
+pub fn delta_e(
+    reference: &Array3<f64>,
+    output: &Array3<f64>,
+    linear: bool,
+) -> ColorDifference {
+    let lab = |pixel: image_recovery::ndarray::ArrayView1<f64>| {
+        let channel = |c: usize| pixel[c.min(pixel.len() - 1)];
+        to_lab([channel(0), channel(1), channel(2)], linear)
+    };
+    let mut differences: Vec<f64> = reference
+        .lanes(Axis(2))
+        .into_iter()
+        .zip(output.lanes(Axis(2)))
+        .map(|(reference, output)| ciede2000(lab(reference), lab(output)))
+        .collect();
+    differences.sort_by(f64::total_cmp);
+    let count = differences.len().max(1);
+    ColorDifference {
+        mean: differences.iter().sum::<f64>() / count as f64,
+        p95: differences
+            .get((count * 95).div_ceil(100).saturating_sub(1))
+            .cloned()
+            .unwrap_or(0.0),
+        max: differences.last().cloned().unwrap_or(0.0),
+    }
+}
+
+/// CIELAB coordinates of RGB samples on the 8-bit scale.
+fn to_lab(rgb: [f64; 3], linear: bool) -> [f64; 3] {
+    // D65 reference white
+    const WHITE: [f64; 3] = [0.950_47, 1.0, 1.088_83];
+    const DELTA: f64 = 6.0 / 29.0;
+
+    let [r, g, b] = rgb.map(|value| {
+        let value = value / 255.0;
+        match linear {
+            true => value,
+            false if value <= 0.040_45 => value / 12.92,
+            false => ((value + 0.055) / 1.055).powf(2.4),
+        }
+    });
+    let xyz = [
+        0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b,
+        0.212_672_9 * r + 0.715_152_2 * g + 0.072_175_0 * b,
+        0.019_333_9 * r + 0.119_192_0 * g + 0.950_304_1 * b,
+    ];
+    let [x, y, z] = [0, 1, 2].map(|i| {
+        let t = xyz[i] / WHITE[i];
+        match t > DELTA.powi(3) {
+            true => t.cbrt(),
+            false => t / (3.0 * DELTA * DELTA) + 4.0 / 29.0,
+        }
+    });
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+/// CIEDE2000 difference of two CIELAB colors (Sharma, G. et al. (2005)),
+/// with unit weighting factors.
+fn ciede2000([l1, a1, b1]: [f64; 3], [l2, a2, b2]: [f64; 3]) -> f64 {
+    const POW25_7: f64 = 6_103_515_625.0;
+
+    let chroma_mean = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let g = 0.5
+        * (1.0
+            - (chroma_mean.powi(7) / (chroma_mean.powi(7) + POW25_7)).sqrt());
+    let (a1, a2) = ((1.0 + g) * a1, (1.0 + g) * a2);
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let hue = |a: f64, b: f64| match a == 0.0 && b == 0.0 {
+        true => 0.0,
+        false => b.atan2(a).to_degrees().rem_euclid(360.0),
+    };
+    let (h1, h2) = (hue(a1, b1), hue(a2, b2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = match c1 * c2 == 0.0 {
+        true => 0.0,
+        false => match h2 - h1 {
+            dh if dh > 180.0 => dh - 360.0,
+            dh if dh < -180.0 => dh + 360.0,
+            dh => dh,
+        },
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h.to_radians() / 2.0).sin();
+
+    let l_mean = (l1 + l2) / 2.0;
+    let c_mean = (c1 + c2) / 2.0;
+    let h_mean = match c1 * c2 == 0.0 {
+        true => h1 + h2,
+        false if (h1 - h2).abs() <= 180.0 => (h1 + h2) / 2.0,
+        false if h1 + h2 < 360.0 => (h1 + h2 + 360.0) / 2.0,
+        false => (h1 + h2 - 360.0) / 2.0,
+    };
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t = 1.0 - 0.17 * cos(h_mean - 30.0)
+        + 0.24 * cos(2.0 * h_mean)
+        + 0.32 * cos(3.0 * h_mean + 6.0)
+        - 0.20 * cos(4.0 * h_mean - 63.0);
+    let delta_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (c_mean.powi(7) / (c_mean.powi(7) + POW25_7)).sqrt();
+    let s_l = 1.0
+        + 0.015 * (l_mean - 50.0).powi(2)
+            / (20.0 + (l_mean - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_mean;
+    let s_h = 1.0 + 0.015 * c_mean * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+    (l * l + c * c + h * h + r_t * c * h).max(0.0).sqrt()
+}
+
+/// Norm of the difference between an output and its input, the data term
+/// of the model.
+pub fn residual_norm(input: &Array3<f64>, output: &Array3<f64>) -> f64 {
+    (input - output).mapv(|v| v * v).sum().sqrt()
+}
+
+/// Total variation of an image, the sum over its channels of the magnitude
+/// of their gradients: the regularization term of the model.
+pub fn total_variation(array: &Array3<f64>) -> f64 {
+    array
+        .axis_iter(Axis(2))
+        .map(|channel| gradient_magnitude(&channel.to_owned()).sum())
+        .sum()
+}
+
+/// Finds the corner of the L-curve, the standard reference-free choice of
+/// a regularization parameter, from `(λ, residual norm, total variation)`
+/// points sorted by ascending λ.
+///
+/// On a log-log scale the residual norm falls and the total variation
+/// rises with λ, along a curve shaped like an L whose corner balances the
+/// two. It is taken to be the point of largest curvature, estimated at
+/// each point from the circle through it and its neighbours (Menger
+/// curvature) and counting only bends toward the origin. Returns `None`
+/// with fewer than 3 points, or if the curve has no such bend.
+pub fn l_curve_corner(points: &[(f64, f64, f64)]) -> Option<f64> {
+    let logged: Vec<(f64, f64, f64)> = points
+        .iter()
+        .filter(|(_, residual, tv)| *residual > 0.0 && *tv > 0.0)
+        .map(|&(lambda, residual, tv)| (lambda, residual.ln(), tv.ln()))
+        .collect();
+    logged
+        .windows(3)
+        .filter_map(|window| {
+            let [(_, x0, y0), (lambda, x1, y1), (_, x2, y2)] = *window else {
+                unreachable!("windows of 3")
+            };
+            let cross = (x1 - x0) * (y2 - y1) - (y1 - y0) * (x2 - x1);
+            let sides = (x1 - x0).hypot(y1 - y0)
+                * (x2 - x1).hypot(y2 - y1)
+                * (x2 - x0).hypot(y2 - y0);
+            // going from small to large λ the curve turns clockwise, from
+            // its flat arm toward its steep one, at the corner
+            let curvature = -2.0 * cross / sides;
+            (curvature.is_finite() && curvature > 0.0)
+                .then_some((lambda, curvature))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(lambda, _)| lambda)
+}
+
+/// Pearson correlation coefficient of a set of pairs, `None` if either
+/// side has no variance.
+fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    let n = pairs.len() as f64;
+    let mean_a = pairs.iter().map(|(a, _)| a).sum::<f64>() / n;
+    let mean_b = pairs.iter().map(|(_, b)| b).sum::<f64>() / n;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (a, b) in pairs {
+        covariance += (a - mean_a) * (b - mean_b);
+        variance_a += (a - mean_a) * (a - mean_a);
+        variance_b += (b - mean_b) * (b - mean_b);
+    }
+    if variance_a <= 0.0 || variance_b <= 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_a * variance_b).sqrt())
+}
+
+/// Averages the color axis into a single intensity channel.
+fn luma(array: &Array3<f64>) -> Array2<f64> {
+    array
+        .mean_axis(Axis(2))
+        .expect("image has at least one channel")
+}
+
+/// Separable box blur, replicating values past the borders.
+fn box_blur(array: &Array2<f64>, radius: usize) -> Array2<f64> {
+    let mut blurred = array.clone();
+    for axis in 0..2 {
+        let source = blurred.clone();
+        let len = source.len_of(Axis(axis)) as isize;
+        for ((x, y), value) in blurred.indexed_iter_mut() {
+            let mut sum = 0_f64;
+            for offset in -(radius as isize)..=radius as isize {
+                let mut index = [x as isize, y as isize];
+                index[axis] = (index[axis] + offset).clamp(0, len - 1);
+                sum += source[[index[0] as usize, index[1] as usize]];
+            }
+            *value = sum / (2 * radius + 1) as f64;
+        }
+    }
+    blurred
+}
+
+/// Magnitude of the forward-difference gradient, zero past the borders.
+fn gradient_magnitude(array: &Array2<f64>) -> Array2<f64> {
+    let (width, height) = array.dim();
+    Array2::from_shape_fn((width, height), |(x, y)| {
+        let dx = if x + 1 < width {
+            array[[x + 1, y]] - array[[x, y]]
+        } else {
+            0.0
+        };
+        let dy = if y + 1 < height {
+            array[[x, y + 1]] - array[[x, y]]
+        } else {
+            0.0
+        };
+        (dx * dx + dy * dy).sqrt()
+    })
+}
@@ -0,0 +1,284 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Tamper-evident audit log of every file read and written, appended to
+//! across runs as newline-delimited JSON. Each entry holds the hash of the
+//! one before it, so that editing or removing any entry (but the last
+//! ones) breaks the chain from there on, as `check-audit` finds.
+
+use std::{
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::Mutex,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use crate::{
+    journal,
+    storage::{
+        Space,
+        Storage,
+    },
+};
+
+/// The `previous` hash of the first entry of a log.
+const GENESIS: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Read,
+    Write,
+    Append,
+    Remove,
+    /// A shell command about to be run, on the file at `path`.
+    Run,
+}
+
+/// One line of the audit log.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    /// Position in the log, from 0.
+    pub sequence: u64,
+    /// Seconds since the Unix epoch at which the operation was done.
+    pub timestamp: u64,
+    /// User and host the program ran as and on, if known.
+    pub user: Option<String>,
+    pub host: Option<String>,
+    pub process: u32,
+    /// Arguments of the command line, the program included.
+    pub command: Vec<String>,
+    pub operation: Operation,
+    /// The shell command run, for a `run`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell_command: Option<String>,
+    /// Absolute path of the file (or folder) operated on.
+    pub path: PathBuf,
+    pub bytes: usize,
+    /// SHA-256 of the bytes read or written, in hexadecimal (none for a
+    /// removal or a run).
+    pub sha256: Option<String>,
+    /// `hash` of the entry before, or zeros for the first one.
+    pub previous: String,
+    /// SHA-256 of this entry serialized with an empty `hash`.
+    pub hash: String,
+}
+
+impl Entry {
+    fn digest(&self) -> String {
+        let unhashed = Entry {
+            hash: String::new(),
+            ..self.clone()
+        };
+        hex(&Sha256::digest(
+            serde_json::to_vec(&unhashed).expect("entries serialize"),
+        ))
+    }
+}
+
+/// Another storage, whose every read, write and removal (and every command
+/// run) is recorded in an audit log (itself written through the other
+/// storage, but not recorded).
+/// The chain of hashes only holds if one run at a time appends to a log.
+pub struct Audited<S> {
+    inner: S,
+    log: PathBuf,
+    /// Sequence number and hash of the last entry, if any.
+    last: Mutex<Option<(u64, String)>>,
+    user: Option<String>,
+    host: Option<String>,
+    command: Vec<String>,
+}
+
+impl<S: Storage> Audited<S> {
+    /// Continues the audit log at `log`, or starts it if it does not exist.
+    pub fn new(inner: S, log: &Path) -> io::Result<Self> {
+        let last = match inner.is_file(log) {
+            true => {
+                let contents = inner.read(log)?;
+                match contents
+                    .split(|&byte| byte == b'\n')
+                    .rfind(|line| !line.is_empty())
+                {
+                    Some(line) => {
+                        let entry: Entry = serde_json::from_slice(line)?;
+                        Some((entry.sequence, entry.hash))
+                    },
+                    None => None,
+                }
+            },
+            false => None,
+        };
+        let variable = |names: &[&str]| {
+            names.iter().find_map(|name| std::env::var(name).ok())
+        };
+        Ok(Self {
+            inner,
+            log: log.to_path_buf(),
+            last: Mutex::new(last),
+            user: variable(&["USER", "USERNAME"]),
+            host: variable(&["HOSTNAME", "COMPUTERNAME"]),
+            command: std::env::args().collect(),
+        })
+    }
+
+    fn record(
+        &self,
+        operation: Operation,
+        path: &Path,
+        bytes: Option<&[u8]>,
+        shell_command: Option<&str>,
+    ) -> io::Result<()> {
+        let mut last = self
+            .last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (sequence, previous) = match &*last {
+            Some((sequence, hash)) => (sequence + 1, hash.clone()),
+            None => (0, GENESIS.to_string()),
+        };
+        let mut entry = Entry {
+            sequence,
+            timestamp: journal::now(),
+            user: self.user.clone(),
+            host: self.host.clone(),
+            process: std::process::id(),
+            command: self.command.clone(),
+            operation,
+            shell_command: shell_command.map(str::to_string),
+            path: std::path::absolute(path)?,
+            bytes: bytes.map_or(0, <[u8]>::len),
+            sha256: bytes.map(|bytes| hex(&Sha256::digest(bytes))),
+            previous,
+            hash: String::new(),
+        };
+        entry.hash = entry.digest();
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        self.inner.append(&self.log, &line)?;
+        *last = Some((entry.sequence, entry.hash));
+        Ok(())
+    }
+}
+
+impl<S: Storage> Storage for Audited<S> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let bytes = self.inner.read(path)?;
+        self.record(Operation::Read, path, Some(&bytes), None)?;
+        Ok(bytes)
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.inner.write(path, bytes)?;
+        self.record(Operation::Write, path, Some(bytes), None)
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.inner.append(path, bytes)?;
+        self.record(Operation::Append, path, Some(bytes), None)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.inner.is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.inner.is_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.inner.create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.inner.remove_dir_all(path)?;
+        self.record(Operation::Remove, path, None, None)
+    }
+
+    fn free_space(&self, path: &Path) -> Option<Space> {
+        self.inner.free_space(path)
+    }
+
+    fn allows(&self, path: &Path) -> bool {
+        self.inner.allows(path)
+    }
+
+    fn before_command(&self, command: &str, paths: &[&Path]) -> io::Result<()> {
+        self.inner.before_command(command, paths)?;
+        // recorded before it runs, as what it does is not
+        for path in paths {
+            self.record(Operation::Run, path, None, Some(command))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the chain of hashes of the audit log at `path`, printing where
+/// it breaks if it does, and returns whether it holds.
+pub fn check(storage: &dyn Storage, path: &Path) -> bool {
+    let contents = match storage.read(path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            println!("FAILED: {} could not be read: {}", path.display(), error);
+            return false;
+        },
+    };
+    let mut previous = GENESIS.to_string();
+    let mut count = 0;
+    let lines = contents.split(|&byte| byte == b'\n');
+    for (number, line) in lines.enumerate().filter(|(_, line)| !line.is_empty())
+    {
+        let failure = match serde_json::from_slice::<Entry>(line) {
+            Err(error) => Some(format!("is not an entry: {}", error)),
+            Ok(entry) if entry.sequence != count => Some(format!(
+                "has sequence {} instead of {}",
+                entry.sequence, count
+            )),
+            Ok(entry) if entry.previous != previous => {
+                Some("does not follow the entry before it".to_string())
+            },
+            Ok(entry) if entry.digest() != entry.hash => {
+                Some("was modified after it was written".to_string())
+            },
+            Ok(entry) => {
+                previous = entry.hash;
+                None
+            },
+        };
+        if let Some(failure) = failure {
+            println!("FAILED: line {} {}", number + 1, failure);
+            return false;
+        }
+        count += 1;
+    }
+    println!("ok: {} entries, chain of hashes intact", count);
+    true
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,225 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Timings of the operations the solver and the conversions around it are
+//! made of, on synthetic images of several sizes, for `bench-kernels`: to
+//! guide optimization with data, and for users to report how their
+//! machines perform.
+
+use std::time::Instant;
+
+use image_recovery::{
+    ndarray::Array3,
+    ImageArray,
+};
+use serde::Serialize;
+
+use crate::{
+    cancel,
+    cpus,
+    output,
+    random::Random,
+    solver,
+};
+
+#[derive(Serialize, Debug)]
+pub struct Bench {
+    pub version: &'static str,
+    /// Target the binary was built for, as `<arch>-<os>`.
+    pub target: String,
+    /// Threads available to the process, if known; every timing is on a
+    /// single thread.
+    pub available_parallelism: Option<usize>,
+    pub repeats: usize,
+    pub timings: Vec<Timing>,
+}
+
+/// Timings of one operation on one size of image.
+#[derive(Serialize, Debug)]
+pub struct Timing {
+    pub kernel: &'static str,
+    /// Precision of the solver computed in, for its kernels.
+    pub precision: Option<solver::Precision>,
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub median_seconds: f64,
+    pub min_seconds: f64,
+    /// Megapixels processed per second, at the median time.
+    pub megapixels_per_second: f64,
+}
+
+impl Timing {
+    fn new(
+        kernel: &'static str,
+        precision: Option<solver::Precision>,
+        image: &Array3<f64>,
+        mut seconds: Vec<f64>,
+    ) -> Self {
+        let (width, height, channels) = image.dim();
+        seconds.sort_by(f64::total_cmp);
+        let median = seconds[seconds.len() / 2];
+        Self {
+            kernel,
+            precision,
+            width,
+            height,
+            channels,
+            median_seconds: median,
+            min_seconds: seconds[0],
+            megapixels_per_second: (width * height) as f64 / 1e6 / median,
+        }
+    }
+}
+
+/// Times every operation `repeats` times on a square RGB image of noise of
+/// each side in `sizes`.
+pub fn run(
+    sizes: &[usize],
+    repeats: usize,
+    cancel: &cancel::Token,
+) -> Result<Bench, cancel::Cancelled> {
+    let repeats = repeats.max(1);
+    let mut timings = Vec::new();
+    for &size in sizes {
+        let image = noise(size);
+        for precision in [solver::Precision::F32, solver::Precision::F64] {
+            for kernel in solver::Kernel::ALL {
+                cancel.check()?;
+                log::info!(
+                    "timing {} in {} on {}x{}",
+                    kernel.name(),
+                    precision,
+                    size,
+                    size
+                );
+                let seconds =
+                    solver::time_kernel(kernel, &image, precision, repeats);
+                timings.push(Timing::new(
+                    kernel.name(),
+                    Some(precision),
+                    &image,
+                    seconds,
+                ));
+            }
+            cancel.check()?;
+            log::info!(
+                "timing iterations in {} on {}x{}",
+                precision,
+                size,
+                size
+            );
+            timings.push(Timing::new(
+                "iteration",
+                Some(precision),
+                &image,
+                time_iterations(&image, precision, repeats, cancel)?,
+            ));
+        }
+
+        cancel.check()?;
+        log::info!("timing conversions on {}x{}", size, size);
+        let rgb = ImageArray::from(&image).into_rgb();
+        timings.push(Timing::new(
+            "from_rgb8",
+            None,
+            &image,
+            time(repeats, || drop(ImageArray::from(&rgb))),
+        ));
+        timings.push(Timing::new(
+            "to_rgb8",
+            None,
+            &image,
+            time(repeats, || drop(ImageArray::from(&image).into_rgb())),
+        ));
+        timings.push(Timing::new(
+            "to_rgb32f",
+            None,
+            &image,
+            time(repeats, || drop(output::to_rgb32f(&image))),
+        ));
+    }
+
+    Ok(Bench {
+        version: env!("CARGO_PKG_VERSION"),
+        target: format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+        available_parallelism: cpus::available()
+            .ok()
+            .map(|available| available.get()),
+        repeats,
+        timings,
+    })
+}
+
+/// Seconds taken by each of `repeats` calls of `f`, after a first call to
+/// warm up.
+fn time(repeats: usize, mut f: impl FnMut()) -> Vec<f64> {
+    (0..=repeats)
+        .map(|_| {
+            let started = Instant::now();
+            f();
+            started.elapsed().as_secs_f64()
+        })
+        .skip(1)
+        .collect()
+}
+
+/// Seconds taken by each of `repeats` whole iterations of a solve, as the
+/// time between one iteration and the next (so leaving out the setup
+/// before the first, and the first itself).
+fn time_iterations(
+    image: &Array3<f64>,
+    precision: solver::Precision,
+    repeats: usize,
+    cancel: &cancel::Token,
+) -> Result<Vec<f64>, cancel::Cancelled> {
+    let lambda = 0.03;
+    let parameters = solver::Parameters {
+        lambda,
+        tau: solver::tau(),
+        sigma: solver::sigma(),
+        gamma: solver::gamma(lambda),
+        max_iter: repeats as u32 + 2,
+        // never reached, so that every iteration is run
+        convergence_threshold: 0.0,
+        acceleration: solver::Acceleration::Gamma,
+        channel_copies: 1,
+        threads: 1,
+        precision,
+        data_weight: None,
+        data_band: None,
+        stop_on_gap: None,
+        keep_dual: false,
+    };
+    let mut finished = Vec::with_capacity(repeats + 2);
+    match solver::denoise(image, &parameters, cancel, &mut |_| {
+        finished.push(Instant::now())
+    }) {
+        Ok(_) => (),
+        Err(solver::Error::Cancelled(cancelled)) => return Err(cancelled),
+        Err(error) => panic!("benchmark solve failed: {}", error),
+    }
+    Ok(finished
+        .windows(2)
+        .skip(1)
+        .map(|pair| (pair[1] - pair[0]).as_secs_f64())
+        .collect())
+}
+
+/// A square RGB image of uniform noise over the 8-bit range.
+fn noise(size: usize) -> Array3<f64> {
+    let mut random = Random::new(0);
+    Array3::from_shape_fn((size, size, 3), |_| random.range(0.0, 255.0))
+}
@@ -0,0 +1,101 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Boundary conditions for the solve.
+//!
+//! The gradient operator of the solver wraps around the borders of the
+//! image (periodic boundary), so opposite edges bleed into each other.
+//! Other boundary conditions are emulated by padding the image before the
+//! solve, so that the wrapping happens far from the actual image, and
+//! cropping the result afterwards.
+
+use image_recovery::ndarray::{
+    s,
+    Array3,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Boundary {
+    /// Wrap around to the opposite edge, as the solver does natively
+    Periodic,
+    /// Repeat the pixels on the edges outwards
+    Replicate,
+    /// Mirror the image at its edges
+    Reflect,
+    /// Surround the image with black
+    Zero,
+}
+
+/// Pads `array` by `padding` pixels on every side of the x and y axes,
+/// according to `boundary`. Periodic boundaries need no padding, so the
+/// array is returned unchanged.
+pub fn pad(
+    array: &Array3<f64>,
+    padding: usize,
+    boundary: Boundary,
+) -> Array3<f64> {
+    if boundary == Boundary::Periodic || padding == 0 {
+        return array.clone();
+    }
+
+    let (width, height, channels) = array.dim();
+    let source = |i: usize, n: usize| -> Option<usize> {
+        let i = i as isize - padding as isize;
+        match boundary {
+            Boundary::Replicate => Some(i.clamp(0, n as isize - 1) as usize),
+            Boundary::Reflect => {
+                let period = 2 * n as isize;
+                let m = i.rem_euclid(period);
+                Some(if m < n as isize { m } else { period - 1 - m } as usize)
+            },
+            Boundary::Zero => {
+                (0..n as isize).contains(&i).then_some(i as usize)
+            },
+            Boundary::Periodic => unreachable!(),
+        }
+    };
+
+    Array3::from_shape_fn(
+        (width + 2 * padding, height + 2 * padding, channels),
+        |(x, y, z)| match (source(x, width), source(y, height)) {
+            (Some(x), Some(y)) => array[[x, y, z]],
+            _ => 0.0,
+        },
+    )
+}
+
+/// Removes `padding` pixels from every side of the x and y axes, undoing
+/// [`pad`].
+pub fn crop(
+    array: &Array3<f64>,
+    padding: usize,
+    boundary: Boundary,
+) -> Array3<f64> {
+    if boundary == Boundary::Periodic || padding == 0 {
+        return array.clone();
+    }
+
+    let (width, height, _) = array.dim();
+    array
+        .slice(s![padding..width - padding, padding..height - padding, ..])
+        .to_owned()
+}
@@ -0,0 +1,174 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! What this build can read and write, for `list-formats`, so that tools
+//! driving it can find out without trial and error.
+
+use image_recovery::image::ImageFormat;
+use serde::Serialize;
+
+use crate::solver;
+
+/// The optional features of the crate, and whether they are enabled.
+const FEATURES: [(&str, bool); 2] = [
+    ("plot", cfg!(feature = "plot")),
+    ("dicom", cfg!(feature = "dicom")),
+];
+
+#[derive(Serialize, Debug)]
+pub struct Capabilities {
+    pub version: &'static str,
+    /// Target the binary was built for, as `<arch>-<os>`.
+    pub target: String,
+    pub inputs: Vec<Format>,
+    pub outputs: Vec<Output>,
+    /// Sample formats inputs may have, all converted to RGB.
+    pub input_samples: Vec<&'static str>,
+    /// How samples are interpreted.
+    pub color_space: &'static str,
+    /// Floating point precisions the solver can compute in.
+    pub precisions: Vec<solver::Precision>,
+    /// Cargo features enabled in this build.
+    pub features: Vec<&'static str>,
+}
+
+/// An image format that decodes as input.
+#[derive(Serialize, Debug)]
+pub struct Format {
+    pub name: String,
+    pub extensions: Vec<&'static str>,
+}
+
+/// A kind of file the program writes.
+#[derive(Serialize, Debug)]
+pub struct Output {
+    pub name: &'static str,
+    pub extension: &'static str,
+    pub samples: &'static str,
+    pub written_for: &'static str,
+}
+
+impl Capabilities {
+    pub fn of_this_build() -> Self {
+        let mut inputs: Vec<Format> = ImageFormat::all()
+            .filter(|format| format.can_read() && format.reading_enabled())
+            .map(|format| Format {
+                name: format!("{:?}", format),
+                extensions: format.extensions_str().to_vec(),
+            })
+            .collect();
+        if cfg!(feature = "dicom") {
+            inputs.push(Format {
+                name: "Dicom".to_string(),
+                extensions: vec!["dcm"],
+            });
+        }
+        let mut outputs = vec![
+            Output {
+                name: "PNG",
+                extension: "png",
+                samples: "8-bit RGB",
+                written_for: "results of integer inputs, previews of \
+                              --tone-map, heat maps of --diff-heatmap",
+            },
+            Output {
+                name: "OpenEXR",
+                extension: "exr",
+                samples: "32-bit float RGB",
+                written_for: "results of floating point inputs",
+            },
+            Output {
+                name: "TIFF",
+                extension: "tif",
+                samples: "8-bit RGB",
+                written_for: match cfg!(feature = "dicom") {
+                    true => {
+                        "results of GeoTIFF inputs, with their georeferencing, \
+                         and of DICOM inputs by --dicom-format"
+                    },
+                    false => {
+                        "results of GeoTIFF inputs, with their georeferencing"
+                    },
+                },
+            },
+        ];
+        if cfg!(feature = "plot") {
+            outputs.push(Output {
+                name: "SVG",
+                extension: "svg",
+                samples: "vector",
+                written_for: "plots of --plot and --plot-l-curve",
+            });
+        }
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            target: format!(
+                "{}-{}",
+                std::env::consts::ARCH,
+                std::env::consts::OS
+            ),
+            inputs,
+            outputs,
+            input_samples: vec![
+                "8-bit integer",
+                "16-bit integer",
+                "32-bit float",
+            ],
+            color_space: "samples are denoised as stored (gamma encoded for \
+                          integer formats, linear for EXR), without color \
+                          management; alpha is dropped",
+            precisions: vec![solver::Precision::F32, solver::Precision::F64],
+            features: FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(feature, _)| *feature)
+                .collect(),
+        }
+    }
+
+    /// Prints the capabilities as a list to stdout.
+    pub fn print(&self) {
+        println!("denoise-cli {} ({})", self.version, self.target);
+        println!("inputs:");
+        for format in &self.inputs {
+            println!("  {:<10} {}", format.name, format.extensions.join(", "));
+        }
+        println!("  samples: {}", self.input_samples.join(", "));
+        println!("outputs:");
+        for output in &self.outputs {
+            println!(
+                "  {:<10} {:<18} {}",
+                output.name, output.samples, output.written_for
+            );
+        }
+        println!("color space: {}", self.color_space);
+        println!(
+            "solver precisions: {}",
+            self.precisions
+                .iter()
+                .map(|precision| precision.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        println!(
+            "features: {}",
+            match self.features.is_empty() {
+                true => "none".to_string(),
+                false => self.features.join(", "),
+            }
+        );
+    }
+}
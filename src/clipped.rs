@@ -0,0 +1,60 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Protection of clipped samples (blown highlights and crushed blacks),
+//! whose true values are lost beyond the ends of the range: pulled toward
+//! them by the data term, the smoothing would spread them into halos
+//! around light sources and shadows.
+
+use image_recovery::ndarray::{
+    Array3,
+    Zip,
+};
+
+/// How close to the ends of the 8-bit range (in levels) a sample must be
+/// to be taken as clipped, so that rounding to 16 bits still counts.
+const MARGIN: f64 = 0.5;
+
+/// Whether a sample on the 8-bit scale is at either end of the range.
+fn is_clipped(value: f64) -> bool {
+    value <= MARGIN || value >= 255.0 - MARGIN
+}
+
+/// Weights of the data term for each sample of `array`: `weight` for the
+/// clipped ones and 1 for the rest, or `None` if none are clipped.
+pub fn data_weight(array: &Array3<f64>, weight: f64) -> Option<Array3<f64>> {
+    let clipped = array.iter().filter(|&&value| is_clipped(value)).count();
+    log::debug!(
+        "{} clipped samples ({:.3}%)",
+        clipped,
+        clipped as f64 / array.len().max(1) as f64 * 100.0
+    );
+    (clipped > 0).then(|| {
+        array.mapv(|value| match is_clipped(value) {
+            true => weight,
+            false => 1.0,
+        })
+    })
+}
+
+/// Puts the clipped samples of `input` back into the `solved` image, as
+/// the smoothing has nothing to tell about them.
+pub fn restore(solved: &mut Array3<f64>, input: &Array3<f64>) {
+    Zip::from(solved).and(input).for_each(|solved, &input| {
+        if is_clipped(input) {
+            *solved = input;
+        }
+    });
+}
@@ -0,0 +1,103 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Stopping conditions of the solver, in the terms users give them.
+
+use std::str::FromStr;
+
+use image_recovery::ndarray::Array3;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// A change per pixel and iteration at which to stop, as a fraction of the
+/// full range, given either as a plain fraction (`0.0004`) or as levels
+/// out of a full scale (`0.1/255`, or `25/65535` for 16-bit images).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Delta(pub f64);
+
+impl Delta {
+    /// The solver stops once the norm of the change between iterations
+    /// relative to the norm of the previous iterate falls below its
+    /// threshold. A root-mean-square change of `delta` per pixel is, on an
+    /// image with root-mean-square intensity `rms`, a relative change of
+    /// `delta / rms`; the intensity of the output is taken to be that of
+    /// the input.
+    pub fn relative_threshold(&self, array: &Array3<f64>) -> f64 {
+        // the solver works on the 8-bit scale
+        let delta = self.0 * 255.0;
+        let rms = array.mapv(|v| v * v).mean().unwrap_or(0.0).sqrt();
+        delta / rms
+    }
+}
+
+impl FromStr for Delta {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |number: &str| {
+            number
+                .trim()
+                .parse::<f64>()
+                .map_err(|error| format!("invalid delta `{}`: {}", s, error))
+        };
+        let fraction = match s.split_once('/') {
+            Some((levels, full_scale)) => parse(levels)? / parse(full_scale)?,
+            None => parse(s)?,
+        };
+        if !(fraction.is_finite() && fraction > 0.0) {
+            return Err(format!("invalid delta `{}`: must be positive", s));
+        }
+        Ok(Delta(fraction))
+    }
+}
+
+/// Predicts how many iterations in total a solve needs for its residual to
+/// fall below `threshold`, from the residuals of its first iterations.
+///
+/// The residuals of the accelerated solver decay roughly as a power of the
+/// iteration number, so a line is fit to the second half of them (the
+/// first iterations are irregular) on a log-log scale, and extrapolated.
+/// Returns `None` if they are not decaying.
+pub fn predict_iterations(residuals: &[f64], threshold: f64) -> Option<u32> {
+    let points: Vec<(f64, f64)> = residuals
+        .iter()
+        .enumerate()
+        .skip(residuals.len() / 2)
+        .filter(|(_, &residual)| residual > 0.0)
+        .map(|(i, &residual)| (((i + 1) as f64).ln(), residual.ln()))
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let slope = covariance / variance;
+    if slope.is_nan() || slope >= 0.0 {
+        return None;
+    }
+
+    // where the fitted line crosses the threshold
+    let iterations = ((threshold.ln() - mean_y) / slope + mean_x).exp();
+    Some(iterations.ceil().clamp(1.0, u32::MAX as f64) as u32)
+}
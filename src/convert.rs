@@ -0,0 +1,72 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Conversion of images between formats and sample depths, with the same
+//! decoding and encoding as denoising, for the steps around it that would
+//! otherwise need another tool.
+
+use image_recovery::image::{
+    ColorType,
+    DynamicImage,
+};
+
+/// Depth of the samples of a converted image.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitDepth {
+    #[value(name = "8")]
+    U8,
+    #[value(name = "16")]
+    U16,
+    /// Floating point
+    #[value(name = "32")]
+    F32,
+}
+
+impl BitDepth {
+    /// The depth closest to that of the samples of `img`.
+    pub fn of(img: &DynamicImage) -> Self {
+        match img.color() {
+            ColorType::L8
+            | ColorType::La8
+            | ColorType::Rgb8
+            | ColorType::Rgba8 => BitDepth::U8,
+            ColorType::Rgb32F | ColorType::Rgba32F => BitDepth::F32,
+            _ => BitDepth::U16,
+        }
+    }
+}
+
+/// `img` with samples of `depth`, keeping whether it is gray and whether
+/// it has alpha. Floating point images are always RGB, as there are no
+/// gray ones.
+pub fn to_depth(img: DynamicImage, depth: BitDepth) -> DynamicImage {
+    let color = img.color();
+    let gray = matches!(
+        color,
+        ColorType::L8 | ColorType::La8 | ColorType::L16 | ColorType::La16
+    );
+    match (depth, gray, color.has_alpha()) {
+        (BitDepth::U8, true, false) => img.into_luma8().into(),
+        (BitDepth::U8, true, true) => img.into_luma_alpha8().into(),
+        (BitDepth::U8, false, false) => img.into_rgb8().into(),
+        (BitDepth::U8, false, true) => img.into_rgba8().into(),
+        (BitDepth::U16, true, false) => img.into_luma16().into(),
+        (BitDepth::U16, true, true) => img.into_luma_alpha16().into(),
+        (BitDepth::U16, false, false) => img.into_rgb16().into(),
+        (BitDepth::U16, false, true) => img.into_rgba16().into(),
+        (BitDepth::F32, _, false) => img.into_rgb32f().into(),
+        (BitDepth::F32, _, true) => img.into_rgba32f().into(),
+    }
+}
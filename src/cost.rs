@@ -0,0 +1,143 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Estimates of what a run cost to compute, to attribute and budget
+//! processing: the CPU time of the process, the energy drawn by the CPU
+//! packages as counted by RAPL (on Linux, where readable), and the work
+//! done by the solver in megapixel-iterations.
+
+use std::{
+    path::PathBuf,
+    time::Instant,
+};
+
+use serde::Serialize;
+
+/// Folder of the power capping interface of Linux, holding the RAPL
+/// energy counters.
+const POWERCAP: &str = "/sys/class/powercap";
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct Cost {
+    /// Wall-clock time of the run.
+    pub seconds: f64,
+    /// CPU time of the process (user and system, all threads), if known.
+    pub cpu_seconds: Option<f64>,
+    /// Energy drawn by the CPU packages over the run, if their RAPL
+    /// counters could be read. It includes that of other processes.
+    pub joules: Option<f64>,
+    /// Pixels solved (with their margins and padding) times the
+    /// iterations they were solved for, in millions.
+    pub megapixel_iterations: f64,
+}
+
+/// Readings at the start of a run, to measure the cost from.
+pub struct Meter {
+    started: Instant,
+    cpu_seconds: Option<f64>,
+    counters: Vec<Counter>,
+}
+
+/// The energy counter of a RAPL package.
+struct Counter {
+    path: PathBuf,
+    /// Value the counter wraps around at, in microjoules.
+    range: u64,
+    started: u64,
+}
+
+impl Meter {
+    pub fn start() -> Self {
+        let counters = counters().unwrap_or_default();
+        Self {
+            started: Instant::now(),
+            cpu_seconds: cpu_seconds(),
+            counters,
+        }
+    }
+
+    /// The cost of the run so far, which solved `megapixel_iterations`.
+    pub fn read(&self, megapixel_iterations: f64) -> Cost {
+        let joules = self
+            .counters
+            .iter()
+            .map(|counter| {
+                let now = read_u64(&counter.path.join("energy_uj"))?;
+                // wrapped around at most once, in a run of reasonable length
+                let used = match now >= counter.started {
+                    true => now - counter.started,
+                    false => counter.range - counter.started + now,
+                };
+                Some(used as f64 / 1e6)
+            })
+            .sum::<Option<f64>>()
+            .filter(|_| !self.counters.is_empty());
+        Cost {
+            seconds: self.started.elapsed().as_secs_f64(),
+            cpu_seconds: cpu_seconds()
+                .zip(self.cpu_seconds)
+                .map(|(now, started)| now - started),
+            joules,
+            megapixel_iterations,
+        }
+    }
+}
+
+/// The counters of every CPU package (`intel-rapl:N`, also used by AMD),
+/// leaving out their subdomains (`intel-rapl:N:M`) which they include.
+/// `None` if any cannot be read, as is usual without root since the
+/// counters can reveal what other processes compute.
+fn counters() -> Option<Vec<Counter>> {
+    let mut counters = Vec::new();
+    for entry in std::fs::read_dir(POWERCAP).ok()? {
+        let path = entry.ok()?.path();
+        let is_package = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("intel-rapl:"))
+            .is_some_and(|domain| !domain.contains(':'));
+        if !is_package {
+            continue;
+        }
+        counters.push(Counter {
+            range: read_u64(&path.join("max_energy_range_uj"))?,
+            started: read_u64(&path.join("energy_uj"))?,
+            path,
+        });
+    }
+    Some(counters)
+}
+
+fn read_u64(path: &std::path::Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// CPU time used by the process so far, user and system.
+#[cfg(unix)]
+fn cpu_seconds() -> Option<f64> {
+    // SAFETY: `usage` is plain data that getrusage fills in
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let seconds =
+        |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1e6;
+    Some(seconds(usage.ru_utime) + seconds(usage.ru_stime))
+}
+
+#[cfg(not(unix))]
+fn cpu_seconds() -> Option<f64> {
+    None
+}
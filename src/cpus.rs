@@ -0,0 +1,179 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The number of CPUs the program may use, taking the CPU quotas of
+//! cgroups (as set for containers, e.g. by Kubernetes CPU limits) into
+//! account on Linux, and how the work of a sweep is spread over them.
+
+use std::{
+    num::NonZeroUsize,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::OnceLock,
+    thread,
+};
+
+use crate::{
+    crossover,
+    DenoiseArgs,
+};
+
+/// The available parallelism, capped by the CPU quotas of the cgroups of
+/// the process, measured on the first call.
+pub fn available() -> Result<NonZeroUsize, String> {
+    static AVAILABLE: OnceLock<Result<NonZeroUsize, String>> = OnceLock::new();
+    AVAILABLE
+        .get_or_init(|| {
+            let parallelism = thread::available_parallelism()
+                .map_err(|error| error.to_string())?;
+            match cgroup_quota() {
+                Some(quota) => {
+                    let cpus = NonZeroUsize::new(quota.ceil() as usize)
+                        .unwrap_or(NonZeroUsize::MIN);
+                    log::info!(
+                        "cgroup CPU quota of {:.2} CPUs, using {} of {}",
+                        quota,
+                        cpus.min(parallelism),
+                        parallelism
+                    );
+                    Ok(cpus.min(parallelism))
+                },
+                None => {
+                    log::debug!("no cgroup CPU quota found");
+                    Ok(parallelism)
+                },
+            }
+        })
+        .clone()
+}
+
+/// How the λ values of a sweep are spread over threads.
+pub enum Plan {
+    /// The image is too small (under `crossover` pixels) for threads to
+    /// pay off.
+    Sequential { crossover: u64 },
+    /// The available parallelism could not be found out.
+    Unavailable(String),
+    Parallel {
+        available: NonZeroUsize,
+        /// λ values solved at the same time.
+        jobs: usize,
+        /// Threads each of them is solved with.
+        threads: usize,
+    },
+}
+
+pub fn plan(args: &DenoiseArgs, lambdas: usize, pixels: u64) -> Plan {
+    // small images are solved faster without spawning any threads
+    let crossover = args
+        .sequential_below
+        .map_or_else(crossover::pixels, |pixels| pixels.get());
+    if pixels < crossover {
+        return Plan::Sequential { crossover };
+    }
+    let num = match available() {
+        Ok(num) => num,
+        Err(message) => return Plan::Unavailable(message),
+    };
+    let cores = std::cmp::min(num, args.max_parallelism).get();
+    let jobs = match (args.jobs, args.threads_per_task) {
+        (Some(jobs), _) => jobs.get(),
+        (None, Some(threads)) => (cores / threads.get()).max(1),
+        (None, None) => cores,
+    };
+    let jobs = jobs.min(lambdas).max(1);
+    // cores left over by the lambda values solved at the same time (e.g.
+    // when there is only one) go to the channels of each
+    let threads = args
+        .threads_per_task
+        .map_or((cores / jobs).max(1), |threads| threads.get());
+    Plan::Parallel {
+        available: num,
+        jobs,
+        threads,
+    }
+}
+
+/// The smallest CPU quota (in CPUs) of the cgroups the process is in and
+/// their ancestors, with cgroup v2 or v1, if any is set.
+fn cgroup_quota() -> Option<f64> {
+    let memberships = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    memberships
+        .lines()
+        .filter_map(|line| {
+            // `hierarchy-ID:controllers:path`
+            let mut fields = line.splitn(3, ':');
+            let (_, controllers, path) =
+                (fields.next()?, fields.next()?, fields.next()?);
+            let relative = path.trim_start_matches('/');
+            if controllers.is_empty() {
+                quota_along(Path::new("/sys/fs/cgroup"), relative, v2_quota)
+            } else if controllers.split(',').any(|name| name == "cpu") {
+                ["/sys/fs/cgroup/cpu", "/sys/fs/cgroup/cpu,cpuacct"]
+                    .iter()
+                    .find_map(|root| {
+                        quota_along(Path::new(root), relative, v1_quota)
+                    })
+            } else {
+                None
+            }
+        })
+        .min_by(f64::total_cmp)
+}
+
+/// The smallest quota of the cgroup at `relative` under `root` and of its
+/// ancestors up to the root (which is all there is inside containers that
+/// only see their own cgroup, mounted at the root).
+fn quota_along(
+    root: &Path,
+    relative: &str,
+    quota: fn(&Path) -> Option<f64>,
+) -> Option<f64> {
+    let mut directory = root.join(relative);
+    let mut quotas = Vec::new();
+    loop {
+        quotas.extend(quota(&directory));
+        if directory == root || !directory.pop() {
+            break;
+        }
+    }
+    quotas.into_iter().min_by(f64::total_cmp)
+}
+
+/// From `cpu.max`, holding `max` or the quota and the period.
+fn v2_quota(directory: &Path) -> Option<f64> {
+    let contents = read(directory.join("cpu.max"))?;
+    let mut fields = contents.split_whitespace();
+    let quota = fields.next()?.parse::<f64>().ok()?;
+    let period = fields.next()?.parse::<f64>().ok()?;
+    (quota > 0.0 && period > 0.0).then_some(quota / period)
+}
+
+/// From `cpu.cfs_quota_us` (`-1` if unlimited) and `cpu.cfs_period_us`.
+fn v1_quota(directory: &Path) -> Option<f64> {
+    let quota = read(directory.join("cpu.cfs_quota_us"))?
+        .parse::<f64>()
+        .ok()?;
+    let period = read(directory.join("cpu.cfs_period_us"))?
+        .parse::<f64>()
+        .ok()?;
+    (quota > 0.0 && period > 0.0).then_some(quota / period)
+}
+
+fn read(path: PathBuf) -> Option<String> {
+    Some(std::fs::read_to_string(path).ok()?.trim().to_string())
+}
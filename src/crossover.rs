@@ -0,0 +1,108 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Measurement of the image size below which threads cost more than they
+//! save, so that small images are solved without spawning any.
+
+use std::{
+    sync::OnceLock,
+    thread,
+    time::Instant,
+};
+
+use image_recovery::ndarray::Array3;
+
+use crate::{
+    cancel,
+    solver,
+};
+
+/// Side in pixels of the image the cost of an iteration is measured on.
+const PROBE_SIZE: usize = 64;
+const PROBE_ITERATIONS: u32 = 10;
+const PROBE_SPAWNS: u32 = 16;
+/// How many times the cost of spawning its threads an iteration of a
+/// channel must cost for the threads to pay off, as they also contend for
+/// caches and memory bandwidth.
+const MARGIN: f64 = 4.0;
+
+/// The number of pixels below which an image is solved faster without
+/// threads, measured on the first call: spawning threads pays off once an
+/// iteration of a channel costs (with some margin) more than spawning
+/// the threads to split it, below which the iterations are too short to
+/// split any further across λ values either.
+pub fn pixels() -> u64 {
+    static PIXELS: OnceLock<u64> = OnceLock::new();
+    *PIXELS.get_or_init(|| {
+        let spawn = spawn_seconds();
+        let pixel_iteration = pixel_iteration_seconds();
+        let pixels = (MARGIN * spawn / pixel_iteration).ceil() as u64;
+        log::debug!(
+            "thread spawn: {:e} s, pixel iteration: {:e} s, solving \
+             sequentially below {} pixels",
+            spawn,
+            pixel_iteration,
+            pixels
+        );
+        pixels
+    })
+}
+
+/// Average time taken to spawn and join a scoped thread.
+fn spawn_seconds() -> f64 {
+    let started = Instant::now();
+    for _ in 0..PROBE_SPAWNS {
+        thread::scope(|scope| {
+            scope.spawn(|| ());
+        });
+    }
+    started.elapsed().as_secs_f64() / PROBE_SPAWNS as f64
+}
+
+/// Average time taken by an iteration of the solver per pixel of a single
+/// channel.
+fn pixel_iteration_seconds() -> f64 {
+    let image =
+        Array3::from_shape_fn((PROBE_SIZE, PROBE_SIZE, 1), |(x, y, _)| {
+            ((x * 7 + y * 13) % 32) as f64 * 8.0
+        });
+    let parameters = solver::Parameters {
+        lambda: 0.05,
+        tau: solver::tau(),
+        sigma: solver::sigma(),
+        gamma: solver::gamma(0.05),
+        max_iter: PROBE_ITERATIONS,
+        convergence_threshold: 0.0,
+        acceleration: solver::Acceleration::Gamma,
+        channel_copies: 1,
+        threads: 1,
+        precision: solver::Precision::F64,
+        data_weight: None,
+        data_band: None,
+        stop_on_gap: None,
+        keep_dual: false,
+    };
+    let started = Instant::now();
+    solver::denoise(
+        &image,
+        &parameters,
+        &cancel::Token::new(None),
+        &mut |_| {},
+    )
+    .expect("the probe is large enough");
+    started.elapsed().as_secs_f64()
+        / (PROBE_SIZE * PROBE_SIZE) as f64
+        / PROBE_ITERATIONS as f64
+}
@@ -0,0 +1,283 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Datasets of aligned (noisy, denoised, clean) triples made from clean
+//! images, for training and evaluating learned denoisers against the
+//! denoising of this program as a baseline.
+//!
+//! Each clean image is saved as an 8-bit PNG, and for each standard
+//! deviation of noise, as many noisy copies of it as asked for, each
+//! with noise of its own, next to their denoised results. An
+//! `index.json` lists all of the triples with the settings they were made
+//! with, so that the dataset can be made again identically.
+
+use std::{
+    ffi::OsStr,
+    path::{
+        Path,
+        PathBuf,
+    },
+    thread,
+};
+
+use image_recovery::{
+    image::{
+        DynamicImage,
+        ImageResult,
+    },
+    ndarray::Array3,
+    ImageArray,
+};
+use serde::Serialize;
+
+use crate::{
+    analysis,
+    cancel,
+    cpus,
+    input,
+    random::Random,
+    solver,
+    storage,
+};
+
+/// Name of the index of the triples, in the folder of the dataset.
+pub const INDEX: &str = "index.json";
+
+/// How the noisy copies are made and denoised.
+#[derive(Serialize, Debug, Clone)]
+pub struct Settings {
+    /// Standard deviations of the gaussian noise, in 8-bit levels.
+    pub noise_sigmas: Vec<f64>,
+    /// Noisy copies of each clean image per standard deviation.
+    pub copies: u32,
+    pub lambda: f64,
+    pub max_iter: u32,
+    pub convergence_threshold: f64,
+    /// Seed of the noise of the first triple; each of the others follows
+    /// in order.
+    pub seed: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct Index<'a> {
+    settings: &'a Settings,
+    triples: Vec<Triple>,
+}
+
+/// A noisy copy of a clean image and its denoised result, with the paths
+/// relative to the folder of the dataset.
+#[derive(Serialize, Debug)]
+pub struct Triple {
+    pub source: PathBuf,
+    pub clean: PathBuf,
+    pub noisy: PathBuf,
+    pub denoised: PathBuf,
+    pub noise_sigma: f64,
+    pub seed: u64,
+    pub iterations: u32,
+    pub converged: bool,
+    /// In dB, relative to the clean image.
+    pub noisy_psnr: f64,
+    pub denoised_psnr: f64,
+    pub noisy_ssim: f64,
+    pub denoised_ssim: f64,
+}
+
+/// Makes the triples of each of the `sources` in `folder`, as many at a
+/// time as the available parallelism allows, and saves their index.
+/// Returns whether all of them were made.
+pub fn make(
+    sources: &[PathBuf],
+    folder: &Path,
+    settings: &Settings,
+    cancel: &cancel::Token,
+) -> bool {
+    let jobs = cpus::available().map_or(1, |available| available.get());
+    let mut triples = Vec::new();
+    let mut failed = false;
+    let mut seed = settings.seed;
+    for source in sources {
+        let stem = source.file_stem().map_or_else(
+            || "image".into(),
+            |stem: &OsStr| stem.to_string_lossy(),
+        );
+        let clean = match input::load(source) {
+            Ok((clean, _)) => quantize(&clean),
+            Err(error) => {
+                log::error!(
+                    "{} could not be open: {}",
+                    source.to_string_lossy(),
+                    error
+                );
+                failed = true;
+                continue;
+            },
+        };
+        let clean_name = PathBuf::from(format!("{}_clean.png", stem));
+        if let Err(error) = save(&clean, &folder.join(&clean_name)) {
+            log::error!(
+                "{} could not be saved: {}",
+                clean_name.display(),
+                error
+            );
+            failed = true;
+            continue;
+        }
+
+        let mut jobs_of_source = Vec::new();
+        for &noise_sigma in &settings.noise_sigmas {
+            for copy in 0..settings.copies {
+                let name = format!("{}_sigma_{}_{}", stem, noise_sigma, copy);
+                jobs_of_source.push((name, noise_sigma, seed));
+                seed = seed.wrapping_add(1);
+            }
+        }
+        for chunk in jobs_of_source.chunks(jobs) {
+            let results = thread::scope(|scope| {
+                let handles: Vec<_> = chunk
+                    .iter()
+                    .map(|(name, noise_sigma, seed)| {
+                        let (clean, clean_name) = (&clean, &clean_name);
+                        scope.spawn(move || {
+                            let noisy = quantize(&add_noise(
+                                clean,
+                                *noise_sigma,
+                                *seed,
+                            ));
+                            let solution = denoise(&noisy, settings, cancel)?;
+                            let denoised = quantize(&solution.image);
+                            let noisy_name =
+                                PathBuf::from(format!("{}_noisy.png", name));
+                            let denoised_name =
+                                PathBuf::from(format!("{}_denoised.png", name));
+                            for (array, name) in [
+                                (&noisy, &noisy_name),
+                                (&denoised, &denoised_name),
+                            ] {
+                                save(array, &folder.join(name)).map_err(
+                                    |error| {
+                                        format!(
+                                            "{} could not be saved: {}",
+                                            name.display(),
+                                            error
+                                        )
+                                    },
+                                )?;
+                            }
+                            Ok::<_, String>(Triple {
+                                source: source.clone(),
+                                clean: clean_name.clone(),
+                                noisy: noisy_name,
+                                denoised: denoised_name,
+                                noise_sigma: *noise_sigma,
+                                seed: *seed,
+                                iterations: solution.iterations(),
+                                converged: solution.converged,
+                                noisy_psnr: analysis::psnr(clean, &noisy),
+                                denoised_psnr: analysis::psnr(clean, &denoised),
+                                noisy_ssim: analysis::ssim(clean, &noisy),
+                                denoised_ssim: analysis::ssim(clean, &denoised),
+                            })
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join())
+                    .collect::<Vec<_>>()
+            });
+            for (result, (name, _, _)) in results.into_iter().zip(chunk) {
+                match result {
+                    Ok(Ok(triple)) => {
+                        log::info!("made {}", name);
+                        triples.push(triple);
+                    },
+                    Ok(Err(error)) => {
+                        log::error!("{} could not be made: {}", name, error);
+                        failed = true;
+                    },
+                    Err(_) => {
+                        log::error!("{} could not be made: panicked", name);
+                        failed = true;
+                    },
+                }
+            }
+        }
+    }
+
+    let index = Index { settings, triples };
+    let path = folder.join(INDEX);
+    match storage::save_json(storage::get(), &path, &index) {
+        Ok(()) => {
+            log::info!(
+                "{} triples listed in {}",
+                index.triples.len(),
+                path.to_string_lossy()
+            );
+            !failed
+        },
+        Err(error) => {
+            log::error!(
+                "{} could not be saved: {}",
+                path.to_string_lossy(),
+                error
+            );
+            false
+        },
+    }
+}
+
+/// Adds gaussian noise from `seed`, clamped to the 8-bit range.
+fn add_noise(array: &Array3<f64>, std_dev: f64, seed: u64) -> Array3<f64> {
+    let mut random = Random::new(seed);
+    array.mapv(|value| (value + std_dev * random.gaussian()).clamp(0.0, 255.0))
+}
+
+/// Rounds to the 8-bit levels the images are saved with, so that each
+/// array is the same as its image, and the noisy copies are denoised as
+/// they are saved.
+fn quantize(array: &Array3<f64>) -> Array3<f64> {
+    array.mapv(|value| value.round().clamp(0.0, 255.0))
+}
+
+fn denoise(
+    noisy: &Array3<f64>,
+    settings: &Settings,
+    cancel: &cancel::Token,
+) -> Result<solver::Solution, String> {
+    let parameters = solver::Parameters {
+        lambda: settings.lambda,
+        tau: solver::tau(),
+        sigma: solver::sigma(),
+        gamma: solver::gamma(settings.lambda),
+        max_iter: settings.max_iter,
+        convergence_threshold: settings.convergence_threshold,
+        acceleration: solver::Acceleration::Gamma,
+        channel_copies: 1,
+        threads: 1,
+        precision: solver::Precision::F64,
+        data_weight: None,
+        data_band: None,
+        stop_on_gap: None,
+        keep_dual: false,
+    };
+    solver::denoise(noisy, &parameters, cancel, &mut |_| {})
+        .map_err(|error| error.to_string())
+}
+
+fn save(array: &Array3<f64>, path: &Path) -> ImageResult<()> {
+    let img = DynamicImage::from(ImageArray::from(array).into_rgb());
+    storage::save_image(storage::get(), &img, path)
+}
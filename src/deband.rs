@@ -0,0 +1,34 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Debanding of smooth gradients (e.g. skies and vignettes of heavily
+//! compressed JPEGs), whose steps the data term would otherwise hold on
+//! to: the solver keeps each sample within half a step of the input, so
+//! that the smoothing fills in the steps without moving past them, and the
+//! results are dithered on their way to integer samples, so that they do
+//! not band again.
+
+use image_recovery::ndarray::Array3;
+
+use crate::random::Random;
+
+/// `array` with triangular noise of up to a level added to every sample,
+/// which breaks the steps of quantization into fine grain; seeded, so that
+/// the same output is dithered the same way every time.
+pub fn dither(array: &Array3<f64>, seed: u64) -> Array3<f64> {
+    let mut random = Random::new(seed);
+    array
+        .mapv(|value| value + random.range(-0.5, 0.5) + random.range(-0.5, 0.5))
+}
@@ -0,0 +1,94 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Reduction of the blocking of JPEG inputs, whose 8×8 blocks are coded
+//! apart and meet in steps that the data term would otherwise hold on to
+//! as if they were structure of the scene.
+
+use image_recovery::ndarray::{
+    Array3,
+    ArrayViewMut1,
+    Axis,
+};
+
+/// Side of the blocks JPEG codes apart, in pixels.
+const BLOCK: usize = 8;
+
+/// How much larger the steps across block boundaries are than the steps
+/// within blocks, on average: about 1 for images with no blocking.
+pub fn blockiness(array: &Array3<f64>) -> f64 {
+    let (mut across, mut across_count) = (0.0, 0);
+    let (mut within, mut within_count) = (0.0, 0);
+    for axis in [0, 1] {
+        for lane in array.lanes(Axis(axis)) {
+            for i in 1..lane.len() {
+                let step = (lane[i] - lane[i - 1]).abs();
+                match i % BLOCK {
+                    0 => {
+                        across += step;
+                        across_count += 1;
+                    },
+                    _ => {
+                        within += step;
+                        within_count += 1;
+                    },
+                }
+            }
+        }
+    }
+    match (across_count, within_count) {
+        (0, _) | (_, 0) => 1.0,
+        _ => {
+            let within = within / within_count as f64;
+            let across = across / across_count as f64;
+            across / within.max(f64::EPSILON)
+        },
+    }
+}
+
+/// `array` with the steps across block boundaries smoothed into the two
+/// pixels on either side, unless they are over `strength` (in 8-bit
+/// levels), which is taken as an edge of the scene happening to fall on a
+/// boundary.
+pub fn deblock(array: &Array3<f64>, strength: f64) -> Array3<f64> {
+    let mut deblocked = array.clone();
+    for axis in [0, 1] {
+        for lane in deblocked.lanes_mut(Axis(axis)) {
+            smooth_boundaries(lane, strength);
+        }
+    }
+    deblocked
+}
+
+/// Spreads the step across each block boundary of a line of samples over
+/// the two pixels on either side, as a ramp, keeping the gradients the
+/// pixels already had within their blocks.
+fn smooth_boundaries(mut lane: ArrayViewMut1<f64>, strength: f64) {
+    let len = lane.len();
+    for boundary in (BLOCK..len.saturating_sub(1)).step_by(BLOCK) {
+        let (p1, p0) = (lane[boundary - 2], lane[boundary - 1]);
+        let (q0, q1) = (lane[boundary], lane[boundary + 1]);
+        if (q0 - p0).abs() > strength {
+            continue;
+        }
+        // the step left once the gradient either side is accounted for
+        let gradient = ((p0 - p1) + (q1 - q0)) / 2.0;
+        let delta = q0 - p0 - gradient;
+        lane[boundary - 2] = p1 + delta / 8.0;
+        lane[boundary - 1] = p0 + delta * 3.0 / 8.0;
+        lane[boundary] = q0 - delta * 3.0 / 8.0;
+        lane[boundary + 1] = q1 - delta / 8.0;
+    }
+}
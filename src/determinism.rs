@@ -0,0 +1,76 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Checks that a solve gives bit-identical results however its work is
+//! scheduled. The solver splits the channels of an image across threads
+//! but combines their results in channel order, and draws nothing at
+//! random, so the number of threads (or of λ values solved at once) should
+//! never change a result.
+
+use image_recovery::ndarray::{
+    Array3,
+    Zip,
+};
+
+/// How two results of the same solve differ.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mismatch {
+    pub differing: usize,
+    pub max_difference: f64,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} samples differ, by up to {:e}",
+            self.differing, self.max_difference
+        )
+    }
+}
+
+/// Threads to solve again with, so that the channels are scheduled
+/// differently than on `threads`: on one if there were more, or on one per
+/// channel otherwise.
+pub fn other_threads(threads: usize, channels: usize) -> usize {
+    match threads {
+        0 | 1 => channels.max(2),
+        _ => 1,
+    }
+}
+
+/// How `a` and `b` differ, comparing the bits of each sample (so that NaNs
+/// of a different kind, or zeros of a different sign, differ too), or
+/// `None` if they are identical.
+pub fn compare(a: &Array3<f64>, b: &Array3<f64>) -> Option<Mismatch> {
+    if a.dim() != b.dim() {
+        return Some(Mismatch {
+            differing: a.len().max(b.len()),
+            max_difference: f64::INFINITY,
+        });
+    }
+    let mut mismatch = Mismatch {
+        differing: 0,
+        max_difference: 0.0,
+    };
+    Zip::from(a).and(b).for_each(|&a, &b| {
+        if a.to_bits() != b.to_bits() {
+            mismatch.differing += 1;
+            mismatch.max_difference =
+                mismatch.max_difference.max((a - b).abs());
+        }
+    });
+    (mismatch.differing > 0).then_some(mismatch)
+}
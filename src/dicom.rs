@@ -0,0 +1,615 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Single-frame DICOM images, as exported by scanners for research, decoded
+//! without a conversion pipeline: their stored values are rescaled to
+//! modality units (e.g. Hounsfield units) and mapped by a window onto the
+//! 16-bit range, which the rest of the pipeline reads as any other image.
+//!
+//! Only uncompressed little endian transfer syntaxes are read, and only the
+//! technical attributes of the image are kept, never those of the patient.
+
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+    sync::RwLock,
+};
+
+use image_recovery::image::{
+    error::{
+        DecodingError,
+        ImageFormatHint,
+        LimitError,
+        LimitErrorKind,
+    },
+    DynamicImage,
+    ImageBuffer,
+    ImageError,
+    ImageResult,
+    Luma,
+    Rgb,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::storage;
+
+const IMPLICIT_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
+const EXPLICIT_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1";
+
+/// Whether `bytes` start like a DICOM file (a 128 byte preamble, then
+/// `DICM`).
+pub fn is_dicom(bytes: &[u8]) -> bool {
+    bytes.get(128..132) == Some(b"DICM")
+}
+
+/// Range of modality values mapped onto the darkest and brightest levels.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Window {
+    /// All the values the samples can store, so nothing is clipped.
+    Stored,
+    /// The window saved in the file, as a viewer would show it.
+    File,
+    Given {
+        center: f64,
+        width: f64,
+    },
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stored" => return Ok(Window::Stored),
+            "file" => return Ok(Window::File),
+            _ => (),
+        }
+        let invalid = || {
+            format!(
+                "invalid window `{}`: expected `stored`, `file` or \
+                 `center,width`",
+                s
+            )
+        };
+        let (center, width) = s.split_once(',').ok_or_else(invalid)?;
+        let (center, width) =
+            match (center.trim().parse(), width.trim().parse()) {
+                (Ok(center), Ok(width)) => (center, width),
+                _ => return Err(invalid()),
+            };
+        match width >= 1.0 {
+            true => Ok(Window::Given { center, width }),
+            false => {
+                Err(format!("invalid window `{}`: width must be at least 1", s))
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Window::Stored => write!(f, "stored"),
+            Window::File => write!(f, "file"),
+            Window::Given { center, width } => {
+                write!(f, "{},{}", center, width)
+            },
+        }
+    }
+}
+
+impl Window {
+    /// Lowest and highest modality values of the window, by the linear
+    /// function of the standard (PS3.3 C.11.2.1.2).
+    fn range(&self, metadata: &Metadata) -> Result<(f64, f64), String> {
+        let given = |center: f64, width: f64| {
+            (
+                center - 0.5 - (width - 1.0) / 2.0,
+                center - 0.5 + (width - 1.0) / 2.0,
+            )
+        };
+        if !metadata.is_monochrome() && *self != Window::Stored {
+            return Err(format!(
+                "only monochrome images can be windowed, not {}",
+                metadata.photometric_interpretation
+            ));
+        }
+        match self {
+            Window::Stored => {
+                let (low, high) = metadata.stored_range();
+                let rescale = |value: f64| match metadata.is_monochrome() {
+                    true => {
+                        value * metadata.rescale_slope
+                            + metadata.rescale_intercept
+                    },
+                    false => value,
+                };
+                let (low, high) = (rescale(low), rescale(high));
+                Ok((low.min(high), low.max(high)))
+            },
+            Window::File => {
+                match (metadata.window_center, metadata.window_width) {
+                    (Some(center), Some(width)) if width >= 1.0 => {
+                        Ok(given(center, width))
+                    },
+                    _ => Err("the file has no window of its own".to_string()),
+                }
+            },
+            &Window::Given { center, width } => Ok(given(center, width)),
+        }
+    }
+}
+
+/// Format the denoised DICOM images are saved in.
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Png,
+    Tiff,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Tiff => "tif",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    pub window: Window,
+    pub format: Format,
+}
+
+impl Options {
+    pub const DEFAULT: Options = Options {
+        window: Window::Stored,
+        format: Format::Png,
+    };
+}
+
+static OPTIONS: RwLock<Options> = RwLock::new(Options::DEFAULT);
+
+/// Sets how every DICOM image decoded from now on is mapped and saved.
+pub fn set_options(options: Options) {
+    *OPTIONS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = options;
+}
+
+/// The options set with `set_options`.
+pub fn options() -> Options {
+    *OPTIONS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The attributes of the image needed to decode it and to map the
+/// denoised levels back to modality values.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub transfer_syntax: String,
+    pub modality: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u16,
+    pub photometric_interpretation: String,
+    pub bits_allocated: u16,
+    pub bits_stored: u16,
+    /// Whether the stored values are two's complement.
+    pub signed: bool,
+    pub rescale_slope: f64,
+    pub rescale_intercept: f64,
+    pub window_center: Option<f64>,
+    pub window_width: Option<f64>,
+}
+
+impl Metadata {
+    fn is_monochrome(&self) -> bool {
+        self.photometric_interpretation.starts_with("MONOCHROME")
+    }
+
+    /// Lowest and highest values the samples can store.
+    fn stored_range(&self) -> (f64, f64) {
+        let levels = 1_i64 << self.bits_stored;
+        match self.signed {
+            true => (-(levels / 2) as f64, (levels / 2 - 1) as f64),
+            false => (0.0, (levels - 1) as f64),
+        }
+    }
+}
+
+/// What is saved next to every output of a DICOM input, as JSON, for the
+/// levels of the output to be mapped back to modality values: a level `l`
+/// of an 8-bit output stands for `low + l / 255 * (high - low)` (or
+/// `high - l / 255 * (high - low)` if `inverted`).
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Sidecar {
+    pub metadata: Metadata,
+    pub window: Window,
+    pub low: f64,
+    pub high: f64,
+    /// Whether the lowest values are the brightest (`MONOCHROME1`).
+    pub inverted: bool,
+}
+
+impl Sidecar {
+    pub fn new(bytes: &[u8], window: Window) -> ImageResult<Self> {
+        let dataset = parse(bytes).map_err(error)?;
+        let (low, high) = window.range(&dataset.metadata).map_err(error)?;
+        Ok(Self {
+            inverted: dataset.metadata.photometric_interpretation
+                == "MONOCHROME1",
+            metadata: dataset.metadata,
+            window,
+            low,
+            high,
+        })
+    }
+}
+
+/// Path of the DICOM sidecar of an output, e.g. `result.png.dicom.json`.
+pub fn sidecar_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".dicom.json");
+    PathBuf::from(path)
+}
+
+/// Width and height of the image in `bytes`.
+pub fn dimensions(bytes: &[u8]) -> ImageResult<(u32, u32)> {
+    let metadata = parse(bytes).map_err(error)?.metadata;
+    Ok((metadata.width, metadata.height))
+}
+
+/// Decodes the image in `bytes`, mapping its values by `window` onto 16-bit
+/// gray (or RGB, for color images, which are not rescaled).
+pub fn decode(bytes: &[u8], window: Window) -> ImageResult<DynamicImage> {
+    let dataset = parse(bytes).map_err(error)?;
+    let metadata = &dataset.metadata;
+    let (width, height) = (metadata.width, metadata.height);
+    let limits = storage::limits();
+    let channels = metadata.samples_per_pixel as u64;
+    if !limits.allows(width, height)
+        || width as u64 * height as u64 * channels * 2
+            > limits.max_decoded_bytes
+    {
+        return Err(ImageError::Limits(LimitError::from_kind(
+            LimitErrorKind::DimensionError,
+        )));
+    }
+    let (low, high) = window.range(metadata).map_err(error)?;
+    let inverted = metadata.photometric_interpretation == "MONOCHROME1";
+
+    let pixels = width as usize * height as usize;
+    let sample_bytes = metadata.bits_allocated as usize / 8;
+    let data = &bytes[dataset.pixel_data];
+    if data.len() < pixels * channels as usize * sample_bytes {
+        return Err(error("pixel data is shorter than the image".to_string()));
+    }
+    let stored_bits = metadata.bits_stored as u32;
+    let stored = |index: usize| -> f64 {
+        let at = index * sample_bytes;
+        let raw = match sample_bytes {
+            1 => data[at] as u32,
+            _ => u16::from_le_bytes([data[at], data[at + 1]]) as u32,
+        };
+        let raw = raw & ((1 << stored_bits) - 1);
+        match metadata.signed && raw >> (stored_bits - 1) == 1 {
+            true => raw as f64 - (1_u64 << stored_bits) as f64,
+            false => raw as f64,
+        }
+    };
+    let level = |value: f64| -> u16 {
+        let level = ((value - low) / (high - low)).clamp(0.0, 1.0);
+        let level = if inverted { 1.0 - level } else { level };
+        (level * u16::MAX as f64).round() as u16
+    };
+
+    let img = if metadata.is_monochrome() {
+        let buffer = ImageBuffer::from_fn(width, height, |x, y| {
+            let value = stored(y as usize * width as usize + x as usize);
+            Luma([level(
+                value * metadata.rescale_slope + metadata.rescale_intercept,
+            )])
+        });
+        DynamicImage::ImageLuma16(buffer)
+    } else {
+        let buffer = ImageBuffer::from_fn(width, height, |x, y| {
+            let pixel = y as usize * width as usize + x as usize;
+            let sample = |channel: usize| {
+                let index = match dataset.planar {
+                    true => channel * pixels + pixel,
+                    false => pixel * 3 + channel,
+                };
+                level(stored(index))
+            };
+            Rgb([sample(0), sample(1), sample(2)])
+        });
+        DynamicImage::ImageRgb16(buffer)
+    };
+    Ok(img)
+}
+
+fn error(message: String) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("DICOM".to_string()),
+        message,
+    ))
+}
+
+/// The attributes of a file, and where its pixel data is.
+struct Dataset {
+    metadata: Metadata,
+    /// Whether color samples are stored a plane per channel.
+    planar: bool,
+    pixel_data: std::ops::Range<usize>,
+}
+
+/// Value representations whose length is stored in 4 bytes (after 2
+/// reserved ones) in explicit VR syntaxes.
+const LONG_VRS: [&[u8; 2]; 13] = [
+    b"OB", b"OD", b"OF", b"OL", b"OV", b"OW", b"SQ", b"SV", b"UC", b"UN",
+    b"UR", b"UT", b"UV",
+];
+
+const UNDEFINED_LENGTH: u32 = u32::MAX;
+const ITEM: (u16, u16) = (0xfffe, 0xe000);
+const ITEM_DELIMITATION: (u16, u16) = (0xfffe, 0xe00d);
+const SEQUENCE_DELIMITATION: (u16, u16) = (0xfffe, 0xe0dd);
+
+/// Cursor over the data elements of a file.
+struct Elements<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    explicit: bool,
+}
+
+/// A data element: its tag, and the range of its value (`None` for values
+/// of undefined length, which are skipped).
+struct Element {
+    tag: (u16, u16),
+    value: Option<std::ops::Range<usize>>,
+}
+
+impl Elements<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + count)
+            .ok_or_else(|| {
+                "file ends in the middle of an element".to_string()
+            })?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn next(&mut self) -> Result<Option<Element>, String> {
+        if self.position >= self.bytes.len() {
+            return Ok(None);
+        }
+        let tag = (self.u16()?, self.u16()?);
+        // items and delimiters have no VR in any syntax
+        let length = if self.explicit && tag.0 != 0xfffe {
+            let vr = self.take(2)?;
+            match LONG_VRS.iter().any(|long| long[..] == *vr) {
+                true => {
+                    self.take(2)?;
+                    self.u32()?
+                },
+                false => self.u16()? as u32,
+            }
+        } else {
+            self.u32()?
+        };
+        if length == UNDEFINED_LENGTH {
+            if tag != ITEM {
+                self.skip_sequence()?;
+            }
+            return Ok(Some(Element { tag, value: None }));
+        }
+        let start = self.position;
+        self.take(length as usize)?;
+        Ok(Some(Element {
+            tag,
+            value: Some(start..self.position),
+        }))
+    }
+
+    /// Skips the items of a sequence of undefined length, up to and
+    /// including its delimitation.
+    fn skip_sequence(&mut self) -> Result<(), String> {
+        loop {
+            let element = self
+                .next()?
+                .ok_or_else(|| "file ends in a sequence".to_string())?;
+            match element.tag {
+                SEQUENCE_DELIMITATION => return Ok(()),
+                // an item of undefined length, its elements follow
+                ITEM if element.value.is_none() => self.skip_item()?,
+                _ => (),
+            }
+        }
+    }
+
+    fn skip_item(&mut self) -> Result<(), String> {
+        loop {
+            let element = self
+                .next()?
+                .ok_or_else(|| "file ends in a sequence item".to_string())?;
+            if element.tag == ITEM_DELIMITATION {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn parse(bytes: &[u8]) -> Result<Dataset, String> {
+    if !is_dicom(bytes) {
+        return Err("not a DICOM file".to_string());
+    }
+    let text = |value: &[u8]| {
+        String::from_utf8_lossy(value)
+            .trim_matches(|c: char| c == ' ' || c == '\0')
+            .to_string()
+    };
+    // multiple values are separated by backslashes, the first one is used
+    let number = |value: &[u8]| -> Result<f64, String> {
+        let value = text(value);
+        let first = value.split('\\').next().unwrap_or_default().trim();
+        first
+            .parse()
+            .map_err(|_| format!("invalid number `{}`", value))
+    };
+    let short = |value: &[u8]| -> Result<u16, String> {
+        match value {
+            [low, high, ..] => Ok(u16::from_le_bytes([*low, *high])),
+            _ => Err("invalid unsigned short".to_string()),
+        }
+    };
+
+    // the file meta information is always explicit VR little endian
+    let mut elements = Elements {
+        bytes,
+        position: 132,
+        explicit: true,
+    };
+    let mut transfer_syntax = None;
+    while bytes.get(elements.position..elements.position + 2) == Some(&[2, 0]) {
+        let element = elements.next()?.expect("checked to be there");
+        if element.tag == (0x0002, 0x0010) {
+            transfer_syntax = element.value.map(|value| text(&bytes[value]));
+        }
+    }
+    let transfer_syntax =
+        transfer_syntax.ok_or_else(|| "no transfer syntax".to_string())?;
+    elements.explicit = match transfer_syntax.as_str() {
+        IMPLICIT_LITTLE_ENDIAN => false,
+        EXPLICIT_LITTLE_ENDIAN => true,
+        other => {
+            return Err(format!(
+                "transfer syntax {} is not supported, only uncompressed \
+                 little endian",
+                other
+            ))
+        },
+    };
+
+    let mut metadata = Metadata {
+        transfer_syntax,
+        modality: None,
+        width: 0,
+        height: 0,
+        samples_per_pixel: 1,
+        photometric_interpretation: "MONOCHROME2".to_string(),
+        bits_allocated: 16,
+        bits_stored: 0,
+        signed: false,
+        rescale_slope: 1.0,
+        rescale_intercept: 0.0,
+        window_center: None,
+        window_width: None,
+    };
+    let mut planar = false;
+    let mut pixel_data = None;
+    while let Some(element) = elements.next()? {
+        let value = &bytes[element.value.clone().unwrap_or_default()];
+        match element.tag {
+            (0x0008, 0x0060) => metadata.modality = Some(text(value)),
+            (0x0028, 0x0002) => metadata.samples_per_pixel = short(value)?,
+            (0x0028, 0x0004) => {
+                metadata.photometric_interpretation = text(value)
+            },
+            (0x0028, 0x0006) => planar = short(value)? == 1,
+            (0x0028, 0x0008) if number(value)? > 1.0 => {
+                return Err("only single-frame images are supported".to_string())
+            },
+            (0x0028, 0x0010) => metadata.height = short(value)? as u32,
+            (0x0028, 0x0011) => metadata.width = short(value)? as u32,
+            (0x0028, 0x0100) => metadata.bits_allocated = short(value)?,
+            (0x0028, 0x0101) => metadata.bits_stored = short(value)?,
+            (0x0028, 0x0103) => metadata.signed = short(value)? == 1,
+            (0x0028, 0x1050) => metadata.window_center = Some(number(value)?),
+            (0x0028, 0x1051) => metadata.window_width = Some(number(value)?),
+            (0x0028, 0x1052) => metadata.rescale_intercept = number(value)?,
+            (0x0028, 0x1053) => metadata.rescale_slope = number(value)?,
+            (0x7fe0, 0x0010) => {
+                pixel_data = Some(element.value);
+                break;
+            },
+            _ => (),
+        }
+    }
+
+    let pixel_data = match pixel_data {
+        Some(Some(pixel_data)) => pixel_data,
+        Some(None) => {
+            return Err("encapsulated pixel data is not supported".to_string())
+        },
+        None => return Err("no pixel data".to_string()),
+    };
+    if metadata.width == 0 || metadata.height == 0 {
+        return Err("image has no rows or columns".to_string());
+    }
+    if metadata.bits_allocated != 8 && metadata.bits_allocated != 16 {
+        return Err(format!(
+            "{} bits allocated per sample are not supported, only 8 or 16",
+            metadata.bits_allocated
+        ));
+    }
+    if metadata.bits_stored == 0
+        || metadata.bits_stored > metadata.bits_allocated
+    {
+        metadata.bits_stored = metadata.bits_allocated;
+    }
+    match (
+        metadata.photometric_interpretation.as_str(),
+        metadata.samples_per_pixel,
+    ) {
+        ("MONOCHROME1" | "MONOCHROME2", 1) | ("RGB", 3) => (),
+        (other, samples) => {
+            return Err(format!(
+                "photometric interpretation {} with {} samples per pixel is \
+                 not supported",
+                other, samples
+            ))
+        },
+    }
+    Ok(Dataset {
+        metadata,
+        planar,
+        pixel_data,
+    })
+}
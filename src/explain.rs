@@ -0,0 +1,253 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Plain language accounts of how the settings of a run were derived, for
+//! --explain: the formulas behind them and what was measured of the input
+//! and of the machine, to learn from or to debug a surprising choice.
+
+use crate::{
+    cpus,
+    region::Region,
+    solver,
+};
+
+/// Where the λ values of a run come from.
+pub enum Origin {
+    Sweep {
+        start: f64,
+        end: f64,
+        steps: usize,
+    },
+    /// Bisected by --discrepancy, as the results come in.
+    Discrepancy {
+        start: f64,
+        end: f64,
+        steps: usize,
+    },
+    /// Recorded for an output (by --like) or in a manifest.
+    Recorded,
+    /// Picked from a preview of the sweep, by --interactive.
+    Chosen,
+}
+
+pub fn lambdas(origin: &Origin, lambdas: &[f64]) -> String {
+    let list = lambdas
+        .iter()
+        .map(|lambda| format!("{:.10}", lambda))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match *origin {
+        Origin::Sweep {
+            start, steps: 1, ..
+        } => {
+            format!("λ = {} (-s), as -t 1 asks for a single value", start)
+        },
+        Origin::Sweep { start, end, steps } => format!(
+            "{} values spread geometrically from -s {} to -e {}, each q = (e \
+             / s)^(1 / (t - 1)) = {:.6} times the one before: {}",
+            steps,
+            start,
+            end,
+            (end / start).powf(1.0 / (steps - 1) as f64),
+            list
+        ),
+        Origin::Discrepancy { start, end, steps } => format!(
+            "bisected by --discrepancy on a log scale between -s {} and -e {} \
+             for at most -t {} solves, each taking the side on which the \
+             output differs from the input by as much as the noise, so the \
+             values follow from the results",
+            start, end, steps
+        ),
+        Origin::Recorded => format!(
+            "λ = {}, as recorded for an output (--like) or in a manifest",
+            list
+        ),
+        Origin::Chosen => format!("λ = {}, picked with --interactive", list),
+    }
+}
+
+pub fn steps(lambdas: &[f64], acceleration: solver::Acceleration) -> String {
+    let gammas = lambdas
+        .iter()
+        .map(|&lambda| format!("{:.6}", solver::gamma(lambda)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "τ = 1 / √2 = {:.6} and σ = 1 / (8 τ) = {:.6}, so that τ σ ‖∇‖² ≤ 1 \
+         for the gradient's ‖∇‖² ≤ 8, which the primal-dual algorithm of \
+         Chambolle and Pock (2011) needs to converge; γ = {} λ = {}, their \
+         choice for acceleration, {}",
+        solver::tau(),
+        solver::sigma(),
+        solver::GAMMA_PER_LAMBDA,
+        gammas,
+        match acceleration {
+            solver::Acceleration::Gamma => {
+                "with which the primal step shrinks and the dual one grows \
+                 every iteration (--acceleration gamma)"
+            },
+            solver::Acceleration::None => {
+                "not used as the steps are kept fixed (--acceleration none)"
+            },
+            solver::Acceleration::Adaptive => {
+                "not used as the steps are instead rebalanced whenever the \
+                 primal or the dual residual dominates (--acceleration \
+                 adaptive)"
+            },
+        }
+    )
+}
+
+/// How the solves stop: `delta` is the per pixel change of --stop-at-delta
+/// (as a fraction of the full range) if given, and `rms` the root mean
+/// square intensity of the input (in 8-bit levels), and `gap` the relative
+/// gap of --stop-on-gap, which replaces the threshold, if given; with
+/// `auto_iter`, `max_iter` only caps the iterations.
+pub fn stopping(
+    threshold: f64,
+    delta: Option<f64>,
+    gap: Option<f64>,
+    rms: f64,
+    max_iter: u32,
+    auto_iter: bool,
+) -> String {
+    let threshold = match delta {
+        Some(delta) => format!(
+            "a change of {:.3} levels (of 255) per pixel, by --stop-at-delta, \
+             divided by the root mean square intensity of the input ({:.3}) \
+             gives a relative threshold of {:.3e}",
+            delta * 255.0,
+            rms,
+            threshold
+        ),
+        None => format!("-c {:e} as given", threshold),
+    };
+    let iterations = match auto_iter {
+        true => format!(
+            "or after as many iterations as a probe of the first ones \
+             predicts from its rate of convergence, with a margin of 1.5 and \
+             up to {} (--auto-iter)",
+            max_iter
+        ),
+        false => format!("or after -m {} iterations", max_iter),
+    };
+    if let Some(gap) = gap {
+        return format!(
+            "every solve stops once the primal-dual gap, relative to the \
+             energy, is below {:e} (--stop-on-gap), i.e. once its energy is \
+             within that fraction of the smallest it could reach; {}",
+            gap, iterations
+        );
+    }
+    format!(
+        "every solve stops once an iteration changes the image by less than \
+         the threshold, relative to the image before it: {}; {}",
+        threshold, iterations
+    )
+}
+
+/// The noise of the input, `given` by --noise-sigma or measured (in `patch`
+/// if any).
+pub fn noise(noise: f64, given: bool, patch: Option<Region>) -> String {
+    let how = match (given, patch) {
+        (true, _) => "as given by --noise-sigma".to_string(),
+        (false, Some(patch)) => format!(
+            "measured as the standard deviation of the intensity in \
+             --noise-patch {}",
+            patch
+        ),
+        (false, None) => "estimated over the whole image with the method of \
+                          Immerkær (1996), a mask cancelling out first and \
+                          second order variations of the scene"
+            .to_string(),
+    };
+    format!(
+        "{:.3} levels (of 255), {}; it is what --discrepancy aims for, and \
+         what the noise left in each output is compared to",
+        noise, how
+    )
+}
+
+pub fn precision(precision: solver::Precision, auto: bool) -> String {
+    match auto {
+        true => format!(
+            "{}, chosen by --precision auto, as solving a downscaled copy of \
+             the input in both precisions gave results {} half a level apart",
+            precision,
+            match precision {
+                solver::Precision::F32 => "less than",
+                solver::Precision::F64 =>
+                    "more than (or a threshold too fine for f32 to reach)",
+            }
+        ),
+        false => format!("{}, as given by --precision", precision),
+    }
+}
+
+/// How the solves are spread over the CPUs, for an image of `pixels`
+/// pixels; `max_parallelism` is that of --max-parallelism, if any.
+pub fn threads(
+    plan: &cpus::Plan,
+    pixels: u64,
+    measured: bool,
+    max_parallelism: Option<usize>,
+) -> String {
+    match plan {
+        cpus::Plan::Sequential { crossover } => format!(
+            "solved one after the other without spawning threads, as the \
+             image's {} pixels are fewer than the {} below which threads cost \
+             more than they save ({})",
+            pixels,
+            crossover,
+            match measured {
+                true => "measured on this machine",
+                false => "given by --sequential-below",
+            }
+        ),
+        cpus::Plan::Unavailable(message) => format!(
+            "solved one after the other, as the available parallelism could \
+             not be found out: {}",
+            message
+        ),
+        cpus::Plan::Parallel {
+            available,
+            jobs,
+            threads,
+        } => format!(
+            "{} CPU(s) available (the parallelism of this machine, capped by \
+             the CPU quota of its cgroup on Linux){}; {} λ value(s) solved at \
+             the same time (--jobs, or as many as there are CPUs, and no more \
+             than there are values), each with {} thread(s) over its channels \
+             (--threads-per-task, or the CPUs left over)",
+            available,
+            match max_parallelism {
+                Some(max) =>
+                    format!(", of which --max-parallelism allows {}", max),
+                None => String::new(),
+            },
+            jobs,
+            threads
+        ),
+    }
+}
+
+/// Prints the explanation, one section per line, to stdout.
+pub fn print(sections: &[(&str, String)]) {
+    log::logger().flush();
+    println!("explanation:");
+    for (name, text) in sections {
+        println!("- {}: {}", name, text);
+    }
+}
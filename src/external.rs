@@ -0,0 +1,154 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! External denoisers (e.g. a learned model behind a script) run on the
+//! same input as the sweep, so that their results are measured and
+//! reported next to those of total variation.
+//!
+//! A method is a command run by the shell, in which `{input}` and
+//! `{output}` stand for the path of the image to denoise and the path its
+//! result is expected at, of the same size.
+
+use std::{
+    path::Path,
+    process,
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    cancel,
+    storage,
+};
+
+/// How often a running command is checked for having finished or for the
+/// work being cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Method {
+    /// Names the method in the report and in the name of its output.
+    pub name: String,
+    pub command: String,
+}
+
+impl Method {
+    /// Runs the command on `input`, waiting for it to write `output`, and
+    /// kills it if the work is cancelled. The storage in use is told of it
+    /// first, and may refuse it.
+    pub fn run(
+        &self,
+        input: &Path,
+        output: &Path,
+        cancel: &cancel::Token,
+    ) -> Result<(), String> {
+        let command = self
+            .command
+            .replace("{input}", &quote(input))
+            .replace("{output}", &quote(output));
+        storage::get()
+            .before_command(&command, &[input, output])
+            .map_err(|error| format!("could not be run: {}", error))?;
+        log::debug!("running {}: {}", self.name, command);
+        let mut child =
+            shell(&command)
+                .stdin(process::Stdio::null())
+                .spawn()
+                .map_err(|error| format!("could not be run: {}", error))?;
+        loop {
+            if let Err(cancelled) = cancel.check() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(cancelled.to_string());
+            }
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => return Ok(()),
+                Ok(Some(status)) => {
+                    return Err(format!("exited with {}", status))
+                },
+                Ok(None) => thread::sleep(POLL_INTERVAL),
+                Err(error) => return Err(error.to_string()),
+            }
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, command) = s.split_once('=').ok_or_else(|| {
+            format!("invalid method `{}`: expected `NAME=COMMAND`", s)
+        })?;
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "invalid method `{}`: its name must be made of letters, \
+                 digits, `-` and `_`",
+                s
+            ));
+        }
+        if !command.contains("{output}") {
+            return Err(format!(
+                "invalid method `{}`: its command must write `{{output}}`",
+                s
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            command: command.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.command)
+    }
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> process::Command {
+    let mut shell = process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> process::Command {
+    let mut shell = process::Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// `path` quoted as a single word for the shell.
+#[cfg(not(windows))]
+fn quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+#[cfg(windows)]
+fn quote(path: &Path) -> String {
+    format!("\"{}\"", path.to_string_lossy())
+}
@@ -0,0 +1,152 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Galleries of the patches of an output that the denoising changed the
+//! most, shown next to the same patches of the input, for reviewers to
+//! check exactly where it altered the image.
+
+use image_recovery::{
+    image::{
+        GenericImage,
+        Rgb,
+        RgbImage,
+    },
+    ndarray::{
+        Array2,
+        Array3,
+        Axis,
+    },
+    ImageArray,
+};
+
+use crate::{
+    patch,
+    region::Region,
+};
+
+/// Width in pixels of the white gap between the input and the output.
+const GAP: u32 = 2;
+
+/// A patch of the gallery.
+#[derive(Debug, Clone, Copy)]
+pub struct Patch {
+    pub region: Region,
+    /// Mean change of its pixels (the euclidean length of the difference
+    /// over the color axis), in 8-bit levels.
+    pub change: f64,
+}
+
+/// The `count` square patches of side `size` (or less, in smaller images)
+/// that changed the most on average from `input` to `output`, most changed
+/// first, none overlapping another.
+pub fn most_changed(
+    input: &Array3<f64>,
+    output: &Array3<f64>,
+    size: u32,
+    count: usize,
+) -> Vec<Patch> {
+    let magnitude = (output - input)
+        .mapv(|x| x * x)
+        .sum_axis(Axis(2))
+        .mapv(f64::sqrt);
+    let (width, height) = magnitude.dim();
+    let size = (size as usize).min(width).min(height).max(1);
+    let sums = summed_area(&magnitude);
+    let window = |x: usize, y: usize| {
+        sums[[x + size, y + size]] - sums[[x, y + size]] - sums[[x + size, y]]
+            + sums[[x, y]]
+    };
+
+    // windows a quarter of their side apart, so that the most changed
+    // areas are not split between two of them
+    let stride = (size / 4).max(1);
+    let starts = |len: usize| {
+        let mut starts: Vec<usize> = (0..=len - size).step_by(stride).collect();
+        if starts.last() != Some(&(len - size)) {
+            starts.push(len - size);
+        }
+        starts
+    };
+    let mut candidates: Vec<Patch> = starts(width)
+        .into_iter()
+        .flat_map(|x| starts(height).into_iter().map(move |y| (x, y)))
+        .map(|(x, y)| Patch {
+            region: Region {
+                x: x as u32,
+                y: y as u32,
+                width: size as u32,
+                height: size as u32,
+            },
+            change: window(x, y) / (size * size) as f64,
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.change.total_cmp(&a.change));
+
+    let mut chosen: Vec<Patch> = Vec::with_capacity(count);
+    for candidate in candidates {
+        if chosen.len() == count {
+            break;
+        }
+        if !chosen
+            .iter()
+            .any(|patch| overlap(patch.region, candidate.region))
+        {
+            chosen.push(candidate);
+        }
+    }
+    chosen
+}
+
+/// The patch of `input` and of `output` side by side, input on the left.
+pub fn render(
+    input: &Array3<f64>,
+    output: &Array3<f64>,
+    region: Region,
+) -> RgbImage {
+    let before = ImageArray::from(&patch::crop(input, region)).into_rgb();
+    let after = ImageArray::from(&patch::crop(output, region)).into_rgb();
+    let mut img = RgbImage::from_pixel(
+        2 * region.width + GAP,
+        region.height,
+        Rgb([255, 255, 255]),
+    );
+    img.copy_from(&before, 0, 0)
+        .expect("the input patch fits on the left");
+    img.copy_from(&after, region.width + GAP, 0)
+        .expect("the output patch fits on the right");
+    img
+}
+
+/// Sums of `values` over `[0, x) x [0, y)` at `[x, y]`, for the sum over
+/// any rectangle from four lookups.
+fn summed_area(values: &Array2<f64>) -> Array2<f64> {
+    let (width, height) = values.dim();
+    let mut sums = Array2::zeros((width + 1, height + 1));
+    for x in 0..width {
+        for y in 0..height {
+            sums[[x + 1, y + 1]] =
+                values[[x, y]] + sums[[x, y + 1]] + sums[[x + 1, y]]
+                    - sums[[x, y]];
+        }
+    }
+    sums
+}
+
+fn overlap(a: Region, b: Region) -> bool {
+    a.x < b.x + b.width
+        && b.x < a.x + a.width
+        && a.y < b.y + b.height
+        && b.y < a.y + a.height
+}
@@ -0,0 +1,246 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Georeferencing of GeoTIFF inputs (e.g. satellite or aerial tiles),
+//! carried over to their outputs so that GIS software still places them:
+//! the GeoTIFF tags are copied, adjusted to the area and resolution of each
+//! output, which keeps the sample type of the input.
+
+use std::{
+    io::{
+        self,
+        Cursor,
+    },
+    path::Path,
+};
+
+use image_recovery::{
+    image::DynamicImage,
+    ndarray::Array3,
+    ImageArray,
+};
+use tiff::{
+    decoder::Decoder,
+    encoder::{
+        colortype::{
+            ColorType,
+            RGB32Float,
+            RGB16,
+            RGB8,
+        },
+        TiffEncoder,
+        TiffValue,
+    },
+    tags::Tag,
+    TiffError,
+};
+
+use crate::{
+    input::SampleFormat,
+    output,
+    storage,
+};
+
+/// The tags placing a raster in the world, and those GDAL keeps beside
+/// them.
+const TAGS: [Tag; 8] = [
+    Tag::ModelPixelScaleTag,
+    Tag::ModelTiepointTag,
+    Tag::ModelTransformationTag,
+    Tag::GeoKeyDirectoryTag,
+    Tag::GeoDoubleParamsTag,
+    Tag::GeoAsciiParamsTag,
+    // GDAL_METADATA, with the per-band statistics and descriptions
+    Tag::Unknown(42112),
+    Tag::GdalNodata,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Doubles(Vec<f64>),
+    Shorts(Vec<u16>),
+    Ascii(String),
+}
+
+/// The GeoTIFF tags of an image, as read from its first page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Georeference {
+    tags: Vec<(Tag, Value)>,
+}
+
+/// Area of the input (in its pixels, as `x, y, width, height`) an output
+/// covers.
+pub type Area = [f64; 4];
+
+/// How the outputs of a GeoTIFF input are saved.
+#[derive(Debug, Clone)]
+pub struct Placement {
+    pub georeference: Georeference,
+    /// Of the input, covered by the outputs.
+    pub area: Area,
+    /// Of the input, kept in the outputs.
+    pub sample_format: SampleFormat,
+}
+
+/// The GeoTIFF tags in `bytes`, if they are a TIFF file and have any.
+pub fn read(bytes: &[u8]) -> Option<Georeference> {
+    let mut decoder = Decoder::new(Cursor::new(bytes)).ok()?;
+    let mut tags = Vec::new();
+    for tag in TAGS {
+        let value = match decoder.find_tag(tag) {
+            Ok(Some(value)) => value,
+            _ => continue,
+        };
+        let value = match tag {
+            Tag::GeoKeyDirectoryTag => value.into_u16_vec().map(Value::Shorts),
+            Tag::GeoAsciiParamsTag | Tag::GdalNodata | Tag::Unknown(_) => {
+                value.into_string().map(Value::Ascii)
+            },
+            _ => value.into_f64_vec().map(Value::Doubles),
+        };
+        match value {
+            Ok(value) => tags.push((tag, value)),
+            Err(error) => {
+                log::warn!("GeoTIFF tag {:?} not kept: {}", tag, error)
+            },
+        }
+    }
+    match tags.is_empty() {
+        true => None,
+        false => Some(Georeference { tags }),
+    }
+}
+
+impl Georeference {
+    /// The georeference of an image of `width` by `height` pixels made of
+    /// `area` of this one, cropped and resampled.
+    fn resampled(&self, area: Area, width: u32, height: u32) -> Self {
+        let [x, y, area_width, area_height] = area;
+        let (x_factor, y_factor) =
+            (width as f64 / area_width, height as f64 / area_height);
+        let tags = self
+            .tags
+            .iter()
+            .map(|(tag, value)| {
+                let value = match (tag, value) {
+                    (Tag::ModelPixelScaleTag, Value::Doubles(scale)) => {
+                        let mut scale = scale.clone();
+                        scale
+                            .iter_mut()
+                            .zip([x_factor, y_factor])
+                            .for_each(|(scale, factor)| *scale /= factor);
+                        Value::Doubles(scale)
+                    },
+                    // sets of (i, j, k) raster and (x, y, z) model points
+                    (Tag::ModelTiepointTag, Value::Doubles(points)) => {
+                        let mut points = points.clone();
+                        for point in points.chunks_mut(6) {
+                            point[0] = (point[0] - x) * x_factor;
+                            if let Some(j) = point.get_mut(1) {
+                                *j = (*j - y) * y_factor;
+                            }
+                        }
+                        Value::Doubles(points)
+                    },
+                    // a row major 4x4 matrix from raster to model points
+                    (Tag::ModelTransformationTag, Value::Doubles(matrix))
+                        if matrix.len() == 16 =>
+                    {
+                        let mut matrix = matrix.clone();
+                        for row in matrix.chunks_mut(4) {
+                            row[3] += row[0] * x + row[1] * y;
+                            row[0] /= x_factor;
+                            row[1] /= y_factor;
+                        }
+                        Value::Doubles(matrix)
+                    },
+                    _ => value.clone(),
+                };
+                (*tag, value)
+            })
+            .collect();
+        Self { tags }
+    }
+}
+
+/// Saves `array` (on the 8-bit scale) as a TIFF file at `path`, with the
+/// samples of the input and its GeoTIFF tags adjusted to the output, and
+/// returns the image saved.
+pub fn save(
+    path: &Path,
+    array: &Array3<f64>,
+    placement: &Placement,
+) -> io::Result<DynamicImage> {
+    let img = match placement.sample_format {
+        SampleFormat::U8 => {
+            DynamicImage::from(ImageArray::from(array).into_rgb())
+        },
+        SampleFormat::U16 => DynamicImage::from(output::to_rgb16(array)),
+        SampleFormat::F32 => DynamicImage::from(output::to_rgb32f(array)),
+    };
+    let georeference = placement.georeference.resampled(
+        placement.area,
+        img.width(),
+        img.height(),
+    );
+
+    let (width, height) = (img.width(), img.height());
+    let mut bytes = Cursor::new(Vec::new());
+    match &img {
+        DynamicImage::ImageRgb16(rgb) => encode::<RGB16>(
+            &mut bytes,
+            (width, height),
+            rgb.as_raw(),
+            &georeference,
+        ),
+        DynamicImage::ImageRgb32F(rgb) => encode::<RGB32Float>(
+            &mut bytes,
+            (width, height),
+            rgb.as_raw(),
+            &georeference,
+        ),
+        _ => encode::<RGB8>(
+            &mut bytes,
+            (width, height),
+            img.as_bytes(),
+            &georeference,
+        ),
+    }
+    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    storage::get().write(path, bytes.get_ref())?;
+    Ok(img)
+}
+
+fn encode<C: ColorType>(
+    bytes: &mut Cursor<Vec<u8>>,
+    (width, height): (u32, u32),
+    samples: &[C::Inner],
+    georeference: &Georeference,
+) -> Result<(), TiffError>
+where
+    [C::Inner]: TiffValue,
+{
+    let mut tiff = TiffEncoder::new(bytes)?;
+    let mut image = tiff.new_image::<C>(width, height)?;
+    for (tag, value) in &georeference.tags {
+        let encoder = image.encoder();
+        match value {
+            Value::Doubles(values) => encoder.write_tag(*tag, &values[..])?,
+            Value::Shorts(values) => encoder.write_tag(*tag, &values[..])?,
+            Value::Ascii(text) => encoder.write_tag(*tag, text.as_str())?,
+        }
+    }
+    image.write_data(samples)
+}
@@ -0,0 +1,168 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Golden runs: the exact outputs of a run, saved at full precision so that
+//! a later run (of a new version, or on another machine) can be checked to
+//! reproduce them, as when validating upgrades.
+
+use std::{
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use image_recovery::ndarray::Array3;
+use serde::Serialize;
+
+use crate::storage;
+
+/// Start of every golden file, followed by the width, height and number of
+/// channels as little-endian u64, the number of iterations as a u32, and
+/// the samples as little-endian f64 in x, y, channel order.
+const MAGIC: &[u8; 8] = b"DNGOLDEN";
+const HEADER_LEN: usize = MAGIC.len() + 3 * 8 + 4;
+
+/// How an output compares to its golden output.
+#[derive(Serialize, Debug)]
+pub struct Comparison {
+    pub golden: PathBuf,
+    /// Largest difference allowed, in 8-bit levels.
+    pub tolerance: f64,
+    /// Largest difference of a sample from the golden output, in 8-bit
+    /// levels.
+    pub max_difference: f64,
+    /// Root-mean-square difference from the golden output.
+    pub rms_difference: f64,
+    /// How many samples differ by more than `tolerance`.
+    pub differing_samples: usize,
+    pub golden_iterations: u32,
+    pub iterations: u32,
+    /// Why the comparison could not be made (e.g. a missing golden output,
+    /// or one of another size), if it could not.
+    pub error: Option<String>,
+}
+
+impl Comparison {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.differing_samples == 0
+    }
+}
+
+/// Path of the golden output of `lambda` in `folder`.
+pub fn path(folder: &Path, lambda: f64) -> PathBuf {
+    folder.join(format!("lambda_{:e}.golden", lambda))
+}
+
+/// Saves `image`, solved in `iterations`, as the golden output of `lambda`
+/// in `folder`.
+pub fn record(
+    folder: &Path,
+    lambda: f64,
+    image: &Array3<f64>,
+    iterations: u32,
+) -> io::Result<PathBuf> {
+    let (width, height, channels) = image.dim();
+    let mut bytes = Vec::with_capacity(HEADER_LEN + image.len() * 8);
+    bytes.extend_from_slice(MAGIC);
+    for dimension in [width, height, channels] {
+        bytes.extend_from_slice(&(dimension as u64).to_le_bytes());
+    }
+    bytes.extend_from_slice(&iterations.to_le_bytes());
+    for value in image.iter() {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    let path = path(folder, lambda);
+    storage::get().write(&path, &bytes)?;
+    Ok(path)
+}
+
+/// Compares `image`, solved in `iterations`, to the golden output of
+/// `lambda` in `folder`.
+pub fn compare(
+    folder: &Path,
+    lambda: f64,
+    image: &Array3<f64>,
+    iterations: u32,
+    tolerance: f64,
+) -> Comparison {
+    let golden = path(folder, lambda);
+    let mut comparison = Comparison {
+        golden: golden.clone(),
+        tolerance,
+        max_difference: 0.0,
+        rms_difference: 0.0,
+        differing_samples: 0,
+        golden_iterations: 0,
+        iterations,
+        error: None,
+    };
+    let (expected, golden_iterations) = match load(&golden) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            comparison.error = Some(format!("could not be read: {}", error));
+            return comparison;
+        },
+    };
+    comparison.golden_iterations = golden_iterations;
+    if expected.dim() != image.dim() {
+        let (width, height, channels) = expected.dim();
+        let (found_width, found_height, found_channels) = image.dim();
+        comparison.error = Some(format!(
+            "is {}x{} with {} channels instead of {}x{} with {}",
+            width, height, channels, found_width, found_height, found_channels
+        ));
+        return comparison;
+    }
+
+    let mut squares = 0.0;
+    for (&expected, &found) in expected.iter().zip(image.iter()) {
+        let difference = (found - expected).abs();
+        comparison.max_difference = comparison.max_difference.max(difference);
+        squares += difference * difference;
+        if difference.is_nan() || difference > tolerance {
+            comparison.differing_samples += 1;
+        }
+    }
+    comparison.rms_difference = (squares / image.len().max(1) as f64).sqrt();
+    comparison
+}
+
+/// The image and number of iterations of the golden file at `path`.
+fn load(path: &Path) -> io::Result<(Array3<f64>, u32)> {
+    let invalid = |message: &str| {
+        io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+    };
+    let bytes = storage::get().read(path)?;
+    if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(invalid("not a golden output"));
+    }
+    let word = |index: usize| {
+        let start = MAGIC.len() + index * 8;
+        u64::from_le_bytes(bytes[start..start + 8].try_into().unwrap()) as usize
+    };
+    let shape = (word(0), word(1), word(2));
+    let iterations = u32::from_le_bytes(
+        bytes[HEADER_LEN - 4..HEADER_LEN].try_into().unwrap(),
+    );
+    let samples: Vec<f64> = bytes[HEADER_LEN..]
+        .chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    let array = Array3::from_shape_vec(shape, samples)
+        .map_err(|_| invalid("truncated golden output"))?;
+    Ok((array, iterations))
+}
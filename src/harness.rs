@@ -0,0 +1,197 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Running the whole pipeline on files kept in memory, for integration
+//! tests (ours or of programs using this crate) that would otherwise need
+//! temporary folders and image files on disk.
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io,
+    panic::{
+        self,
+        AssertUnwindSafe,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+        Mutex,
+    },
+};
+
+use image_recovery::image::{
+    self,
+    DynamicImage,
+    ImageResult,
+};
+
+pub use crate::locale::Formatter;
+use crate::{
+    cancel,
+    storage::{
+        self,
+        Memory,
+        Storage,
+    },
+    Cli,
+};
+
+/// Path the report of a denoising run is saved at, unless the arguments
+/// give their own with `--report`.
+pub const REPORT: &str = "report.json";
+
+/// Held for the whole of a run, since the storage is shared by the process.
+static RUNNING: Mutex<()> = Mutex::new(());
+/// Whether a run is in progress, so that errors of the command line unwind
+/// back to it rather than exit the process.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Why a run could not be completed.
+#[derive(Debug)]
+pub enum Error {
+    /// The arguments were rejected, with the message the binary would give
+    /// (e.g. arguments that do not parse, or an input that is not among the
+    /// files given).
+    Cli(clap::error::Error<Formatter>),
+    /// The run panicked on an error it cannot go on from (e.g. an output
+    /// that could not be saved), with its message.
+    Failed(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Cli(error) => write!(f, "{}", error),
+            Error::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<clap::error::Error<Formatter>> for Error {
+    fn from(error: clap::error::Error<Formatter>) -> Self {
+        Error::Cli(error)
+    }
+}
+
+/// The outcome of a run.
+#[derive(Debug)]
+pub struct Run {
+    /// Whether the command succeeded, as told by the exit status of the
+    /// binary.
+    pub succeeded: bool,
+    /// Every file in memory after the run, inputs included, by path.
+    pub files: HashMap<PathBuf, Vec<u8>>,
+    /// The report of a denoising run, as saved with `--report`.
+    pub summary: Option<serde_json::Value>,
+}
+
+impl Run {
+    /// Decodes the image saved at `path`.
+    pub fn image(&self, path: impl AsRef<Path>) -> ImageResult<DynamicImage> {
+        let path = path.as_ref();
+        let bytes = self.files.get(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no file at {}", path.to_string_lossy()),
+            )
+        })?;
+        image::load_from_memory(bytes)
+    }
+}
+
+/// Runs the command line interface with `args` (without the name of the
+/// program), reading and writing files in memory only, starting from
+/// `inputs`. Output paths are the ones the binary would write, e.g.
+/// `out/image_lambda_=_0.1.png` with `--output-folder out`; any folder is
+/// taken to exist.
+///
+/// Arguments the binary would reject (such as an input that is not among
+/// `inputs`) and errors it would stop on are returned as errors, where the
+/// binary would exit; a run that goes to its end but fails (such as a
+/// solve that failed, with the others saved) is returned as not
+/// `succeeded`.
+pub fn run<I, T>(
+    args: I,
+    inputs: impl IntoIterator<Item = (PathBuf, Vec<u8>)>,
+) -> Result<Run, Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    // the language the arguments are parsed in is shared by the process too
+    let _running = RUNNING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let program = std::iter::once(OsString::from(env!("CARGO_PKG_NAME")));
+    let mut cli =
+        Cli::try_parse_args(program.chain(args.into_iter().map(Into::into)))?;
+    let report = cli.denoise.as_mut().map(|args| {
+        args.report
+            .get_or_insert_with(|| PathBuf::from(REPORT))
+            .clone()
+    });
+
+    let memory = Arc::new(Memory::default());
+    for (path, bytes) in inputs {
+        memory
+            .write(&path, &bytes)
+            .map_err(|error| Error::Failed(error.to_string()))?;
+    }
+    let previous = storage::replace(Some(memory.clone()));
+    ACTIVE.store(true, Ordering::SeqCst);
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        crate::execute(cli, &cancel::Token::new(None))
+    }));
+    ACTIVE.store(false, Ordering::SeqCst);
+    storage::replace(previous);
+    let succeeded = outcome.map_err(|payload| {
+        match payload.downcast::<clap::error::Error<Formatter>>() {
+            Ok(error) => Error::Cli(*error),
+            Err(payload) => Error::Failed(
+                payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| {
+                        payload.downcast_ref::<&str>().map(|s| s.to_string())
+                    })
+                    .unwrap_or_else(|| "the run panicked".to_string()),
+            ),
+        }
+    })?;
+
+    let summary =
+        report.and_then(|path| storage::load_json(memory.as_ref(), &path).ok());
+    Ok(Run {
+        succeeded,
+        files: memory.snapshot(),
+        summary,
+    })
+}
+
+/// Whether errors of the command line are to be returned by `run`, rather
+/// than exit the process.
+pub(crate) fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
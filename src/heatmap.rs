@@ -0,0 +1,158 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! False-color maps of the per-pixel change between input and output.
+
+use image_recovery::{
+    image::{
+        Rgb,
+        RgbImage,
+    },
+    ndarray::{
+        Array2,
+        Array3,
+        Axis,
+    },
+};
+
+/// Height in pixels of the color strip in the scale bar.
+const BAR_HEIGHT: u32 = 10;
+/// Glyphs are 3x5 pixels, drawn at this integer scale.
+const GLYPH_SCALE: u32 = 2;
+/// Padding in pixels around the elements of the scale bar.
+const PADDING: u32 = 2;
+
+/// Renders the magnitude of the per-pixel change between `input` and
+/// `output` (the euclidean length of the difference over the color axis,
+/// in 8-bit levels) using the viridis color map, with a scale bar appended
+/// below the image going from zero to the largest change found.
+pub fn render(input: &Array3<f64>, output: &Array3<f64>) -> RgbImage {
+    let magnitude = (output - input)
+        .mapv(|x| x * x)
+        .sum_axis(Axis(2))
+        .mapv(f64::sqrt);
+    let max = magnitude.fold(0_f64, |acc, &x| acc.max(x));
+    log::debug!("heat map scale: 0 to {:.4}", max);
+
+    let (width, height) = (magnitude.shape()[0], magnitude.shape()[1]);
+    let label_height = 5 * GLYPH_SCALE + 2 * PADDING;
+    let bar_area = PADDING + BAR_HEIGHT + label_height;
+    let mut img = RgbImage::new(width as u32, height as u32 + bar_area);
+
+    draw_map(&mut img, &magnitude, max);
+    draw_scale_bar(&mut img, height as u32, max);
+
+    img
+}
+
+fn draw_map(img: &mut RgbImage, magnitude: &Array2<f64>, max: f64) {
+    for ((x, y), &value) in magnitude.indexed_iter() {
+        let t = if max > 0.0 { value / max } else { 0.0 };
+        img.put_pixel(x as u32, y as u32, viridis(t));
+    }
+}
+
+fn draw_scale_bar(img: &mut RgbImage, top: u32, max: f64) {
+    let width = img.width();
+    for y in top..img.height() {
+        for x in 0..width {
+            img.put_pixel(x, y, Rgb([255, 255, 255]));
+        }
+    }
+
+    let bar_top = top + PADDING;
+    for x in 0..width {
+        let t = x as f64 / (width.max(2) - 1) as f64;
+        for y in bar_top..bar_top + BAR_HEIGHT {
+            img.put_pixel(x, y, viridis(t));
+        }
+    }
+
+    let label_top = bar_top + BAR_HEIGHT + PADDING;
+    let low = "0";
+    let high = format!("{:.2}", max);
+    let high_width = text_width(&high);
+    draw_text(img, low, PADDING, label_top);
+    if text_width(low) + high_width + 4 * PADDING <= width {
+        draw_text(img, &high, width - high_width - PADDING, label_top);
+    } else {
+        log::warn!("image too narrow to label heat map scale bar");
+    }
+}
+
+/// Polynomial approximation to the viridis color map for `t` in `[0, 1]`.
+fn viridis(t: f64) -> Rgb<u8> {
+    const C: [[f64; 3]; 7] = [
+        [0.277727327223417, 0.005407344544966, 0.334099805335306],
+        [0.105093043108577, 1.404613529898575, 1.384590162594685],
+        [-0.330861828725556, 0.214847559468213, 0.095095163028236],
+        [-4.634230498983486, -5.799100973351585, -19.33244095627987],
+        [6.228269936347081, 14.17993336680509, 56.69055260068105],
+        [4.776384997670288, -13.74514537774601, -65.35303263337234],
+        [-5.435455855934631, 4.645852612178535, 26.3124352495832],
+    ];
+    let t = t.clamp(0.0, 1.0);
+    let mut rgb = [0_u8; 3];
+    for (channel, value) in rgb.iter_mut().enumerate() {
+        let v = C.iter().rev().fold(0_f64, |acc, c| acc * t + c[channel]);
+        *value = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    Rgb(rgb)
+}
+
+fn text_width(text: &str) -> u32 {
+    text.chars().count() as u32 * 4 * GLYPH_SCALE
+}
+
+fn draw_text(img: &mut RgbImage, text: &str, left: u32, top: u32) {
+    for (i, c) in text.chars().enumerate() {
+        let rows = glyph(c);
+        let glyph_left = left + i as u32 * 4 * GLYPH_SCALE;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..GLYPH_SCALE {
+                    for dx in 0..GLYPH_SCALE {
+                        let x = glyph_left + col * GLYPH_SCALE + dx;
+                        let y = top + row as u32 * GLYPH_SCALE + dy;
+                        if x < img.width() && y < img.height() {
+                            img.put_pixel(x, y, Rgb([0, 0, 0]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 3x5 bitmap glyphs, one `u8` per row with the 3 lowest bits as columns.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        _ => [0b000; 5],
+    }
+}
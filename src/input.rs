@@ -0,0 +1,210 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Loading of input images into the arrays the solver works on, and the
+//! corrections applied to them before denoising.
+
+use std::path::Path;
+
+use image_recovery::{
+    image::{
+        self,
+        DynamicImage,
+    },
+    ndarray::{
+        Array3,
+        Axis,
+        Zip,
+    },
+    ImageArray,
+};
+
+use crate::storage;
+
+/// Type of the samples of a decoded image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    U8,
+    U16,
+    /// Floating point, possibly holding high dynamic range.
+    F32,
+}
+
+impl SampleFormat {
+    fn of(img: &DynamicImage) -> Self {
+        match img {
+            DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageRgba8(_) => SampleFormat::U8,
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+                SampleFormat::F32
+            },
+            _ => SampleFormat::U16,
+        }
+    }
+}
+
+/// Loads an image as RGB, with values on the 8-bit scale (0 to 255).
+///
+/// 8-bit images are converted exactly; images with more depth are kept at
+/// full precision rather than quantized to 8 bits, so that corrections
+/// happen in the same f64 pipeline as the denoising. Floating point values
+/// are not clamped.
+pub fn load(
+    path: &Path,
+) -> image::ImageResult<(ImageArray<Array3<f64>>, SampleFormat)> {
+    let img = storage::open_image(storage::get(), path)?;
+    let format = SampleFormat::of(&img);
+    let array = match format {
+        SampleFormat::U8 => ImageArray::from(&img.into_rgb8()),
+        _ => {
+            let img = img.into_rgb32f();
+            let (width, height) = img.dimensions();
+            let array = Array3::from_shape_fn(
+                (width as usize, height as usize, 3),
+                |(x, y, z)| img.get_pixel(x as u32, y as u32)[z] as f64 * 255.0,
+            );
+            ImageArray::from(&array)
+        },
+    };
+    Ok((array, format))
+}
+
+/// Applies dark-frame subtraction and flat-field correction, as usual in
+/// astronomy and microscopy pipelines:
+/// `(raw - dark) * mean(flat - dark) / (flat - dark)`, per channel.
+///
+/// Pixels where the dark-subtracted flat is not positive are only dark
+/// subtracted, since no gain can be derived for them.
+pub fn calibrate(
+    raw: &Array3<f64>,
+    dark: Option<&Array3<f64>>,
+    flat: Option<&Array3<f64>>,
+) -> Array3<f64> {
+    let mut calibrated = match dark {
+        Some(dark) => raw - dark,
+        None => raw.clone(),
+    };
+
+    if let Some(flat) = flat {
+        let flat = match dark {
+            Some(dark) => flat - dark,
+            None => flat.clone(),
+        };
+        for (mut channel, flat) in calibrated
+            .axis_iter_mut(Axis(2))
+            .zip(flat.axis_iter(Axis(2)))
+        {
+            let usable = flat.iter().filter(|&&v| v > 0.0);
+            let count = usable.clone().count();
+            if count == 0 {
+                log::warn!("flat field has no usable pixels in a channel");
+                continue;
+            }
+            let mean = usable.sum::<f64>() / count as f64;
+            Zip::from(&mut channel).and(&flat).for_each(|value, &gain| {
+                if gain > 0.0 {
+                    *value *= mean / gain;
+                }
+            });
+        }
+    }
+
+    calibrated
+}
+
+/// Finds columns and rows whose mean intensity stands out from both of
+/// their neighbours' in the same direction, as left by defective sensor
+/// lines (unlike edges in the scene, where a line only differs from one
+/// side). A line is flagged if it stands out by more than `threshold`
+/// robust standard deviations, estimated from the median absolute
+/// deviation of the line means from their neighbours' over the whole image.
+pub fn detect_bad_lines(
+    array: &Array3<f64>,
+    threshold: f64,
+) -> (Vec<usize>, Vec<usize>) {
+    let intensity = array
+        .mean_axis(Axis(2))
+        .expect("image has at least one channel");
+    let outliers = |axis: usize| -> Vec<usize> {
+        let means: Vec<f64> = intensity
+            .axis_iter(Axis(axis))
+            .map(|line| line.mean().unwrap_or(0.0))
+            .collect();
+        if means.len() < 3 {
+            return Vec::new();
+        }
+        let mut deviations: Vec<f64> = (1..means.len() - 1)
+            .map(|i| (means[i] - (means[i - 1] + means[i + 1]) / 2.0).abs())
+            .collect();
+        deviations.sort_by(f64::total_cmp);
+        // scaled so that it estimates the standard deviation
+        let scatter = 1.4826 * deviations[deviations.len() / 2];
+        // at least a full level apart, for images with no scatter at all
+        let cutoff = (threshold * scatter).max(1.0);
+        (1..means.len() - 1)
+            .filter(|&i| {
+                let before = means[i] - means[i - 1];
+                let after = means[i] - means[i + 1];
+                before.signum() == after.signum()
+                    && before.abs().min(after.abs()) > cutoff
+            })
+            .collect()
+    };
+
+    (outliers(0), outliers(1))
+}
+
+/// Replaces the given columns and rows by linear interpolation between the
+/// nearest good lines on either side (or a copy of the only good neighbour
+/// on the borders of the image).
+pub fn repair_lines(
+    array: &mut Array3<f64>,
+    columns: &[usize],
+    rows: &[usize],
+) {
+    for (axis, bad) in [(0, columns), (1, rows)] {
+        let len = array.len_of(Axis(axis));
+        let is_bad = |i: usize| bad.contains(&i);
+        for &line in bad {
+            let before = (0..line).rev().find(|&i| !is_bad(i));
+            let after = (line + 1..len).find(|&i| !is_bad(i));
+            let repaired = match (before, after) {
+                (Some(a), Some(b)) => {
+                    let t = (line - a) as f64 / (b - a) as f64;
+                    &array.index_axis(Axis(axis), a) * (1.0 - t)
+                        + &array.index_axis(Axis(axis), b) * t
+                },
+                (Some(a), None) => array.index_axis(Axis(axis), a).to_owned(),
+                (None, Some(b)) => array.index_axis(Axis(axis), b).to_owned(),
+                (None, None) => {
+                    log::warn!("no good lines left to repair from");
+                    return;
+                },
+            };
+            array.index_axis_mut(Axis(axis), line).assign(&repaired);
+        }
+    }
+}
+
+/// Whether all channels of every pixel are within `tolerance` (in 8-bit
+/// levels) of their mean, as in grayscale images stored as RGB.
+pub fn is_near_gray(array: &Array3<f64>, tolerance: f64) -> bool {
+    array.lanes(Axis(2)).into_iter().all(|pixel| {
+        let mean = pixel.mean().unwrap_or(0.0);
+        pixel.iter().all(|&value| (value - mean).abs() <= tolerance)
+    })
+}
@@ -0,0 +1,56 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Prompts on the terminal for choices during interactive runs.
+
+use std::io::{
+    self,
+    BufRead,
+    Write,
+};
+
+use crate::report::Report;
+
+/// Lists the drafts of `report` and asks which one to solve at full
+/// resolution, returning its λ value, or `None` if the input is skipped
+/// (or stdin is closed).
+pub fn choose_lambda(report: &Report) -> Option<f64> {
+    let count = report.lambdas.len();
+    println!("drafts of {}:", report.input.to_string_lossy());
+    for (number, entry) in report.lambdas.iter().enumerate() {
+        println!(
+            "  {:>3}) lambda {:.10}: {}",
+            number + 1,
+            entry.lambda,
+            entry.output.to_string_lossy()
+        );
+    }
+
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("choose a draft (1-{}), or `s` to skip: ", count);
+        io::stdout().flush().expect("prompt could not be shown");
+        let line = lines.next()?.expect("answer could not be read");
+        match line.trim() {
+            "s" | "skip" => return None,
+            answer => match answer.parse::<usize>() {
+                Ok(number) if (1..=count).contains(&number) => {
+                    return Some(report.lambdas[number - 1].lambda)
+                },
+                _ => println!("invalid choice `{}`", answer),
+            },
+        }
+    }
+}
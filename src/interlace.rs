@@ -0,0 +1,105 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Interlaced sources, as in archived broadcast material, whose even and
+//! odd rows (the two fields) were captured at different times: denoised
+//! together, the combing between moving fields gets smeared across both.
+
+use image_recovery::ndarray::{
+    s,
+    Array3,
+    Axis,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Denoise the frame as a whole
+    Off,
+    /// Denoise the fields separately if the frame shows combing
+    Auto,
+    /// Denoise the fields separately
+    On,
+}
+
+impl Mode {
+    /// Whether the fields are denoised separately, `None` for `Auto` until
+    /// it is detected.
+    pub fn fixed(self) -> Option<bool> {
+        match self {
+            Mode::Off => Some(false),
+            Mode::Auto => None,
+            Mode::On => Some(true),
+        }
+    }
+}
+
+/// Ratio of `combing` above which a frame is taken to be interlaced.
+pub const COMBING_THRESHOLD: f64 = 1.1;
+
+/// How much more rows differ from their neighbours (of the other field)
+/// than from the rows two away (of the same field). Under 1 for progressive
+/// frames, where nearer rows are more alike, and over 1 for interlaced
+/// ones with motion between the fields.
+pub fn combing(array: &Array3<f64>) -> f64 {
+    let height = array.len_of(Axis(1));
+    if height < 3 {
+        return 0.0;
+    }
+    let mean_difference = |step: usize| {
+        let (top, bottom) = (
+            array.slice(s![.., ..height - step, ..]),
+            array.slice(s![.., step.., ..]),
+        );
+        (&top - &bottom).mapv(f64::abs).mean().unwrap_or(0.0)
+    };
+    let other_field = mean_difference(1);
+    let same_field = mean_difference(2);
+    match same_field {
+        0.0 => 0.0,
+        _ => other_field / same_field,
+    }
+}
+
+/// Puts the two fields of `array` side by side as separate channels (those
+/// of the even rows first), so that they are solved independently. With an
+/// odd number of rows, the last row of the odd field is repeated (or the
+/// only row, with a single one).
+pub fn split(array: &Array3<f64>) -> Array3<f64> {
+    let (width, height, channels) = array.dim();
+    let rows = height.div_ceil(2);
+    // the last row of each field
+    let last = [(height - 1) & !1, (height.max(2) - 2) | 1];
+    Array3::from_shape_fn((width, rows, 2 * channels), |(x, y, z)| {
+        let (field, channel) = (z / channels, z % channels);
+        let row = (2 * y + field).min(last[field]).min(height - 1);
+        array[[x, row, channel]]
+    })
+}
+
+/// Undoes `split`, for a frame of `height` rows.
+pub fn interleave(fields: &Array3<f64>, height: usize) -> Array3<f64> {
+    let (width, _, channels) = fields.dim();
+    let channels = channels / 2;
+    Array3::from_shape_fn((width, height, channels), |(x, y, z)| {
+        fields[[x, y / 2, (y % 2) * channels + z]]
+    })
+}
@@ -0,0 +1,65 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Blends between the outputs of adjacent λ values, for previews (e.g.
+//! smooth animations of a sweep) that would otherwise take more solves.
+//! They are approximations: denoising is not linear in λ, so a blend is
+//! not what solving for the λ value it stands for would give.
+
+use std::{
+    io,
+    path::Path,
+};
+
+use image_recovery::ndarray::Array3;
+
+use crate::provenance;
+
+/// Keyword of the text chunk labeling a PNG blend, one of the keywords
+/// the PNG format predefines.
+const KEYWORD: &str = "Warning";
+
+/// The λ value a blend `t` of the way from `lower` to `upper` stands for,
+/// as far along on a log scale as sweeps are spread.
+pub fn lambda_between(lower: f64, upper: f64, t: f64) -> f64 {
+    lower * (upper / lower).powf(t)
+}
+
+/// The weights of `count` blends evenly spaced between two outputs.
+pub fn steps(count: usize) -> impl Iterator<Item = f64> {
+    (1..=count).map(move |step| step as f64 / (count + 1) as f64)
+}
+
+/// The blend `t` of the way from `lower` to `upper`, pixel by pixel.
+pub fn blend(lower: &Array3<f64>, upper: &Array3<f64>, t: f64) -> Array3<f64> {
+    lower * (1.0 - t) + upper * t
+}
+
+/// Adds a `tEXt` chunk to the PNG blend at `path` telling that it is an
+/// approximation, and of which outputs.
+pub fn label(path: &Path, lower: f64, upper: f64) -> io::Result<()> {
+    let mut chunk = b"tEXt".to_vec();
+    chunk.extend_from_slice(KEYWORD.as_bytes());
+    chunk.push(0);
+    chunk.extend_from_slice(
+        format!(
+            "approximation: a blend of the outputs for lambda = {} and lambda \
+             = {}, not solved",
+            lower, upper
+        )
+        .as_bytes(),
+    );
+    provenance::add_chunk(path, &chunk)
+}
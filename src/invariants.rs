@@ -0,0 +1,236 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Properties the solver must have on any input, checked on random
+//! synthetic images: a regression gate for changes to the solver or to the
+//! way its parameters are derived.
+
+use image_recovery::ndarray::Array3;
+
+use crate::{
+    analysis,
+    cancel::{
+        self,
+        Cancelled,
+    },
+    random::Random,
+    solver,
+};
+
+/// Smallest and largest side in pixels of the images.
+const SIZES: (usize, usize) = (16, 48);
+/// Smallest and largest standard deviation of their noise, in 8-bit
+/// levels.
+const NOISE: (f64, f64) = (5.0, 40.0);
+/// Smallest and largest λ, drawn uniformly on a log scale.
+const LAMBDAS: (f64, f64) = (0.02, 1.0);
+const MAX_ITER: u32 = 5000;
+const CONVERGENCE_THRESHOLD: f64 = 1e-6;
+/// How far outside the range of the input an output may go, in 8-bit
+/// levels, for what is left of convergence.
+const RANGE_TOLERANCE: f64 = 0.5;
+/// Relative increase of total variation tolerated from a smaller λ to a
+/// larger one.
+const MONOTONY_TOLERANCE: f64 = 1e-3;
+/// Largest change of denoising an output again, relative to the change of
+/// denoising it the first time: denoising is non-expansive, so it cannot
+/// be more but for what is left of convergence.
+const IDEMPOTENCE_TOLERANCE: f64 = 1.01;
+
+/// Checks every property on `cases` random images from `seed`, printing
+/// the violations and how each property went, and returns whether none was
+/// violated.
+pub fn run(cases: u32, seed: u64, cancel: &cancel::Token) -> bool {
+    println!("seed: {}", seed);
+    let mut random = Random::new(seed);
+    let mut violations = [0; 3];
+    for case in 1..=cases {
+        match check_case(&mut random, cancel) {
+            Ok(found) => {
+                for (index, violation) in found {
+                    println!("FAILED: case {}: {}", case, violation);
+                    violations[index] += 1;
+                }
+            },
+            Err(Cancelled) => {
+                println!("FAILED: cancelled at case {}", case);
+                return false;
+            },
+        }
+    }
+
+    let properties = [
+        "output within the range of the input",
+        "smoothing increases as lambda decreases",
+        "denoising an output again changes it no more than the first time",
+    ];
+    for (property, &count) in properties.iter().zip(&violations) {
+        match count {
+            0 => println!("ok: {} ({} cases)", property, cases),
+            _ => {
+                println!("FAILED: {} ({} of {} cases)", property, count, cases)
+            },
+        }
+    }
+    violations.iter().all(|&count| count == 0)
+}
+
+/// Checks one random image, returning the violations found, each with the
+/// index of its property.
+fn check_case(
+    random: &mut Random,
+    cancel: &cancel::Token,
+) -> Result<Vec<(usize, String)>, Cancelled> {
+    let input = random_image(random);
+    let mut lambdas: Vec<f64> = (0..3)
+        .map(|_| random.range(LAMBDAS.0.ln(), LAMBDAS.1.ln()).exp())
+        .collect();
+    lambdas.sort_by(f64::total_cmp);
+    let (width, height, _) = input.dim();
+    let describe = |lambda: f64| {
+        format!("{}x{} image, lambda {:.4}", width, height, lambda)
+    };
+
+    let mut violations = Vec::new();
+    let (low, high) = input
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(l, h), &v| (l.min(v), h.max(v)));
+    let mut outputs = Vec::with_capacity(lambdas.len());
+    for &lambda in &lambdas {
+        let output = solve(&input, lambda, cancel)?;
+        let (out_low, out_high) = output
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(l, h), &v| (l.min(v), h.max(v)));
+        if out_low < low - RANGE_TOLERANCE || out_high > high + RANGE_TOLERANCE
+        {
+            violations.push((
+                0,
+                format!(
+                    "{}: output in [{:.3}, {:.3}], input in [{:.3}, {:.3}]",
+                    describe(lambda),
+                    out_low,
+                    out_high,
+                    low,
+                    high
+                ),
+            ));
+        }
+        outputs.push(output);
+    }
+
+    for (pair, outputs) in lambdas.windows(2).zip(outputs.windows(2)) {
+        let smoother = analysis::total_variation(&outputs[0]);
+        let rougher = analysis::total_variation(&outputs[1]);
+        if smoother > rougher * (1.0 + MONOTONY_TOLERANCE) {
+            violations.push((
+                1,
+                format!(
+                    "{}: total variation {:.3}, above {:.3} at lambda {:.4}",
+                    describe(pair[0]),
+                    smoother,
+                    rougher,
+                    pair[1]
+                ),
+            ));
+        }
+    }
+
+    let lambda = lambdas[1];
+    let output = &outputs[1];
+    let again = solve(output, lambda, cancel)?;
+    let first = rms_difference(&input, output);
+    let second = rms_difference(output, &again);
+    if second > IDEMPOTENCE_TOLERANCE * first {
+        violations.push((
+            2,
+            format!(
+                "{}: changed by {:.3} again, after {:.3} the first time",
+                describe(lambda),
+                second,
+                first
+            ),
+        ));
+    }
+
+    Ok(violations)
+}
+
+/// Rectangles of random levels (and sizes and places) on a random ramp,
+/// with gaussian noise of a random level, each channel on its own.
+fn random_image(random: &mut Random) -> Array3<f64> {
+    let mut side =
+        || random.range(SIZES.0 as f64, SIZES.1 as f64 + 1.0).floor() as usize;
+    let (width, height) = (side(), side());
+    let mut image = Array3::zeros((width, height, 3));
+    for z in 0..3 {
+        let (base, slope) = (random.range(0.0, 255.0), random.range(-2.0, 2.0));
+        for x in 0..width {
+            for y in 0..height {
+                image[[x, y, z]] = base + slope * x as f64;
+            }
+        }
+        for _ in 0..random.range(1.0, 5.0) as usize {
+            let level = random.range(0.0, 255.0);
+            let x0 = random.range(0.0, width as f64) as usize;
+            let y0 = random.range(0.0, height as f64) as usize;
+            let x1 = random.range(x0 as f64 + 1.0, width as f64 + 1.0) as usize;
+            let y1 =
+                random.range(y0 as f64 + 1.0, height as f64 + 1.0) as usize;
+            for x in x0..x1.min(width) {
+                for y in y0..y1.min(height) {
+                    image[[x, y, z]] = level;
+                }
+            }
+        }
+    }
+    let noise = random.range(NOISE.0, NOISE.1);
+    image.mapv_inplace(|value| {
+        (value + noise * random.gaussian()).clamp(0.0, 255.0)
+    });
+    image
+}
+
+fn solve(
+    image: &Array3<f64>,
+    lambda: f64,
+    cancel: &cancel::Token,
+) -> Result<Array3<f64>, Cancelled> {
+    let parameters = solver::Parameters {
+        lambda,
+        tau: solver::tau(),
+        sigma: solver::sigma(),
+        gamma: solver::gamma(lambda),
+        max_iter: MAX_ITER,
+        convergence_threshold: CONVERGENCE_THRESHOLD,
+        acceleration: solver::Acceleration::Gamma,
+        channel_copies: 1,
+        threads: 1,
+        precision: solver::Precision::F64,
+        data_weight: None,
+        data_band: None,
+        stop_on_gap: None,
+        keep_dual: false,
+    };
+    match solver::denoise(image, &parameters, cancel, &mut |_| {}) {
+        Ok(solution) => Ok(solution.image),
+        Err(solver::Error::Cancelled(cancelled)) => Err(cancelled),
+        Err(error) => panic!("invariant check failed: {}", error),
+    }
+}
+
+fn rms_difference(a: &Array3<f64>, b: &Array3<f64>) -> f64 {
+    let difference = a - b;
+    (difference.mapv(|v| v * v).sum() / difference.len() as f64).sqrt()
+}
@@ -0,0 +1,77 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Journal of completed outputs, appended to across invocations as
+//! newline-delimited JSON, to find out over many runs which settings work
+//! for which inputs.
+
+use std::{
+    io,
+    path::Path,
+    time::SystemTime,
+};
+
+use serde::Serialize;
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use crate::{
+    report::LambdaReport,
+    storage,
+    DenoiseArgs,
+};
+
+/// One line of the journal.
+#[derive(Serialize, Debug)]
+pub struct Record<'a> {
+    /// Seconds since the Unix epoch at which the record was written.
+    pub timestamp: u64,
+    /// SHA-256 of the input file, in hexadecimal.
+    pub input_sha256: &'a str,
+    /// Estimated standard deviation of the noise in the input.
+    pub input_noise: f64,
+    #[serde(flatten)]
+    pub result: &'a LambdaReport,
+    pub settings: &'a DenoiseArgs,
+}
+
+/// SHA-256 of the file at `path`, in hexadecimal.
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    Ok(Sha256::digest(storage::get().read(path)?)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Appends the records to the journal at `path`, creating it if needed.
+/// Each record is written in a single call, so that runs appending to the
+/// same journal at once don't interleave within lines.
+pub fn append(path: &Path, records: &[Record]) -> io::Result<()> {
+    for record in records {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        storage::get().append(path, &line)?;
+    }
+    Ok(())
+}
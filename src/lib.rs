@@ -0,0 +1,3812 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod analysis;
+mod audit;
+mod bench;
+mod boundary;
+mod cancel;
+mod capabilities;
+mod clipped;
+mod convergence;
+mod convert;
+mod cost;
+mod cpus;
+mod crossover;
+mod dataset;
+mod deband;
+mod deblock;
+mod determinism;
+#[cfg(feature = "dicom")]
+mod dicom;
+mod explain;
+mod external;
+mod gallery;
+mod geotiff;
+mod golden;
+pub mod harness;
+mod heatmap;
+mod input;
+mod interactive;
+mod interlace;
+mod interpolate;
+mod invariants;
+mod journal;
+#[macro_use]
+mod locale;
+mod logger;
+mod names;
+mod npy;
+mod output;
+mod params;
+mod patch;
+#[cfg(feature = "plot")]
+mod plot;
+mod precision;
+mod progress;
+mod provenance;
+mod queue;
+mod random;
+mod range;
+mod region;
+mod report;
+mod resize;
+mod restrict;
+mod selftest;
+mod session;
+mod solver;
+mod space;
+mod stats;
+pub mod storage;
+mod throttle;
+mod tonemap;
+mod verify;
+mod xmp;
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    path::{
+        Path,
+        PathBuf,
+    },
+    thread,
+};
+
+use clap::{
+    Args,
+    CommandFactory,
+    FromArgMatches,
+    Parser,
+    Subcommand,
+};
+use image_recovery::{
+    image,
+    ndarray::Array3,
+    ImageArray,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// CLI wrapper for the denoising algorithm from image-recovery.
+///
+/// λ values:
+///
+/// The algorithm will run on the given input for as
+/// many λ values as given. Simply choose a start and
+/// end point, as well as how many steps there should
+/// be in between.
+///
+/// Stopping conditions:
+///
+/// The algorithm will run for at most `max_iter` number
+/// of iterations per λ value, but may stop earlier if the
+/// relative differente between the current candidate output
+/// and the previous iteration's candidate output becomes
+/// smaller than the given value for the `convergence_threshold`
+/// (or the one corresponding to the change per pixel given as
+/// `stop_at_delta`), or, with `stop_on_gap`, once the primal-dual gap
+/// relative to the energy becomes smaller than the given value
+///
+/// With `auto_iter`, `max_iter` is instead chosen from the
+/// convergence rate measured on the first iterations.
+#[derive(Parser, Debug)]
+#[command(author, version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    denoise: Option<DenoiseArgs>,
+    /// Replay the choices recorded in a session file by --session,
+    /// solving each chosen input at full resolution
+    #[arg(long, conflicts_with = "DenoiseArgs")]
+    apply_session: Option<PathBuf>,
+    /// Seconds after which to stop all work in progress, as on Ctrl-C
+    #[arg(long, global = true)]
+    timeout: Option<std::num::NonZeroU64>,
+    /// Path of a file to write the log to, except for warnings and errors
+    /// which always go to stderr; stdout by default
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+    /// Limit the rate at which files are read and written together (e.g.
+    /// `50MB/s`), so as not to saturate storage shared with others
+    #[arg(long, global = true, value_name = "RATE")]
+    io_limit: Option<throttle::Rate>,
+    /// Refuse to read or write any file outside these folders (separated
+    /// by commas), the log file included
+    #[arg(long, global = true, value_name = "DIRS", value_delimiter = ',')]
+    restrict_paths: Option<Vec<PathBuf>>,
+    /// Path of a tamper-evident log to which to append a record (with the
+    /// SHA-256 of the contents, the user and the time) of every file read
+    /// and written, kept across runs and checked by `check-audit`
+    #[arg(long, global = true)]
+    audit_log: Option<PathBuf>,
+    /// Refuse to decode images with more than this many megapixels
+    #[arg(long, global = true, default_value_t = 100.0)]
+    max_megapixels: f64,
+    /// Refuse to decode images taking more than this much memory to
+    /// decode (e.g. `2G`)
+    #[arg(long, global = true, value_name = "SIZE", default_value = "1G")]
+    max_decoded_size: space::Bytes,
+    /// Window of modality values (after the rescale slope and intercept)
+    /// mapped onto the levels of DICOM inputs: `stored` for every value
+    /// their samples can hold, `file` for the one saved in the file, or
+    /// `center,width`
+    #[cfg(feature = "dicom")]
+    #[arg(
+        long,
+        global = true,
+        value_name = "WINDOW",
+        default_value = "stored"
+    )]
+    dicom_window: dicom::Window,
+    /// Format to save the outputs of DICOM inputs in
+    #[cfg(feature = "dicom")]
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_parser = locale::values::<dicom::Format>(),
+        default_value_t = dicom::Format::Png,
+    )]
+    dicom_format: dicom::Format,
+    /// Language of the help, errors and summaries, by default that of the
+    /// locale (`LC_ALL`, `LC_MESSAGES` or `LANG`)
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_parser = locale::values::<locale::Lang>(),
+    )]
+    lang: Option<locale::Lang>,
+    /// Verbosity (from -v to -vvvv)
+    #[arg(
+        short,
+        long,
+        global = true,
+        action = clap::ArgAction::Count,
+        value_parser = clap::value_parser!(u8).range(..=4),
+    )]
+    verbose: u8,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print per-channel statistics, histograms and estimated noise
+    /// of an image
+    Stats(StatsArgs),
+    /// Execute a manifest written by --write-manifest (or a session
+    /// file), with the paths and settings recorded in it for each input
+    Apply(ApplyArgs),
+    /// Denoise a synthetic noisy image and check the result against
+    /// known bounds, as a smoke test of the installation
+    SelfTest,
+    /// Check properties the solver must have (outputs within the range of
+    /// the input, smoothing increasing as λ decreases, denoising an output
+    /// again changing it no more than the first time) on random synthetic
+    /// images
+    CheckInvariants(CheckInvariantsArgs),
+    /// Check that the chain of hashes of an audit log written by
+    /// --audit-log is intact, i.e. that no entry was modified or removed
+    CheckAudit(CheckAuditArgs),
+    /// Print the λ value and settings a result was solved with, saved
+    /// next to it by --save-params
+    ParamsOf(ParamsOfArgs),
+    /// Print the input and output formats, sample formats and features
+    /// supported by this build
+    ListFormats(ListFormatsArgs),
+    /// Time the operations the solver is made of (gradient, divergence,
+    /// dual and primal steps, whole iterations) and the conversions around
+    /// it on images of several sizes, printing the timings as JSON
+    BenchKernels(BenchKernelsArgs),
+    /// Convert an image to another format (that of the extension of the
+    /// output) or sample depth, with the same decoding and encoding as
+    /// denoising
+    Convert(ConvertArgs),
+    /// Make aligned (noisy, denoised, clean) triples from clean images,
+    /// with noise of known standard deviations, and an index of them, as
+    /// datasets for learned denoisers
+    Dataset(DatasetArgs),
+}
+
+#[derive(Args, Serialize, Deserialize, Debug, Clone)]
+struct DenoiseArgs {
+    /// Path of input image
+    #[arg(short, long)]
+    input_image: PathBuf,
+    /// Path of folder in which output images should be saved
+    #[arg(short, long)]
+    output_folder: PathBuf,
+    /// How to make the names of the outputs valid on the filesystem they
+    /// are saved to
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<names::Policy>(),
+        default_value_t = names::Policy::Keep,
+    )]
+    sanitize_names: names::Policy,
+    /// Transliterate the accented letters of the input's name to ASCII
+    /// in the names of the outputs (e.g. `é` as `e`), replacing other
+    /// non-ASCII characters by `_`
+    #[arg(long)]
+    ascii_names: bool,
+    /// How to write the lambda value in the names of the outputs
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<names::LambdaFormat>(),
+        default_value_t = names::LambdaFormat::Decimal,
+    )]
+    lambda_format: names::LambdaFormat,
+    /// Maximum number of iterations
+    #[arg(short, long, required_unless_present_any = ["auto_iter", "like"])]
+    max_iter: Option<u32>,
+    /// Choose the maximum number of iterations for each lambda value
+    /// from the convergence rate measured on a short probe, predicted
+    /// to reach the threshold; --max-iter, if given, caps it
+    #[arg(long)]
+    auto_iter: bool,
+    /// How the solver's step sizes evolve over the iterations
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<solver::Acceleration>(),
+        default_value_t = solver::Acceleration::Gamma,
+    )]
+    acceleration: solver::Acceleration,
+    /// Floating point precision of the solver; `auto` solves a downscaled
+    /// copy of the input in both first, and picks f32 if it makes no
+    /// visible difference
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<precision::Choice>(),
+        default_value_t = precision::Choice::Auto,
+    )]
+    precision: precision::Choice,
+    /// Solve all channels even when they are (nearly) the same, as in
+    /// grayscale images stored as RGB, which are otherwise solved as a
+    /// single gray channel replicated on output
+    #[arg(long)]
+    no_gray_shortcut: bool,
+    /// Denoise the two fields (even and odd rows) of interlaced frames, as
+    /// in broadcast material, separately so that the combing between them
+    /// isn't smeared across both; `auto` does so if the input shows combing
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<interlace::Mode>(),
+        default_value_t = interlace::Mode::Off,
+    )]
+    interlaced: interlace::Mode,
+    /// Also save a snapshot of the result after every this many
+    /// iterations, as `_iteration_<n>` images
+    #[arg(long)]
+    snapshot_every: Option<std::num::NonZeroU32>,
+    /// Also save a snapshot of the result at the first iteration whose
+    /// residual is under each power of ten (1e-1, 1e-2...), as
+    /// `_residual_1e-<n>` images
+    #[arg(long)]
+    snapshot_at_residuals: bool,
+    /// Compute the energy (the objective the solver minimizes) after
+    /// every iteration, logged with the residuals (-vvv) and kept in the
+    /// --report, rather than only once it stops
+    #[arg(long)]
+    log_energy: bool,
+    /// Keep the intermediate files of each λ value (the images of
+    /// --snapshot-every and --snapshot-at-residuals) in a folder of its own in
+    /// this one instead of the output folder, removed once its output is
+    /// saved
+    #[arg(long, value_name = "DIR")]
+    #[serde(skip)]
+    scratch_dir: Option<PathBuf>,
+    /// Refuse to start unless this much space (e.g. `10G`) would be left
+    /// free on each filesystem after saving the outputs, whose size is
+    /// estimated before starting
+    #[arg(long, value_name = "SIZE", default_value = "0")]
+    #[serde(skip)]
+    min_free_space: space::Bytes,
+    /// Show the progress of the sweep on stderr, with an estimate of the
+    /// time left from how fast the residual of each solve is falling: as a
+    /// status line (`bar`, if given alone) or as lines of plain text
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<progress::Style>(),
+        value_name = "STYLE",
+        num_args = 0..=1,
+        default_missing_value = "bar"
+    )]
+    #[serde(skip)]
+    progress: Option<progress::Style>,
+    /// Print how the λ values, the step sizes, the stopping criteria and
+    /// the threads were derived, from what and with which formulas, before
+    /// solving
+    #[arg(long)]
+    #[serde(skip)]
+    explain: bool,
+    /// Re-open each saved output to check that it decodes with the
+    /// expected dimensions and bit depth
+    #[arg(long)]
+    verify_output: bool,
+    /// With `--verify-output`, also check that the checksum of the saved
+    /// samples matches the result's
+    #[arg(long, requires = "verify_output")]
+    verify_checksum: bool,
+    /// Solve each λ value a second time on a different number of threads,
+    /// failing it unless both results are bit-identical
+    #[arg(long)]
+    #[serde(skip)]
+    determinism_check: bool,
+    /// Save the exact outputs (at full precision, before --output-scale)
+    /// in this folder, for later runs to be checked against with
+    /// --compare-golden
+    #[arg(long, value_name = "DIR")]
+    #[serde(skip)]
+    record_golden: Option<PathBuf>,
+    /// Check the outputs against those saved with --record-golden in this
+    /// folder, failing if any sample differs by more than --tolerance
+    #[arg(long, value_name = "DIR")]
+    #[serde(skip)]
+    compare_golden: Option<PathBuf>,
+    /// Largest difference from the golden outputs allowed by
+    /// --compare-golden, in 8-bit levels
+    #[arg(long, default_value_t = 1e-6, requires = "compare_golden")]
+    #[serde(skip)]
+    tolerance: f64,
+    /// Convergence threshold
+    #[arg(
+        short,
+        long,
+        required_unless_present_any = ["stop_at_delta", "stop_on_gap", "like"],
+    )]
+    convergence_threshold: Option<f64>,
+    /// Stop once the root-mean-square change per pixel between
+    /// iterations is below this fraction of the full range, given as a
+    /// fraction or as levels out of a full scale (e.g. `0.1/255`);
+    /// translated into a --convergence-threshold for the input
+    #[arg(long, conflicts_with = "convergence_threshold")]
+    stop_at_delta: Option<convergence::Delta>,
+    /// Stop once the primal-dual gap (the energy less the objective of the
+    /// dual problem, which bounds the smallest energy from below) relative
+    /// to the energy is below this, rather than on the change between
+    /// iterations, which may be small while the solve is still far from
+    /// the minimum; measured every iteration
+    #[arg(
+        long,
+        value_name = "EPSILON",
+        conflicts_with_all = ["convergence_threshold", "stop_at_delta", "auto_iter"],
+    )]
+    stop_on_gap: Option<f64>,
+    /// Starting range for lambda values
+    #[arg(short = 's', long, required_unless_present = "like")]
+    start_lambda: Option<f64>,
+    /// End range for lambda values
+    #[arg(short = 'e', long, required_unless_present = "like")]
+    end_lambda: Option<f64>,
+    /// Number of steps, i.e. lambda values to use;
+    /// Cannot be zero. `-t=1` will produce a single output
+    /// using the --start-lambda value
+    #[arg(short = 't', long, required_unless_present = "like")]
+    steps: Option<std::num::NonZeroUsize>,
+    /// Run with values the solver has no meaning for (e.g. a lambda of 0,
+    /// or a convergence threshold that can never be met), which are
+    /// refused otherwise, to study edge cases on purpose
+    #[arg(long)]
+    force: bool,
+    /// Leave blown highlights and crushed blacks (samples at either end of
+    /// the 8-bit range) out of the data term, so that they do not pull
+    /// their neighbours toward them into halos, and keep them as they were
+    #[arg(long)]
+    protect_clipped: bool,
+    /// Weight of the data term of the samples of --protect-clipped, from 0
+    /// (left out) to 1 (as any other)
+    #[arg(long, default_value_t = 0.0, requires = "protect_clipped")]
+    clipped_weight: f64,
+    /// Deband smooth gradients (e.g. skies of heavily compressed JPEGs)
+    /// whose steps are this many levels apart (1 by default): each sample
+    /// is kept within half a step of the input, so that the smoothing
+    /// fills in the steps without moving past them, and integer outputs
+    /// are dithered so that they do not band again; best with small
+    /// lambda values
+    #[arg(
+        long,
+        value_name = "LEVELS",
+        num_args = 0..=1,
+        default_missing_value = "1",
+    )]
+    deband: Option<f64>,
+    /// Solve the input with the exact λ value and settings of a previous
+    /// output saved with --save-params, instead of a sweep; only the
+    /// settings of the solver and the processing are taken from it, and
+    /// only those not given on the command line
+    #[arg(long, conflicts_with_all = ["start_lambda", "end_lambda", "steps"])]
+    #[serde(skip)]
+    like: Option<PathBuf>,
+    /// Ids of the arguments given on the command line, rather than left to
+    /// their defaults, which --like does not replace.
+    #[arg(skip)]
+    #[serde(skip)]
+    given: Vec<String>,
+    /// Save the λ value and settings of each output next to it, as a
+    /// `.json` sidecar, to be read back by `params-of` or `--like`
+    #[arg(long)]
+    save_params: bool,
+    /// Embed the SHA-256 of the input file in each PNG output, as a
+    /// `Source SHA-256` text chunk (it is also recorded in the sidecars of
+    /// --save-params and in the --journal)
+    #[arg(long)]
+    embed_source_hash: bool,
+    /// Creator stamped into each PNG output, in its XMP metadata
+    #[arg(long, value_name = "NAME")]
+    #[serde(skip)]
+    creator: Option<String>,
+    /// Copyright notice stamped into each PNG output, in its XMP metadata
+    #[arg(long, value_name = "NOTICE")]
+    #[serde(skip)]
+    copyright: Option<String>,
+    /// Stamp a note of how each PNG output was processed (the program, its
+    /// version and the λ value and iterations solved with) into its XMP
+    /// metadata
+    #[arg(long)]
+    #[serde(skip)]
+    processing_note: bool,
+    /// Refuse to run if any output (image, sidecar, report, plot,
+    /// journal, session or manifest) would overwrite one of the input
+    /// files
+    #[arg(long)]
+    no_clobber_inputs: bool,
+    /// Path of a newline-delimited JSON journal to which to append a
+    /// record of each output (settings, input hash, timing and metrics),
+    /// kept across runs
+    #[arg(long)]
+    journal: Option<PathBuf>,
+    /// Maximum parallelism to use
+    /// If larger than the available parallelism it won't
+    /// have any effect
+    #[arg(long, default_value_t = std::num::NonZeroUsize::MAX)]
+    max_parallelism: std::num::NonZeroUsize,
+    /// Solve images with fewer pixels than this sequentially, without
+    /// spawning any threads; measured on this machine by default
+    #[arg(long)]
+    sequential_below: Option<std::num::NonZeroU64>,
+    /// How many lambda values to solve at the same time; by default as
+    /// many as there are threads to spare
+    #[arg(long)]
+    jobs: Option<std::num::NonZeroUsize>,
+    /// How many threads each lambda value is solved with (splitting its
+    /// color channels); by default the threads left over by the jobs
+    #[arg(long)]
+    threads_per_task: Option<std::num::NonZeroUsize>,
+    /// Also save a false-color heat map of the per-pixel change
+    /// between the input and each output, with a scale bar
+    #[arg(long)]
+    diff_heatmap: bool,
+    /// Also save the dual variables of the final iterate of each lambda
+    /// value, as `_dual.npy` arrays of f64 indexed by x, y, channel and
+    /// the axis of the gradient (0 for x, 1 for y), for research use
+    #[arg(long)]
+    debug_export: bool,
+    /// Also save this many patches of each output that changed the
+    /// most from the input, each next to the same patch of the input, as
+    /// `_gallery_<n>` images from the most changed
+    #[arg(long, value_name = "COUNT")]
+    gallery: Option<std::num::NonZeroUsize>,
+    /// Side in pixels of the patches of --gallery
+    #[arg(
+        long,
+        default_value_t = 64,
+        value_parser = clap::value_parser!(u32).range(1..),
+    )]
+    gallery_patch_size: u32,
+    /// Also save this many blends between the outputs of each pair of
+    /// adjacent lambda values, for previews (e.g. smooth animations of the
+    /// sweep) without solving more, as `_approximation` images; denoising
+    /// is not linear in lambda, so they only approximate what solving would
+    /// give
+    #[arg(long, value_name = "COUNT")]
+    interpolate_outputs: Option<std::num::NonZeroUsize>,
+    /// Staircasing score (fraction of smooth ramp patches turned
+    /// into flat plateaus) above which a lambda value is flagged
+    #[arg(long, default_value_t = 0.5)]
+    staircase_threshold: f64,
+    /// Path of a JSON file in which to save the sweep summary
+    #[arg(long)]
+    report: Option<PathBuf>,
+    /// Path of a CSV file in which to save the sweep summary, with one
+    /// row per lambda value
+    #[arg(long)]
+    report_csv: Option<PathBuf>,
+    /// Path of a clean version of the input (e.g. from before noise was
+    /// added to it) against which to measure the PSNR and SSIM of each
+    /// output; given several times or separated by commas (e.g. clean
+    /// frames of a static scene), against their mean and each of them
+    #[arg(long, value_delimiter = ',')]
+    reference: Vec<PathBuf>,
+    /// Also denoise the input with an external method (e.g. a script
+    /// running a learned model), given as `NAME=COMMAND`, and report its
+    /// result next to those of the sweep; the command is run by the shell
+    /// with `{input}` and `{output}` replaced by the path of the image to
+    /// denoise and the path to save its result at (as PNG, or EXR for
+    /// floating point inputs), and may be given several times; only taken
+    /// from the command line, never from saved parameters
+    #[arg(long, value_name = "NAME=COMMAND")]
+    #[serde(skip)]
+    compare_with: Vec<external::Method>,
+    /// Path of an SVG file in which to plot the PSNR, SSIM and time taken
+    /// against the lambda values of the sweep
+    #[cfg(feature = "plot")]
+    #[arg(long, requires = "reference")]
+    plot: Option<PathBuf>,
+    /// Path of an SVG file in which to plot the L-curve of the sweep
+    /// (residual norm against total variation), with its corner
+    #[cfg(feature = "plot")]
+    #[arg(long)]
+    plot_l_curve: Option<PathBuf>,
+    /// Instead of a sweep, search between the start and end lambda values
+    /// for the one whose output differs from the input by as much as the
+    /// noise, with at most --steps solves
+    #[arg(long, conflicts_with_all = ["interactive", "write_manifest"])]
+    discrepancy: bool,
+    /// Standard deviation of the noise per sample (in 8-bit levels) for
+    /// --discrepancy, if known; estimated from the input otherwise
+    #[arg(long, requires = "discrepancy")]
+    noise_sigma: Option<f64>,
+    /// Region `x,y,w,h` of the input known to be flat (e.g. a gray
+    /// card or a defocused sky) in which to measure the noise, instead
+    /// of estimating it over the whole image
+    #[arg(long)]
+    noise_patch: Option<region::Region>,
+    /// Path of a dark frame (taken with the same exposure and no light)
+    /// to subtract from the input before denoising
+    #[arg(long)]
+    dark_frame: Option<PathBuf>,
+    /// Path of a flat field (an evenly lit exposure) with which to
+    /// correct the input's uneven sensitivity before denoising
+    #[arg(long)]
+    flat_field: Option<PathBuf>,
+    /// Comma separated x coordinates of defective sensor columns to
+    /// reconstruct from their neighbours before denoising
+    #[arg(long, value_delimiter = ',')]
+    bad_columns: Vec<u32>,
+    /// Comma separated y coordinates of defective sensor rows to
+    /// reconstruct from their neighbours before denoising
+    #[arg(long, value_delimiter = ',')]
+    bad_rows: Vec<u32>,
+    /// Detect defective sensor rows and columns (whose mean stands out
+    /// from their neighbours') and reconstruct them before denoising
+    #[arg(long)]
+    detect_bad_lines: bool,
+    /// How many robust standard deviations a line must stand out from
+    /// its neighbours to be detected as defective
+    #[arg(long, default_value_t = 6.0)]
+    bad_line_threshold: f64,
+    /// For JPEG inputs, smooth the steps across the boundaries of their
+    /// 8x8 blocks before denoising, so that they are not kept as if they
+    /// were edges of the scene; steps over this many levels (8 by default)
+    /// are taken as edges that happen to fall on a boundary
+    #[arg(
+        long,
+        value_name = "LEVELS",
+        num_args = 0..=1,
+        default_missing_value = "8",
+    )]
+    deblock: Option<f64>,
+    /// For floating point (e.g. EXR) inputs, whose results are saved as
+    /// EXR, also save a tone-mapped PNG preview of each result
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<tonemap::ToneMap>(),
+    )]
+    tone_map: Option<tonemap::ToneMap>,
+    /// What to do with values of the results outside of the 8-bit range,
+    /// as the solver may leave near bright edges: the samples out of range
+    /// are logged with -vv, and saved in the --report (floating point
+    /// results are saved as they are)
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<range::Policy>(),
+        default_value_t = range::Policy::Clamp,
+    )]
+    out_of_range: range::Policy,
+    /// Downscale the input before denoising so that neither side is
+    /// larger than this many pixels
+    #[arg(long)]
+    max_dimension: Option<std::num::NonZeroU32>,
+    /// Resize each result before saving it, as a percentage (`50%`) or
+    /// a factor (`0.5`)
+    #[arg(long)]
+    output_scale: Option<resize::Scale>,
+    /// Filter used for --max-dimension and --output-scale
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<resize::ResizeFilter>(),
+        default_value_t = resize::ResizeFilter::Lanczos,
+    )]
+    resize_filter: resize::ResizeFilter,
+    /// Boundary condition for the gradient operator at the edges of the
+    /// image; other than periodic, it is applied by padding the image
+    /// before denoising and cropping the result afterwards
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<boundary::Boundary>(),
+        default_value_t = boundary::Boundary::Periodic,
+    )]
+    boundary: boundary::Boundary,
+    /// Pixels of padding added on each side for --boundary
+    #[arg(long, default_value_t = 16)]
+    boundary_padding: usize,
+    /// Region `x,y,w,h` of the input to denoise, leaving the rest out;
+    /// the output holds only this region unless --composite is given
+    #[arg(long, conflicts_with = "max_dimension")]
+    patch: Option<region::Region>,
+    /// Composite the denoised --patch back into the untouched original,
+    /// blending it in smoothly over --patch-margin pixels
+    #[arg(long, requires = "patch")]
+    composite: bool,
+    /// Pixels around the --patch also given to the solver as context,
+    /// and over which the result is blended in for --composite
+    #[arg(long, default_value_t = 16)]
+    patch_margin: u32,
+    /// First solve the sweep on a downscaled draft of the input, then
+    /// prompt for the λ value to solve at full resolution (or to skip)
+    #[arg(long)]
+    #[serde(skip)]
+    interactive: bool,
+    /// Largest side in pixels of the drafts for --interactive
+    #[arg(long, default_value_t = std::num::NonZeroU32::new(512).unwrap())]
+    draft_dimension: std::num::NonZeroU32,
+    /// Path of a session file in which to record the choice made for
+    /// the input with --interactive, to replay with --apply-session
+    #[arg(long, requires = "interactive")]
+    #[serde(skip)]
+    session: Option<PathBuf>,
+    /// Only record the choice in the --session, leaving the full
+    /// resolution solve to --apply-session
+    #[arg(long, requires = "session")]
+    #[serde(skip)]
+    defer_solve: bool,
+    /// Instead of denoising, add the planned outputs with their
+    /// settings to a manifest, to review and execute with `apply`
+    #[arg(long, conflicts_with = "interactive")]
+    #[serde(skip)]
+    write_manifest: Option<PathBuf>,
+}
+
+impl DenoiseArgs {
+    /// Whether the argument with the id `id` was given on the command line.
+    fn given(&self, id: &str) -> bool {
+        self.given.iter().any(|given| given == id)
+    }
+}
+
+impl Cli {
+    /// Parses `args` (the name of the program first), noting which of the
+    /// denoising arguments were given rather than left to their defaults.
+    /// The language of --lang (or of the locale) is set first, for the help
+    /// and the errors of the parsing to be in it.
+    fn try_parse_args<I, T>(args: I) -> Result<Self, locale::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let args: Vec<std::ffi::OsString> =
+            args.into_iter().map(Into::into).collect();
+        locale::set_lang(
+            locale::Lang::from_args(&args)
+                .unwrap_or_else(locale::Lang::from_env),
+        );
+        let matches = locale::command(Cli::command())
+            .try_get_matches_from(args)
+            .map_err(clap::Error::apply)?;
+        let mut cli =
+            Cli::from_arg_matches(&matches).map_err(clap::Error::apply)?;
+        if let Some(args) = &mut cli.denoise {
+            args.given = matches
+                .ids()
+                .filter(|id| {
+                    matches.value_source(id.as_str())
+                        == Some(clap::parser::ValueSource::CommandLine)
+                })
+                .map(|id| id.to_string())
+                .collect();
+        }
+        Ok(cli)
+    }
+}
+
+#[derive(Args, Debug)]
+struct StatsArgs {
+    /// Path of image to analyse
+    image: PathBuf,
+    /// Number of histogram bins
+    #[arg(
+        long,
+        default_value_t = 16,
+        value_parser = clap::value_parser!(u16).range(1..=256),
+    )]
+    bins: u16,
+    /// Print the statistics as JSON
+    #[arg(long)]
+    json: bool,
+    /// Region `x,y,w,h` of the image known to be flat in which to
+    /// measure the noise, instead of estimating it over the whole image
+    #[arg(long)]
+    noise_patch: Option<region::Region>,
+}
+
+#[derive(Args, Debug)]
+struct CheckInvariantsArgs {
+    /// Number of random images to check
+    #[arg(long, default_value_t = 20)]
+    cases: u32,
+    /// Seed of the random images, printed by every run so that it can be
+    /// repeated [default: from the clock]
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+struct CheckAuditArgs {
+    /// Path of the audit log
+    log: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct ListFormatsArgs {
+    /// Print the capabilities as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct BenchKernelsArgs {
+    /// Sides in pixels of the square images to time on, separated by
+    /// commas
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "256,512,1024",
+        value_parser = clap::value_parser!(u64).range(2..),
+    )]
+    sizes: Vec<u64>,
+    /// Times to run each operation, of which the median and the minimum
+    /// are reported
+    #[arg(long, default_value_t = 10)]
+    repeats: usize,
+}
+
+#[derive(Args, Debug)]
+struct ConvertArgs {
+    /// Path of the image to convert
+    input: PathBuf,
+    /// Path to save the converted image to, in the format of its extension
+    output: PathBuf,
+    /// Bits per sample of the output (32 for floating point), as supported
+    /// by its format; those of the input by default
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<convert::BitDepth>(),
+    )]
+    bit_depth: Option<convert::BitDepth>,
+}
+
+#[derive(Args, Debug)]
+struct DatasetArgs {
+    /// Paths of the clean images
+    #[arg(required = true)]
+    clean: Vec<PathBuf>,
+    /// Path of folder in which the triples and their index should be saved
+    #[arg(short, long)]
+    output_folder: PathBuf,
+    /// Comma separated standard deviations (in 8-bit levels) of the
+    /// gaussian noise to add, each making triples of its own
+    #[arg(long, value_delimiter = ',', default_value = "15")]
+    noise_sigma: Vec<f64>,
+    /// Number of noisy copies of each clean image for each standard
+    /// deviation, each with noise of its own
+    #[arg(long, default_value_t = std::num::NonZeroU32::MIN)]
+    copies: std::num::NonZeroU32,
+    /// Lambda value to denoise the noisy copies with
+    #[arg(short, long)]
+    lambda: f64,
+    /// Maximum number of iterations
+    #[arg(short, long, default_value_t = 500)]
+    max_iter: u32,
+    /// Convergence threshold
+    #[arg(short, long, default_value_t = 1e-4)]
+    convergence_threshold: f64,
+    /// Seed of the noise of the first triple, each of the others taking
+    /// the next one, so that a dataset can be made again identically
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+#[derive(Args, Debug)]
+struct ApplyArgs {
+    /// Path of manifest to execute
+    manifest: PathBuf,
+    /// Order in which to solve the entries
+    #[arg(
+        long,
+        value_enum,
+        value_parser = locale::values::<session::Order>(),
+        default_value_t = session::Order::Manifest,
+    )]
+    order: session::Order,
+    /// Path of a file in which to record the progress through the
+    /// entries, to resume from if it already exists (e.g. after an
+    /// interruption), skipping the entries done
+    #[arg(long)]
+    queue: Option<PathBuf>,
+    /// Times to attempt each entry of the --queue, across runs, before
+    /// giving up on it
+    #[arg(long, default_value_t = std::num::NonZeroU32::new(3).unwrap(), requires = "queue")]
+    max_attempts: std::num::NonZeroU32,
+}
+
+#[derive(Args, Debug)]
+struct ParamsOfArgs {
+    /// Path of a result saved with --save-params
+    image: PathBuf,
+}
+
+/// How errors of the command line end the run.
+trait Fail {
+    /// Exits with the error as clap does, or, within `harness::run`, unwinds
+    /// back to it with the error, for the harness to return.
+    fn fail(self) -> !;
+}
+
+impl Fail for locale::Error {
+    fn fail(self) -> ! {
+        match harness::is_active() {
+            true => std::panic::resume_unwind(Box::new(self)),
+            false => self.exit(),
+        }
+    }
+}
+
+/// An error of the command line with `message`, to fail with.
+fn cli_error(
+    kind: clap::error::ErrorKind,
+    message: impl std::fmt::Display,
+) -> locale::Error {
+    locale::error(&mut Cli::command(), kind, message)
+}
+
+fn validate_args(args: &DenoiseArgs) {
+    if !storage::get().is_file(&args.input_image) {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!("`input_image` must be a valid file"),
+        )
+        .fail();
+    }
+    validate_image_size("input_image", &args.input_image);
+
+    if !storage::get().is_dir(&args.output_folder) {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!("`output_path` must be a valid directory"),
+        )
+        .fail();
+    }
+
+    for (name, folder) in [
+        ("record_golden", &args.record_golden),
+        ("compare_golden", &args.compare_golden),
+        ("scratch_dir", &args.scratch_dir),
+    ] {
+        if folder
+            .as_ref()
+            .is_some_and(|folder| !storage::get().is_dir(folder))
+        {
+            cli_error(
+                clap::error::ErrorKind::ValueValidation,
+                tr!("`{}` must be a valid directory", name),
+            )
+            .fail();
+        }
+    }
+
+    if !args.force {
+        validate_domain(args);
+    }
+
+    // samples left out of the data term, with no band to bound them, make
+    // the dual objective unbounded, and the gap infinite
+    if args.stop_on_gap.is_some()
+        && args.protect_clipped
+        && args.clipped_weight == 0.0
+        && args.deband.is_none()
+    {
+        cli_error(
+            clap::error::ErrorKind::ArgumentConflict,
+            tr!("--stop-on-gap cannot be measured with clipped samples left \
+                 out of the data term: give a --clipped-weight greater than 0"),
+        )
+        .fail();
+    }
+
+    // as clap checks on the command line, for settings taken by --like
+    if args.stop_on_gap.is_some() && args.auto_iter {
+        cli_error(
+            clap::error::ErrorKind::ArgumentConflict,
+            tr!("--stop-on-gap cannot be combined with --auto-iter"),
+        )
+        .fail();
+    }
+
+    if args.start_lambda.partial_cmp(&args.end_lambda)
+        != Some(std::cmp::Ordering::Less)
+        && args.like.is_none()
+    {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!("`start_lambda` must be smaller than `end_lambda`"),
+        )
+        .fail();
+    }
+
+    // either alone counts as a single thread or job of the other
+    let jobs = args.jobs.map_or(1, |jobs| jobs.get());
+    let threads = args.threads_per_task.map_or(1, |threads| threads.get());
+    if jobs.saturating_mul(threads) > args.max_parallelism.get() {
+        cli_error(
+            clap::error::ErrorKind::ArgumentConflict,
+            tr!(
+                "{} `jobs` of {} `threads_per_task` each are more than the \
+                 `max_parallelism` of {}",
+                jobs,
+                threads,
+                args.max_parallelism
+            ),
+        )
+        .fail();
+    }
+
+    validate_noise_patch(&args.input_image, args.noise_patch);
+
+    let (width, height) =
+        storage::image_dimensions(storage::get(), &args.input_image)
+            .expect("image could not be open");
+
+    if let Some(patch) = args.patch {
+        if !patch.fits_in(width, height) {
+            cli_error(
+                clap::error::ErrorKind::ValueValidation,
+                tr!(
+                    "`patch` {} must fit in the {}x{} image",
+                    patch,
+                    width,
+                    height
+                ),
+            )
+            .fail();
+        }
+        if args.noise_patch.is_some() && !args.composite {
+            cli_error(
+                clap::error::ErrorKind::ArgumentConflict,
+                tr!("`noise_patch` is in the coordinates of the whole image, \
+                     it requires `composite` when a `patch` is given"),
+            )
+            .fail();
+        }
+    }
+
+    for (name, lines, len) in [
+        ("bad_columns", &args.bad_columns, width),
+        ("bad_rows", &args.bad_rows, height),
+    ] {
+        if lines.iter().any(|&line| line >= len) {
+            cli_error(
+                clap::error::ErrorKind::ValueValidation,
+                tr!("`{}` must be smaller than {}", name, len),
+            )
+            .fail();
+        }
+    }
+
+    for (name, frame) in [
+        ("dark_frame", args.dark_frame.as_ref()),
+        ("flat_field", args.flat_field.as_ref()),
+    ]
+    .into_iter()
+    .chain(args.reference.iter().map(|path| ("reference", Some(path))))
+    {
+        if let Some(frame) = frame {
+            validate_matching_image(name, frame, &args.input_image);
+        }
+    }
+}
+
+/// Checks that the numbers given are in the domain the solver and the
+/// measurements have a meaning for, as values outside of it (e.g. a λ value
+/// of 0, or NaN) run without failing but give nonsensical results, with a
+/// suggestion of what to give instead.
+fn validate_domain(args: &DenoiseArgs) {
+    let invalid = |name: &str, value: &dyn std::fmt::Display, hint: String| {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!(
+                "`{}` cannot be {}: {} (or give --force to run it anyway)",
+                name,
+                value,
+                hint
+            ),
+        )
+        .fail()
+    };
+
+    for (name, value, hint) in [
+        (
+            "start_lambda",
+            args.start_lambda,
+            tr!("lambda must be a finite number greater than 0, e.g. `-s \
+                 0.001`"),
+        ),
+        (
+            "end_lambda",
+            args.end_lambda,
+            tr!("lambda must be a finite number greater than 0, e.g. `-e 1`"),
+        ),
+        (
+            "convergence_threshold",
+            args.convergence_threshold,
+            tr!("it is the relative change between iterations to stop at, a \
+                 finite number greater than 0, e.g. `-c 1e-6`"),
+        ),
+        (
+            "stop_on_gap",
+            args.stop_on_gap,
+            tr!("it is the primal-dual gap relative to the energy to stop \
+                 at, a finite number greater than 0, e.g. `--stop-on-gap \
+                 1e-4`"),
+        ),
+        (
+            "noise_sigma",
+            args.noise_sigma,
+            tr!("it is a standard deviation in 8-bit levels, greater than 0; \
+                 leave it out to estimate it from the input"),
+        ),
+        (
+            "bad_line_threshold",
+            Some(args.bad_line_threshold),
+            tr!("it is a number of standard deviations greater than 0, e.g. 6"),
+        ),
+        (
+            "deblock",
+            args.deblock,
+            tr!("it is a step in 8-bit levels, greater than 0, e.g. 8"),
+        ),
+        (
+            "deband",
+            args.deband,
+            tr!("it is the step of the banding in 8-bit levels, greater than \
+                 0, e.g. 1 for 8-bit gradients"),
+        ),
+    ] {
+        if let Some(value) = value.filter(|v| !(v.is_finite() && *v > 0.0)) {
+            invalid(name, &value, hint);
+        }
+    }
+
+    if args.max_iter == Some(0) {
+        invalid(
+            "max_iter",
+            &0,
+            tr!("at least one iteration is needed, e.g. `-m 1000`"),
+        );
+    }
+    if !(args.tolerance.is_finite() && args.tolerance >= 0.0) {
+        invalid(
+            "tolerance",
+            &args.tolerance,
+            tr!("it is a difference in 8-bit levels, 0 or more"),
+        );
+    }
+    if !(0.0..=1.0).contains(&args.staircase_threshold) {
+        invalid(
+            "staircase_threshold",
+            &args.staircase_threshold,
+            tr!("it is a fraction of the ramp patches, from 0 to 1"),
+        );
+    }
+    if !(0.0..=1.0).contains(&args.clipped_weight) {
+        invalid(
+            "clipped_weight",
+            &args.clipped_weight,
+            tr!("it is a weight, from 0 (left out) to 1 (as any other sample)"),
+        );
+    }
+}
+
+fn validate_matching_image(name: &str, frame: &Path, image: &Path) {
+    if !storage::get().is_file(frame) {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!("`{}` must be a valid file", name),
+        )
+        .fail();
+    }
+
+    let expected = storage::image_dimensions(storage::get(), image)
+        .expect("image could not be open");
+    let found = storage::image_dimensions(storage::get(), frame)
+        .unwrap_or_else(|error| unreadable(name, frame, &error));
+    if expected != found {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!(
+                "`{}` must have the same dimensions as the input, {}x{}",
+                name,
+                expected.0,
+                expected.1
+            ),
+        )
+        .fail();
+    }
+}
+
+/// Exits with an error if any of `outputs` is the same file as one of the
+/// inputs of `args`, which are left untouched with --no-clobber-inputs.
+fn validate_no_clobber(args: &DenoiseArgs, outputs: &[PathBuf]) {
+    let inputs: Vec<PathBuf> = [
+        Some(&args.input_image),
+        args.dark_frame.as_ref(),
+        args.flat_field.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(&args.reference)
+    .filter_map(|input| input.canonicalize().ok())
+    .collect();
+    // outputs that don't exist yet cannot be any of the inputs
+    let clobbered = outputs.iter().find(|output| {
+        output
+            .canonicalize()
+            .is_ok_and(|output| inputs.contains(&output))
+    });
+    if let Some(output) = clobbered {
+        cli_error(
+            clap::error::ErrorKind::ArgumentConflict,
+            tr!(
+                "output `{}` would overwrite an input",
+                output.to_string_lossy()
+            ),
+        )
+        .fail();
+    }
+}
+
+/// Loads an input with `input::load`, exiting with an error if it takes
+/// more memory to decode than --max-decoded-size allows.
+fn load_input(
+    name: &str,
+    path: &Path,
+) -> (ImageArray<Array3<f64>>, input::SampleFormat) {
+    match input::load(path) {
+        Ok(loaded) => loaded,
+        Err(image::ImageError::Limits(_)) => cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!(
+                "{} {} is over the limits of --max-megapixels or \
+                 --max-decoded-size",
+                name,
+                path.to_string_lossy()
+            ),
+        )
+        .fail(),
+        Err(error) => unreadable(name, path, &error),
+    }
+}
+
+/// Exits with an error for an input at `path` that could not be decoded.
+fn unreadable(name: &str, path: &Path, error: &image::ImageError) -> ! {
+    cli_error(
+        clap::error::ErrorKind::ValueValidation,
+        tr!("{} {} could not be open: {}", name, path.to_string_lossy(), error),
+    )
+    .fail()
+}
+
+/// Checks that the image at `path` is within the limits of
+/// --max-megapixels, before anything is decoded.
+fn validate_image_size(name: &str, path: &Path) {
+    let (width, height) = storage::image_dimensions(storage::get(), path)
+        .unwrap_or_else(|error| unreadable(name, path, &error));
+    if !storage::limits().allows(width, height) {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!(
+                "`{}` is {}x{} ({} megapixels), over the limit of \
+                 --max-megapixels",
+                name,
+                width,
+                height,
+                format!("{:.1}", width as f64 * height as f64 / 1e6)
+            ),
+        )
+        .fail();
+    }
+}
+
+fn validate_noise_patch(image: &Path, noise_patch: Option<region::Region>) {
+    let patch = match noise_patch {
+        Some(patch) => patch,
+        None => return,
+    };
+    let (width, height) = storage::image_dimensions(storage::get(), image)
+        .expect("image could not be open");
+    if !patch.fits_in(width, height) {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!(
+                "`noise_patch` {} must fit in the {}x{} image",
+                patch,
+                width,
+                height
+            ),
+        )
+        .fail();
+    }
+}
+
+/// Runs the command line interface with the arguments of the process.
+pub fn run() {
+    let cli = Cli::try_parse_args(std::env::args_os())
+        .unwrap_or_else(|error| error.exit());
+    let mut storage: Box<dyn storage::Storage> = Box::new(storage::FileSystem);
+    if let Some(allowed) = &cli.restrict_paths {
+        storage = Box::new(
+            restrict::Restricted::new(storage, allowed).unwrap_or_else(
+                |error| {
+                    cli_error(
+                        clap::error::ErrorKind::ValueValidation,
+                        tr!("`restrict_paths` must be folders: {}", error),
+                    )
+                    .fail()
+                },
+            ),
+        );
+        for (name, path) in
+            [("log_file", &cli.log_file), ("audit_log", &cli.audit_log)]
+        {
+            if path.as_ref().is_some_and(|path| !storage.allows(path)) {
+                cli_error(
+                    clap::error::ErrorKind::ValueValidation,
+                    tr!("`{}` must be inside `restrict_paths`", name),
+                )
+                .fail();
+            }
+        }
+        if cli
+            .denoise
+            .as_ref()
+            .is_some_and(|args| !args.compare_with.is_empty())
+        {
+            cli_error(
+                clap::error::ErrorKind::ArgumentConflict,
+                tr!("`compare_with` runs commands, whose file access \
+                     `restrict_paths` cannot confine"),
+            )
+            .fail();
+        }
+    }
+    if let Some(rate) = cli.io_limit {
+        storage = Box::new(throttle::Throttled::new(storage, rate));
+    }
+    if let Some(path) = &cli.audit_log {
+        storage = Box::new(audit::Audited::new(storage, path).unwrap_or_else(
+            |error| {
+                cli_error(
+                    clap::error::ErrorKind::Io,
+                    tr!("audit log could not be continued: {}", error),
+                )
+                .fail()
+            },
+        ));
+    }
+    storage::init(storage);
+
+    let verbosity = match cli.verbose {
+        0 => log::LevelFilter::Error,
+        1 => log::LevelFilter::Warn,
+        2 => log::LevelFilter::Info,
+        3 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    let sink: Box<dyn std::io::Write + Send> = match &cli.log_file {
+        Some(path) => Box::new(
+            std::fs::File::create(path).expect("log file could not be created"),
+        ),
+        None => Box::new(std::io::stdout()),
+    };
+    logger::Logger::init(verbosity, sink).unwrap();
+    log::trace!("log level is TRACE");
+
+    let cancel = cancel::Token::new(
+        cli.timeout
+            .map(|timeout| std::time::Duration::from_secs(timeout.get())),
+    );
+    let handler_cancel = cancel.clone();
+    ctrlc::set_handler(move || {
+        if handler_cancel.is_cancelled() {
+            log::logger().flush();
+            std::process::exit(130);
+        }
+        eprintln!("cancelling, press Ctrl-C again to quit immediately");
+        handler_cancel.cancel();
+    })
+    .expect("Ctrl-C handler could not be set");
+
+    let succeeded = execute(cli, &cancel);
+    log::logger().flush();
+    if !succeeded {
+        std::process::exit(1);
+    }
+}
+
+/// Runs the command given by `cli`, returning whether it succeeded.
+fn execute(cli: Cli, cancel: &cancel::Token) -> bool {
+    storage::set_limits(storage::Limits {
+        max_pixels: (cli.max_megapixels * 1e6) as u64,
+        max_decoded_bytes: cli.max_decoded_size.0,
+    });
+    #[cfg(feature = "dicom")]
+    dicom::set_options(dicom::Options {
+        window: cli.dicom_window,
+        format: cli.dicom_format,
+    });
+    match (cli.command, cli.denoise) {
+        (Some(Command::Stats(args)), _) => {
+            print_stats(args);
+            true
+        },
+        (Some(Command::Apply(args)), _) => apply_session(
+            &args.manifest,
+            args.order,
+            args.queue
+                .as_deref()
+                .map(|queue| (queue, args.max_attempts)),
+            cancel,
+        ),
+        (Some(Command::SelfTest), _) => selftest::run(cancel),
+        (Some(Command::CheckAudit(args)), _) => {
+            audit::check(storage::get(), &args.log)
+        },
+        (Some(Command::CheckInvariants(args)), _) => {
+            let seed = args.seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+            });
+            invariants::run(args.cases, seed, cancel)
+        },
+        (Some(Command::ParamsOf(args)), _) => {
+            print_params(&args.image);
+            true
+        },
+        (Some(Command::ListFormats(args)), _) => {
+            let capabilities = capabilities::Capabilities::of_this_build();
+            if args.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&capabilities)
+                        .expect("capabilities could not be serialized")
+                );
+            } else {
+                capabilities.print();
+            }
+            true
+        },
+        (Some(Command::BenchKernels(args)), _) => {
+            let sizes: Vec<usize> =
+                args.sizes.iter().map(|&size| size as usize).collect();
+            match bench::run(&sizes, args.repeats, cancel) {
+                Ok(bench) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&bench)
+                            .expect("timings could not be serialized")
+                    );
+                    true
+                },
+                Err(cancelled) => {
+                    log::error!("{}", cancelled);
+                    false
+                },
+            }
+        },
+        (Some(Command::Convert(args)), _) => convert_image(&args),
+        (Some(Command::Dataset(args)), _) => make_dataset(args, cancel),
+        (None, Some(args)) => denoise(args, None, None, cancel),
+        (None, None) => match cli.apply_session {
+            Some(path) => {
+                apply_session(&path, session::Order::Manifest, None, cancel)
+            },
+            None => unreachable!("clap requires the denoising arguments"),
+        },
+    }
+}
+
+fn print_stats(args: StatsArgs) {
+    validate_image_size("image", &args.image);
+    validate_noise_patch(&args.image, args.noise_patch);
+
+    let img = storage::open_image(storage::get(), &args.image)
+        .expect("image could not be open")
+        .into_rgb8();
+    let stats = stats::ImageStats::new(
+        args.image,
+        &img,
+        args.bins.into(),
+        args.noise_patch,
+    );
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&stats)
+                .expect("statistics could not be serialized")
+        );
+    } else {
+        stats.print();
+    }
+}
+
+/// Converts the input of `args` to the format and depth of its output,
+/// returning whether it could be saved.
+fn convert_image(args: &ConvertArgs) -> bool {
+    if !storage::get().is_file(&args.input) {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!("`input` must be a valid file"),
+        )
+        .fail();
+    }
+    validate_image_size("input", &args.input);
+
+    let img = storage::open_image(storage::get(), &args.input)
+        .expect("image could not be open");
+    let depth = args
+        .bit_depth
+        .unwrap_or_else(|| convert::BitDepth::of(&img));
+    let img = convert::to_depth(img, depth);
+    match storage::save_image(storage::get(), &img, &args.output) {
+        Ok(()) => {
+            log::info!(
+                "converted to {:?}: {}",
+                img.color(),
+                args.output.to_string_lossy()
+            );
+            true
+        },
+        Err(error) => {
+            log::error!(
+                "{} could not be saved as {:?} (its format may take another \
+                 --bit-depth): {}",
+                args.output.to_string_lossy(),
+                img.color(),
+                error
+            );
+            false
+        },
+    }
+}
+
+fn make_dataset(args: DatasetArgs, cancel: &cancel::Token) -> bool {
+    if !storage::get().is_dir(&args.output_folder) {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!("`{}` must be a valid directory", "output_folder"),
+        )
+        .fail();
+    }
+    let mut stems = std::collections::HashSet::new();
+    for clean in &args.clean {
+        if !storage::get().is_file(clean) {
+            cli_error(
+                clap::error::ErrorKind::ValueValidation,
+                tr!("`{}` must be a valid file", clean.to_string_lossy()),
+            )
+            .fail();
+        }
+        validate_image_size("clean image", clean);
+        // the triples are named after the clean images
+        if !stems.insert(clean.file_stem()) {
+            cli_error(
+                clap::error::ErrorKind::ValueValidation,
+                tr!(
+                    "the clean images must have different names: {}",
+                    clean.to_string_lossy()
+                ),
+            )
+            .fail();
+        }
+    }
+    for (name, value) in args
+        .noise_sigma
+        .iter()
+        .map(|&sigma| ("noise_sigma", sigma))
+        .chain([
+            ("lambda", args.lambda),
+            ("convergence_threshold", args.convergence_threshold),
+            ("max_iter", args.max_iter as f64),
+        ])
+    {
+        if !(value.is_finite() && value > 0.0) {
+            cli_error(
+                clap::error::ErrorKind::ValueValidation,
+                tr!("`{}` must be a finite number greater than 0", name),
+            )
+            .fail();
+        }
+    }
+
+    let settings = dataset::Settings {
+        noise_sigmas: args.noise_sigma,
+        copies: args.copies.get(),
+        lambda: args.lambda,
+        max_iter: args.max_iter,
+        convergence_threshold: args.convergence_threshold,
+        seed: args.seed,
+    };
+    dataset::make(&args.clean, &args.output_folder, &settings, cancel)
+}
+
+/// Takes the settings of the solver and the processing of `recorded`, the
+/// settings of the output `like`, into `args`, except for those given on
+/// the command line. The settings of the input (its references and frames,
+/// patches and lines) and of what is saved are left to the command line,
+/// but a recorded frame that changed the result has to be given again.
+fn solve_like(args: &mut DenoiseArgs, recorded: DenoiseArgs, like: &Path) {
+    for (name, frame) in [
+        ("dark_frame", &recorded.dark_frame),
+        ("flat_field", &recorded.flat_field),
+    ] {
+        if let Some(frame) = frame.as_ref().filter(|_| !args.given(name)) {
+            cli_error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                tr!(
+                    "{} was solved with the `{}` {}, which --like does not \
+                     take for another input: give its own",
+                    like.to_string_lossy(),
+                    name,
+                    frame.to_string_lossy()
+                ),
+            )
+            .fail();
+        }
+    }
+
+    // settings that exclude each other are taken together, and only if
+    // none of them is given
+    macro_rules! take {
+        ($([$($setting:ident),+]),* $(,)?) => {
+            $(
+                if !($(args.given(stringify!($setting)))||+) {
+                    $(args.$setting = recorded.$setting;)+
+                }
+            )*
+        };
+    }
+    take!(
+        [start_lambda],
+        [end_lambda],
+        [steps],
+        [max_iter, auto_iter],
+        [convergence_threshold, stop_at_delta, stop_on_gap],
+        [acceleration],
+        [precision],
+        [no_gray_shortcut],
+        [interlaced],
+        [force],
+        [protect_clipped],
+        [clipped_weight],
+        [deband],
+        [detect_bad_lines],
+        [bad_line_threshold],
+        [deblock],
+        [out_of_range],
+        [max_dimension],
+        [output_scale],
+        [resize_filter],
+        [boundary],
+        [boundary_padding],
+    );
+}
+
+/// Loads the parameters saved next to a result by --save-params, exiting
+/// with an error if there are none.
+fn load_params(image: &Path) -> session::Entry {
+    params::load(image).unwrap_or_else(|error| {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!(
+                "no parameters could be read for {} (was it saved with \
+                 --save-params?): {}",
+                image.to_string_lossy(),
+                error
+            ),
+        )
+        .fail()
+    })
+}
+
+fn print_params(image: &Path) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&load_params(image))
+            .expect("parameters could not be serialized")
+    );
+}
+
+/// Solves the entries of a session or manifest in `order`, recording the
+/// progress in a queue, if given with its maximum attempts per entry.
+/// Returns whether none of the solves failed.
+fn apply_session(
+    path: &Path,
+    order: session::Order,
+    queue: Option<(&Path, std::num::NonZeroU32)>,
+    cancel: &cancel::Token,
+) -> bool {
+    let session = session::Session::load(path).unwrap_or_else(|error| {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!("{} could not be read: {}", path.to_string_lossy(), error),
+        )
+        .fail()
+    });
+    let mut queue = queue.map(|(queue_path, max_attempts)| {
+        let inputs: Vec<&Path> = session
+            .entries
+            .iter()
+            .map(|entry| entry.settings.input_image.as_path())
+            .collect();
+        let queue =
+            queue::Queue::open(queue_path, &inputs).unwrap_or_else(|error| {
+                cli_error(
+                    clap::error::ErrorKind::ValueValidation,
+                    tr!(
+                        "{} could not be resumed: {}",
+                        queue_path.to_string_lossy(),
+                        error
+                    ),
+                )
+                .fail()
+            });
+        (queue, max_attempts.get())
+    });
+
+    // the index of each entry in the manifest, by which the queue knows it
+    let mut entries: Vec<(usize, session::Entry)> =
+        session.entries.into_iter().enumerate().collect();
+    match order {
+        session::Order::Manifest => (),
+        // stable, so that entries of the same cost keep their order
+        session::Order::Cost => entries.sort_by_cached_key(|(_, entry)| {
+            std::cmp::Reverse(estimate_cost(&entry.settings).to_bits())
+        }),
+        session::Order::Name => entries.sort_by(|(_, a), (_, b)| {
+            a.settings.input_image.cmp(&b.settings.input_image)
+        }),
+    }
+    let mut succeeded = true;
+    for (index, entry) in entries {
+        if cancel.is_cancelled() {
+            log::error!("cancelled before the rest of the session");
+            return false;
+        }
+        let input = entry.settings.input_image.clone();
+        if let Some((queue, max_attempts)) = &queue {
+            let progress = queue.progress(index);
+            if progress.done {
+                log::info!(
+                    "already done in the queue: {}",
+                    input.to_string_lossy()
+                );
+                continue;
+            }
+            if progress.attempts >= *max_attempts {
+                log::error!(
+                    "giving up after {} attempts: {}",
+                    progress.attempts,
+                    input.to_string_lossy()
+                );
+                succeeded = false;
+                continue;
+            }
+        }
+        match entry.lambda {
+            Some(lambda) => {
+                if let Some((queue, _)) = &mut queue {
+                    queue
+                        .record(index, &input, queue::Event::Started)
+                        .expect("queue could not be saved");
+                }
+                let done = denoise(
+                    entry.settings,
+                    Some(lambda),
+                    entry.output.as_deref(),
+                    cancel,
+                );
+                if let Some((queue, _)) = &mut queue {
+                    let event = if done {
+                        queue::Event::Done
+                    } else if cancel.is_cancelled() {
+                        queue::Event::Cancelled
+                    } else {
+                        queue::Event::Failed
+                    };
+                    queue
+                        .record(index, &input, event)
+                        .expect("queue could not be saved");
+                }
+                succeeded &= done;
+            },
+            None => log::info!(
+                "skipped in session: {}",
+                entry.settings.input_image.to_string_lossy()
+            ),
+        }
+    }
+    succeeded
+}
+
+/// Rough cost of solving an entry of a session, in megapixel-iterations:
+/// the pixels solved (of the patch, or of the input as resized) times the
+/// iterations they may take. Inputs that cannot be read cost nothing, as
+/// they fail right away.
+fn estimate_cost(settings: &DenoiseArgs) -> f64 {
+    let path = &settings.input_image;
+    let (width, height) = match storage::image_dimensions(storage::get(), path)
+    {
+        Ok(dimensions) => dimensions,
+        Err(error) => {
+            log::warn!(
+                "cost of {} not estimated: {}",
+                path.to_string_lossy(),
+                error
+            );
+            return 0.0;
+        },
+    };
+    let (width, height) = (width as f64, height as f64);
+    let scale = settings
+        .max_dimension
+        .map_or(1.0, |max| (max.get() as f64 / width.max(height)).min(1.0));
+    let megapixels = match settings.patch.filter(|_| !settings.composite) {
+        Some(patch) => patch.width as f64 * patch.height as f64,
+        None => width * height * scale * scale,
+    } / 1e6;
+    let iterations = settings.max_iter.unwrap_or(AUTO_ITER_LIMIT) as f64;
+    let cost = megapixels * iterations;
+    log::debug!(
+        "estimated cost of {}: {:.3} megapixel-iterations",
+        path.to_string_lossy(),
+        cost
+    );
+    cost
+}
+
+/// Prints how the settings of the solves of `lambdas` were derived, as by
+/// --explain; `lambda` is the one given to `denoise`, if any.
+fn explain_settings(
+    args: &DenoiseArgs,
+    settings: &DenoiseArgs,
+    img_array: &Array3<f64>,
+    lambdas: &[f64],
+    lambda: Option<f64>,
+) {
+    let (start, end, steps) = (
+        args.start_lambda.unwrap_or_default(),
+        args.end_lambda.unwrap_or_default(),
+        args.steps.map_or(1, |steps| steps.get()),
+    );
+    let origin = if lambda.is_some() {
+        explain::Origin::Recorded
+    } else if args.discrepancy {
+        explain::Origin::Discrepancy { start, end, steps }
+    } else if args.interactive {
+        explain::Origin::Chosen
+    } else {
+        explain::Origin::Sweep { start, end, steps }
+    };
+    let rms = img_array.mapv(|v| v * v).mean().unwrap_or(0.0).sqrt();
+    let threshold = match (args.stop_at_delta, args.stop_on_gap) {
+        (Some(delta), _) => delta.relative_threshold(img_array),
+        (None, Some(gap)) => gap,
+        (None, None) => args
+            .convergence_threshold
+            .expect("clap requires a convergence threshold"),
+    };
+    let max_iter = args
+        .max_iter
+        .or(args.auto_iter.then_some(AUTO_ITER_LIMIT))
+        .expect("clap requires a maximum number of iterations");
+    let noise = args.noise_sigma.unwrap_or_else(|| {
+        analysis::measure_noise(img_array, args.noise_patch)
+    });
+    let (width, height, _) = img_array.dim();
+    let pixels = (width * height) as u64;
+    let max_parallelism =
+        Some(args.max_parallelism.get()).filter(|&max| max != usize::MAX);
+
+    explain::print(&[
+        ("λ values", explain::lambdas(&origin, lambdas)),
+        ("step sizes", explain::steps(lambdas, args.acceleration)),
+        (
+            "stopping",
+            explain::stopping(
+                threshold,
+                args.stop_at_delta.map(|delta| delta.0),
+                args.stop_on_gap,
+                rms,
+                max_iter,
+                args.auto_iter,
+            ),
+        ),
+        (
+            "noise",
+            explain::noise(noise, args.noise_sigma.is_some(), args.noise_patch),
+        ),
+        (
+            "precision",
+            explain::precision(
+                args.precision
+                    .fixed()
+                    .expect("precision is chosen before solving"),
+                settings.precision == precision::Choice::Auto,
+            ),
+        ),
+        (
+            "threads",
+            explain::threads(
+                &cpus::plan(args, lambdas.len(), pixels),
+                pixels,
+                args.sequential_below.is_none(),
+                max_parallelism,
+            ),
+        ),
+    ]);
+}
+
+/// Estimates how many bytes the outputs of `lambdas` λ values of an image
+/// of the given size will take in each folder, erring on the large side:
+/// images are counted as if they did not compress at all, and every
+/// intermediate file as if it was kept.
+fn estimate_outputs(
+    args: &DenoiseArgs,
+    lambdas: usize,
+    (width, height): (usize, usize),
+    float_output: bool,
+) -> space::Estimate {
+    // the solved area, and what is saved of it
+    let (width, height) = match args.patch {
+        Some(patch) if !args.composite => {
+            (patch.width as usize, patch.height as usize)
+        },
+        _ => (width, height),
+    };
+    let (saved_width, saved_height) = match args.output_scale {
+        Some(factor) => (
+            ((width as f64 * factor.0).round() as u64).max(1),
+            ((height as f64 * factor.0).round() as u64).max(1),
+        ),
+        None => (width as u64, height as u64),
+    };
+    let sample_bytes = if float_output { 4 } else { 1 };
+    let pixels = (width * height) as u64;
+    let lambdas = lambdas as u64;
+
+    let mut estimate = space::Estimate::default();
+    let mut per_lambda = saved_width * saved_height * 3 * sample_bytes;
+    if args.save_params {
+        per_lambda += SIDECAR_BYTES;
+    }
+    if args.tone_map.is_some() && float_output {
+        per_lambda += saved_width * saved_height * 3;
+    }
+    if args.diff_heatmap {
+        per_lambda += pixels * 3;
+    }
+    if let Some(count) = args.gallery {
+        let size = args.gallery_patch_size as u64;
+        per_lambda += count.get() as u64 * (2 * size + 2) * size * 3;
+    }
+    estimate.add(&args.output_folder, per_lambda * lambdas);
+    if let Some(count) = args.interpolate_outputs {
+        estimate.add(
+            &args.output_folder,
+            saved_width
+                * saved_height
+                * 3
+                * sample_bytes
+                * count.get() as u64
+                * lambdas.saturating_sub(1),
+        );
+    }
+    if let Some(every) = args.snapshot_every {
+        let max_iter = args.max_iter.unwrap_or(AUTO_ITER_LIMIT);
+        let snapshots = (max_iter / every.get()) as u64;
+        estimate.add(
+            args.scratch_dir.as_ref().unwrap_or(&args.output_folder),
+            snapshots * pixels * 3 * sample_bytes * lambdas,
+        );
+    }
+    if args.snapshot_at_residuals {
+        // down to the threshold if known, or to the precision of f64
+        let decades =
+            args.convergence_threshold
+                .map_or(RESIDUAL_DECADES, |threshold| {
+                    (-threshold.log10())
+                        .ceil()
+                        .clamp(1.0, RESIDUAL_DECADES as f64)
+                        as u64
+                });
+        estimate.add(
+            args.scratch_dir.as_ref().unwrap_or(&args.output_folder),
+            decades * pixels * 3 * sample_bytes * lambdas,
+        );
+    }
+    if let Some(folder) = &args.record_golden {
+        estimate.add(folder, pixels * 3 * 8 * lambdas);
+    }
+    estimate
+}
+
+/// Room left for the settings saved next to each output by --save-params.
+const SIDECAR_BYTES: u64 = 4096;
+/// Snapshots of --snapshot-at-residuals to leave room for without a
+/// convergence threshold, as changes under 1e-16 are mostly rounding.
+const RESIDUAL_DECADES: u64 = 16;
+
+/// Denoises the input for every λ value of the sweep, or only for the
+/// given one, saving it to `output_path` if given. Returns whether none of
+/// them failed.
+fn denoise(
+    mut args: DenoiseArgs,
+    lambda: Option<f64>,
+    output_path: Option<&Path>,
+    cancel: &cancel::Token,
+) -> bool {
+    let meter = cost::Meter::start();
+    if let Some(like) = args.like.take() {
+        let recorded = load_params(&like);
+        let lambda = recorded.lambda.expect("saved outputs have a lambda");
+        log::info!("solving like {}", like.to_string_lossy());
+        solve_like(&mut args, recorded.settings, &like);
+        return denoise(args, Some(lambda), None, cancel);
+    }
+    validate_args(&args);
+    // as given, before any of them are adjusted to the preprocessing
+    let settings = args.clone();
+
+    // load the RGB image into a 3D Array
+    let (img_array, sample_format) = load_input("image", &args.input_image);
+    if cancel.is_cancelled() {
+        log::error!("cancelled: {}", args.input_image.to_string_lossy());
+        return false;
+    }
+    let float_output = sample_format == input::SampleFormat::F32;
+    if args.tone_map.is_some() && !float_output {
+        log::warn!("input is not floating point, no preview to tone-map");
+    }
+    if args.protect_clipped && float_output {
+        log::warn!(
+            "input is floating point, with no range to be clipped at; not \
+             protecting any samples"
+        );
+        args.protect_clipped = false;
+    }
+
+    let (start_lambda, end_lambda, steps) = (
+        args.start_lambda.expect("required without --like"),
+        args.end_lambda.expect("required without --like"),
+        args.steps.expect("required without --like").get(),
+    );
+
+    // calculate `q`, the multiplier for the number of steps
+    let q = (end_lambda / start_lambda).powf(1_f64 / (steps - 1) as f64);
+
+    // calculate the lambda(s) to use
+    let lambdas = (0..steps).map(|step| start_lambda * q.powi(step as i32));
+    let lambdas: Vec<f64> = match lambda {
+        Some(lambda) => vec![lambda],
+        None => lambdas.collect(),
+    };
+
+    let input_bytes = storage::get()
+        .read(&args.input_image)
+        .expect("input could not be read");
+    // GeoTIFF inputs are saved as TIFF, to carry their georeferencing
+    let georeference = geotiff::read(&input_bytes);
+    let extension = match georeference {
+        Some(_) => "tif",
+        None if float_output => "exr",
+        None => "png",
+    };
+    // what is saved next to each output to map its levels back to those
+    // of a DICOM input
+    #[cfg(feature = "dicom")]
+    let dicom_sidecar = dicom::is_dicom(&input_bytes).then(|| {
+        dicom::Sidecar::new(&input_bytes, dicom::options().window)
+            .expect("input was decoded")
+    });
+    #[cfg(feature = "dicom")]
+    let extension = match dicom_sidecar {
+        Some(_) => dicom::options().format.extension(),
+        None => extension,
+    };
+    drop(input_bytes);
+    let stem = args
+        .input_image
+        .file_prefix()
+        .unwrap_or(std::ffi::OsStr::new("img"))
+        .to_string_lossy();
+    let stem = match args.ascii_names {
+        true => names::ascii(&stem),
+        false => stem.into_owned(),
+    };
+    let formatter = names::LambdaFormatter::new(args.lambda_format, &lambdas);
+    // the paths given so far, so that every λ value keeps its own
+    let named: RefCell<Vec<(f64, PathBuf)>> = RefCell::new(Vec::new());
+    let make_output_path_for = |lambda: f64| -> PathBuf {
+        if let Some(output_path) = output_path {
+            return names::long_path(output_path.to_path_buf());
+        }
+        if let Some((_, path)) =
+            named.borrow().iter().find(|(named, _)| *named == lambda)
+        {
+            return path.clone();
+        }
+        let path_with = |lambda: String| {
+            let file_name =
+                format!("{}_lambda_=_{}.{}", stem, lambda, extension);
+            let mut output_path = args.output_folder.clone();
+            output_path.push(names::sanitize(&file_name, args.sanitize_names));
+            names::long_path(output_path)
+        };
+        let mut output_path = path_with(formatter.format(lambda));
+        if named.borrow().iter().any(|(_, path)| *path == output_path) {
+            // another λ value (not of the sweep, or one closer than f64
+            // digits tell apart) is written the same, so this one is
+            // written in full (which no other value can share)
+            log::debug!(
+                "lambda {:e} rounds the same as another, named in full",
+                lambda
+            );
+            output_path = path_with(format!("{:e}", lambda));
+        }
+        log::info!("set output file name: {}", output_path.to_string_lossy());
+        named.borrow_mut().push((lambda, output_path.clone()));
+        output_path
+    };
+
+    let mut outputs: Vec<PathBuf> = lambdas
+        .iter()
+        .map(|&lambda| make_output_path_for(lambda))
+        .collect();
+    if args.save_params {
+        let sidecars: Vec<PathBuf> = outputs
+            .iter()
+            .map(|image| params::sidecar_path(image))
+            .collect();
+        outputs.extend(sidecars);
+    }
+    #[cfg(feature = "dicom")]
+    if dicom_sidecar.is_some() {
+        let sidecars: Vec<PathBuf> = outputs[..lambdas.len()]
+            .iter()
+            .map(|image| dicom::sidecar_path(image))
+            .collect();
+        outputs.extend(sidecars);
+    }
+    outputs.extend(
+        [
+            &args.report,
+            &args.report_csv,
+            &args.journal,
+            &args.session,
+            &args.write_manifest,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned(),
+    );
+    #[cfg(feature = "plot")]
+    outputs.extend(
+        [&args.plot, &args.plot_l_curve]
+            .into_iter()
+            .flatten()
+            .cloned(),
+    );
+    // so that a sweep does not fail at the end, on saving its report
+    if let Some(output) =
+        outputs.iter().find(|output| !storage::get().allows(output))
+    {
+        cli_error(
+            clap::error::ErrorKind::ValueValidation,
+            tr!(
+                "output `{}` is outside of --restrict-paths",
+                output.to_string_lossy()
+            ),
+        )
+        .fail();
+    }
+    if args.no_clobber_inputs {
+        validate_no_clobber(&args, &outputs);
+    }
+    let (width, height, _) = img_array.dim();
+    let estimate =
+        estimate_outputs(&args, lambdas.len(), (width, height), float_output);
+    #[cfg(feature = "dicom")]
+    let estimate = {
+        let mut estimate = estimate;
+        if dicom_sidecar.is_some() {
+            estimate
+                .add(&args.output_folder, SIDECAR_BYTES * lambdas.len() as u64);
+        }
+        estimate
+    };
+    if let Err(message) = estimate.check(args.min_free_space.0) {
+        cli_error(clap::error::ErrorKind::Io, message).fail();
+    }
+    if let Some(path) = &args.write_manifest {
+        let mut manifest = session::Session::load_or_default(path)
+            .expect("manifest could not be read");
+        for &lambda in &lambdas {
+            manifest.plan(session::Entry {
+                lambda: Some(lambda),
+                output: Some(make_output_path_for(lambda)),
+                input_sha256: None,
+                settings: settings.clone(),
+            });
+        }
+        manifest.save(path).expect("manifest could not be saved");
+        log::info!("manifest saved: {}", path.to_string_lossy());
+        return true;
+    }
+
+    let img_array = if args.dark_frame.is_some() || args.flat_field.is_some() {
+        let load_frame = |frame: &Option<PathBuf>, name: &str| {
+            frame.as_ref().map(|frame| {
+                log::info!("loading {}: {}", name, frame.to_string_lossy());
+                load_input(name, frame).0
+            })
+        };
+        let dark = load_frame(&args.dark_frame, "dark frame");
+        let flat = load_frame(&args.flat_field, "flat field");
+        ImageArray::from(&input::calibrate(
+            &img_array,
+            dark.as_deref(),
+            flat.as_deref(),
+        ))
+    } else {
+        img_array
+    };
+
+    let img_array = if args.detect_bad_lines
+        || !args.bad_columns.is_empty()
+        || !args.bad_rows.is_empty()
+    {
+        let mut columns: Vec<usize> =
+            args.bad_columns.iter().map(|&x| x as usize).collect();
+        let mut rows: Vec<usize> =
+            args.bad_rows.iter().map(|&y| y as usize).collect();
+        if args.detect_bad_lines {
+            let (detected_columns, detected_rows) =
+                input::detect_bad_lines(&img_array, args.bad_line_threshold);
+            log::info!("detected bad columns: {:?}", detected_columns);
+            log::info!("detected bad rows: {:?}", detected_rows);
+            columns.extend(detected_columns);
+            rows.extend(detected_rows);
+        }
+        let mut repaired = (*img_array).clone();
+        input::repair_lines(&mut repaired, &columns, &rows);
+        ImageArray::from(&repaired)
+    } else {
+        img_array
+    };
+
+    let img_array = match args.deblock {
+        Some(strength)
+            if storage::is_jpeg(storage::get(), &args.input_image)
+                .unwrap_or(false) =>
+        {
+            let deblocked = deblock::deblock(&img_array, strength);
+            log::info!(
+                "deblocked: blockiness {:.3} before, {:.3} after",
+                deblock::blockiness(&img_array),
+                deblock::blockiness(&deblocked)
+            );
+            ImageArray::from(&deblocked)
+        },
+        Some(_) => {
+            log::warn!(
+                "{} is not a JPEG, so it is not deblocked",
+                args.input_image.to_string_lossy()
+            );
+            img_array
+        },
+        None => img_array,
+    };
+
+    let (input_width, input_height, _) = img_array.dim();
+    let img_array = match args.max_dimension.and_then(|max_dimension| {
+        resize::fit_within(&img_array, max_dimension.get(), args.resize_filter)
+    }) {
+        Some(resized) => {
+            // the noise patch was given in the coordinates of the input
+            args.noise_patch = args.noise_patch.map(|patch| {
+                rescale_region(patch, img_array.dim(), resized.dim())
+            });
+            ImageArray::from(&resized)
+        },
+        None => img_array,
+    };
+
+    // GeoTIFF outputs are placed on the area of the input they cover
+    let placement = georeference.map(|georeference| geotiff::Placement {
+        georeference,
+        area: {
+            let (width, height, _) = img_array.dim();
+            let x_factor = width as f64 / input_width as f64;
+            let y_factor = height as f64 / input_height as f64;
+            match args.patch.filter(|_| !args.composite) {
+                Some(patch) => [
+                    patch.x as f64 / x_factor,
+                    patch.y as f64 / y_factor,
+                    patch.width as f64 / x_factor,
+                    patch.height as f64 / y_factor,
+                ],
+                None => [0.0, 0.0, input_width as f64, input_height as f64],
+            }
+        },
+        sample_format,
+    });
+
+    // the clean references go through the same resizing as the input
+    let references: Vec<Array3<f64>> = args
+        .reference
+        .iter()
+        .map(|path| {
+            let (clean, _) = load_input("reference", path);
+            match args.max_dimension.and_then(|max_dimension| {
+                resize::fit_within(
+                    &clean,
+                    max_dimension.get(),
+                    args.resize_filter,
+                )
+            }) {
+                Some(resized) => resized,
+                None => (*clean).clone(),
+            }
+        })
+        .collect();
+    // several references stand for the clean image by their mean
+    let clean = match &references[..] {
+        [] => None,
+        [clean] => Some(Cow::Borrowed(clean)),
+        [first, rest @ ..] => Some(Cow::Owned(
+            rest.iter().fold(first.clone(), |sum, clean| sum + clean)
+                / references.len() as f64,
+        )),
+    };
+    let input_sha256 =
+        (args.save_params || args.embed_source_hash || args.journal.is_some())
+            .then(|| {
+                journal::hash_file(&args.input_image)
+                    .expect("input could not be hashed")
+            });
+    let source = Source {
+        image: &img_array,
+        clean: clean.as_deref(),
+        references: match references.len() {
+            0 | 1 => &[],
+            _ => &references,
+        },
+        placement: placement.as_ref(),
+        sha256: input_sha256.as_deref().filter(|_| args.embed_source_hash),
+    };
+
+    if args.precision == precision::Choice::Auto {
+        // checked in the middle of the sweep, on a log scale
+        let lambda = (lambdas[0] * lambdas[lambdas.len() - 1]).sqrt();
+        let convergence_threshold = match (args.stop_at_delta, args.stop_on_gap)
+        {
+            (Some(delta), _) => delta.relative_threshold(&img_array),
+            // a relative change as fine as the relative gap, which f32 can
+            // reach as far as either of them
+            (None, Some(gap)) => gap,
+            (None, None) => args
+                .convergence_threshold
+                .expect("clap requires a convergence threshold"),
+        };
+        args.precision = match precision::choose(
+            &img_array,
+            lambda,
+            convergence_threshold,
+            args.acceleration,
+            cancel,
+        ) {
+            Ok(solver::Precision::F32) => precision::Choice::F32,
+            Ok(solver::Precision::F64) => precision::Choice::F64,
+            Err(cancelled) => {
+                log::error!(
+                    "{}: {}",
+                    cancelled,
+                    args.input_image.to_string_lossy()
+                );
+                return false;
+            },
+        };
+    }
+    if args.interlaced == interlace::Mode::Auto {
+        let combing = interlace::combing(&img_array);
+        let interlaced = combing > interlace::COMBING_THRESHOLD;
+        log::info!(
+            "combing of {:.3}: {}",
+            combing,
+            if interlaced {
+                "interlaced, denoising the fields separately"
+            } else {
+                "progressive"
+            }
+        );
+        args.interlaced = match interlaced {
+            true => interlace::Mode::On,
+            false => interlace::Mode::Off,
+        };
+    }
+
+    let lambdas = if args.interactive {
+        let chosen = choose_from_drafts(
+            &source,
+            &args,
+            &lambdas,
+            &make_output_path_for,
+            float_output,
+            cancel,
+        );
+        if cancel.is_cancelled() {
+            return false;
+        }
+        if let Some(path) = &args.session {
+            let mut session = session::Session::load_or_default(path)
+                .expect("session could not be read");
+            session.record(session::Entry {
+                lambda: chosen,
+                output: None,
+                input_sha256: None,
+                settings: settings.clone(),
+            });
+            session.save(path).expect("session could not be saved");
+            log::info!(
+                "choice recorded in session: {}",
+                path.to_string_lossy()
+            );
+        }
+        match chosen {
+            Some(_) if args.defer_solve => return true,
+            Some(lambda) => vec![lambda],
+            None => {
+                log::info!("skipped: {}", args.input_image.to_string_lossy());
+                return true;
+            },
+        }
+    } else {
+        lambdas
+    };
+
+    if args.explain {
+        explain_settings(&args, &settings, &img_array, &lambdas, lambda);
+    }
+    let (reports, discrepancy) = if args.discrepancy && lambda.is_none() {
+        let (reports, discrepancy) = discrepancy_search(
+            &source,
+            &args,
+            &make_output_path_for,
+            float_output,
+            cancel,
+        );
+        (reports, Some(discrepancy))
+    } else {
+        let reports = sweep(
+            &source,
+            &args,
+            &lambdas,
+            &make_output_path_for,
+            float_output,
+            cancel,
+        );
+        (reports, None)
+    };
+    let mut report = summarize(&img_array, &args, reports);
+    report.discrepancy = discrepancy.flatten();
+    (report.methods, report.method_failures) =
+        compare_methods(&source, &args, &stem, float_output, cancel);
+    report.cost = Some(
+        meter.read(
+            report
+                .lambdas
+                .iter()
+                .map(|entry| entry.megapixel_iterations)
+                .sum(),
+        ),
+    );
+    report.print_summary();
+    if let Some(path) = &args.report {
+        report.save_json(path).expect("report could not be saved");
+        log::info!("report saved: {}", path.to_string_lossy());
+    }
+    if let Some(path) = &args.report_csv {
+        report
+            .save_csv(path, &settings)
+            .expect("report could not be saved");
+        log::info!("report saved: {}", path.to_string_lossy());
+    }
+    #[cfg(feature = "plot")]
+    {
+        if let Some(path) = &args.plot {
+            plot::render(&report, path).expect("plot could not be saved");
+            log::info!("plot saved: {}", path.to_string_lossy());
+        }
+        if let Some(path) = &args.plot_l_curve {
+            plot::render_l_curve(&report, path)
+                .expect("plot could not be saved");
+            log::info!("plot saved: {}", path.to_string_lossy());
+        }
+    }
+    if args.save_params {
+        for entry in &report.lambdas {
+            params::save(
+                &entry.output,
+                &session::Entry {
+                    lambda: Some(entry.lambda),
+                    output: Some(entry.output.clone()),
+                    input_sha256: input_sha256.clone(),
+                    settings: settings.clone(),
+                },
+            )
+            .expect("parameters could not be saved");
+            log::info!(
+                "parameters saved: {}",
+                params::sidecar_path(&entry.output).to_string_lossy()
+            );
+        }
+    }
+    #[cfg(feature = "dicom")]
+    if let Some(sidecar) = &dicom_sidecar {
+        for entry in &report.lambdas {
+            let path = dicom::sidecar_path(&entry.output);
+            storage::save_json(storage::get(), &path, sidecar)
+                .expect("DICOM sidecar could not be saved");
+            log::info!("DICOM sidecar saved: {}", path.to_string_lossy());
+        }
+    }
+    if let Some(path) = &args.journal {
+        let input_sha256 = input_sha256.as_deref().expect("hashed above");
+        let timestamp = journal::now();
+        let records: Vec<_> = report
+            .lambdas
+            .iter()
+            .map(|result| journal::Record {
+                timestamp,
+                input_sha256,
+                input_noise: report.input_noise,
+                result,
+                settings: &settings,
+            })
+            .collect();
+        journal::append(path, &records).expect("journal could not be saved");
+        log::info!("journal appended: {}", path.to_string_lossy());
+    }
+    if let Some(count) = args.interpolate_outputs {
+        save_interpolations(&report, &args, count.get(), &stem, float_output);
+    }
+    report.succeeded()
+}
+
+/// Saves `count` blends between the saved outputs of each pair of adjacent
+/// λ values of the report, named like outputs for the λ values they stand
+/// for (with `~` for `=`) and labeled as approximations.
+fn save_interpolations(
+    report: &report::Report,
+    args: &DenoiseArgs,
+    count: usize,
+    stem: &str,
+    float_output: bool,
+) {
+    let between = |entries: &[report::LambdaReport]| {
+        let (lower, upper) = (entries[0].lambda, entries[1].lambda);
+        interpolate::steps(count)
+            .map(move |t| (t, interpolate::lambda_between(lower, upper, t)))
+    };
+    // named with as many digits as tell apart the blends and the outputs
+    let lambdas: Vec<f64> = report
+        .lambdas
+        .windows(2)
+        .flat_map(|entries| between(entries).map(|(_, lambda)| lambda))
+        .chain(report.lambdas.iter().map(|entry| entry.lambda))
+        .collect();
+    let formatter = names::LambdaFormatter::new(args.lambda_format, &lambdas);
+    let extension = if float_output { "exr" } else { "png" };
+
+    let load = |path: &Path| match input::load(path) {
+        Ok((array, _)) => Some(array),
+        Err(error) => {
+            log::warn!(
+                "not interpolating from {}: {}",
+                path.to_string_lossy(),
+                error
+            );
+            None
+        },
+    };
+    for entries in report.lambdas.windows(2) {
+        let (lower, upper) = (&entries[0], &entries[1]);
+        let Some((lower_image, upper_image)) =
+            load(&lower.output).zip(load(&upper.output))
+        else {
+            continue;
+        };
+        if lower_image.dim() != upper_image.dim() {
+            log::warn!(
+                "not interpolating between {} and {}: their sizes differ",
+                lower.output.to_string_lossy(),
+                upper.output.to_string_lossy()
+            );
+            continue;
+        }
+        for (t, lambda) in between(entries) {
+            let file_name = format!(
+                "{}_lambda_~_{}_approximation.{}",
+                stem,
+                formatter.format(lambda),
+                extension
+            );
+            let mut path = args.output_folder.clone();
+            path.push(names::sanitize(&file_name, args.sanitize_names));
+            let path = names::long_path(path);
+            save_image(
+                &interpolate::blend(&lower_image, &upper_image, t),
+                &path,
+                float_output,
+            )
+            .expect("approximation could not be saved");
+            if !float_output {
+                interpolate::label(&path, lower.lambda, upper.lambda)
+                    .expect("approximation could not be labeled");
+            }
+            log::info!(
+                "approximation for lambda {:.10} saved: {}",
+                lambda,
+                path.to_string_lossy()
+            );
+        }
+    }
+}
+
+/// Runs a quick sweep on a downscaled draft of the input, saving `_draft`
+/// images next to where the full resolution results would go, and asks
+/// which λ value to solve at full resolution, or `None` to skip the input.
+fn choose_from_drafts(
+    source: &Source,
+    args: &DenoiseArgs,
+    lambdas: &[f64],
+    output_path_for: &dyn Fn(f64) -> PathBuf,
+    float_output: bool,
+    cancel: &cancel::Token,
+) -> Option<f64> {
+    let img_array = source.image;
+    let draft = match resize::fit_within(
+        img_array,
+        args.draft_dimension.get(),
+        args.resize_filter,
+    ) {
+        Some(resized) => ImageArray::from(&resized),
+        None => ImageArray::from(&**img_array),
+    };
+    let draft_clean = source.clean.map(|clean| {
+        resize::fit_within(
+            clean,
+            args.draft_dimension.get(),
+            args.resize_filter,
+        )
+        .unwrap_or_else(|| clean.clone())
+    });
+    let rescale = |region: region::Region| {
+        rescale_region(region, img_array.dim(), draft.dim())
+    };
+    let draft_args = DenoiseArgs {
+        noise_patch: args.noise_patch.map(rescale),
+        patch: args.patch.map(rescale),
+        patch_margin: (args.patch_margin as f64 * draft.dim().0 as f64
+            / img_array.dim().0 as f64)
+            .round() as u32,
+        output_scale: None,
+        diff_heatmap: false,
+        debug_export: false,
+        gallery: None,
+        ..args.clone()
+    };
+
+    log::info!("solving drafts of {}x{}", draft.dim().0, draft.dim().1);
+    let reports = sweep(
+        &Source {
+            image: &draft,
+            clean: draft_clean.as_ref(),
+            references: &[],
+            placement: source.placement,
+            sha256: source.sha256,
+        },
+        &draft_args,
+        lambdas,
+        &|lambda| with_suffix(&output_path_for(lambda), "_draft"),
+        float_output,
+        cancel,
+    );
+    let report = summarize(&draft, &draft_args, reports);
+    report.print_summary();
+    if cancel.is_cancelled() {
+        return None;
+    }
+    interactive::choose_lambda(&report)
+}
+
+/// Maps a region given in the coordinates of an array of dimensions `from`
+/// to those of a resized copy of dimensions `to`, keeping it inside.
+fn rescale_region(
+    region: region::Region,
+    from: (usize, usize, usize),
+    to: (usize, usize, usize),
+) -> region::Region {
+    let x_factor = to.0 as f64 / from.0 as f64;
+    let y_factor = to.1 as f64 / from.1 as f64;
+    let mut scaled = region.scaled(x_factor, y_factor);
+    scaled.x = scaled.x.min(to.0 as u32 - 1);
+    scaled.y = scaled.y.min(to.1 as u32 - 1);
+    scaled.width = scaled.width.min(to.0 as u32 - scaled.x);
+    scaled.height = scaled.height.min(to.1 as u32 - scaled.y);
+    scaled
+}
+
+/// An input prepared for the solver, with a clean reference of it if one
+/// was given to measure the results against.
+struct Source<'a> {
+    image: &'a ImageArray<Array3<f64>>,
+    /// The mean of the references, if there are several.
+    clean: Option<&'a Array3<f64>>,
+    /// Each of the references, to also measure against apart, if there
+    /// are several.
+    references: &'a [Array3<f64>],
+    /// Where the outputs of a GeoTIFF input are placed, to save them as
+    /// GeoTIFF too.
+    placement: Option<&'a geotiff::Placement>,
+    /// SHA-256 of the input file, to embed in the outputs with
+    /// --embed-source-hash.
+    sha256: Option<&'a str>,
+}
+
+/// Denoises the `source` for each of the `lambdas`, as many at a time as
+/// the available parallelism allows, and saves the results.
+fn sweep(
+    source: &Source,
+    args: &DenoiseArgs,
+    lambdas: &[f64],
+    output_path_for: &dyn Fn(f64) -> PathBuf,
+    float_output: bool,
+    cancel: &cancel::Token,
+) -> Vec<Result<report::LambdaReport, report::LambdaFailure>> {
+    // a failure is the failure of a single lambda value, which should not
+    // take the others down with it
+    let failure = |lambda: f64, message: String| {
+        log::error!("lambda {:.10} failed: {}", lambda, message);
+        report::LambdaFailure {
+            lambda,
+            output: output_path_for(lambda),
+            message,
+        }
+    };
+
+    let (width, height, _) = source.image.dim();
+    let pixels = (width * height) as u64;
+    match cpus::plan(args, lambdas.len(), pixels) {
+        cpus::Plan::Parallel {
+            available: num,
+            jobs,
+            threads,
+        } => {
+            log::info!("available parallelism: {num}");
+            if jobs * threads > num.get() {
+                log::warn!(
+                    "{} jobs of {} threads each are more than the {} available",
+                    jobs,
+                    threads,
+                    num
+                );
+            }
+            log::debug!(
+                "jobs: {}, threads per lambda value: {}",
+                jobs,
+                threads
+            );
+            let progress = args.progress.map(|style| {
+                progress::Progress::new(lambdas.len(), jobs, style)
+            });
+            let progress = progress.as_ref();
+            let mut reports = Vec::with_capacity(lambdas.len());
+            for chunk in lambdas.chunks(jobs) {
+                log::debug!("processing chunk of len {}", chunk.len());
+                thread::scope(|scope| {
+                    let mut handles = Vec::with_capacity(chunk.len());
+                    for &lambda in chunk {
+                        let output_path = output_path_for(lambda);
+                        handles.push((
+                            lambda,
+                            scope.spawn(move || {
+                                logger::with_context(
+                                    format!("lambda {:.10}", lambda),
+                                    || {
+                                        log::debug!("spawned thread");
+                                        if let Some(progress) = progress {
+                                            progress.start(lambda);
+                                        }
+                                        denoise_and_save(
+                                            source,
+                                            args,
+                                            Task {
+                                                lambda,
+                                                output_file_name: &output_path,
+                                                threads,
+                                                progress,
+                                            },
+                                            float_output,
+                                            cancel,
+                                        )
+                                    },
+                                )
+                            }),
+                        ));
+                    }
+                    log::debug!("waiting before next chunk");
+                    for (lambda, handle) in handles {
+                        log::debug!(
+                            "calling join on thread for lambda: {}",
+                            lambda
+                        );
+                        let result = match handle.join() {
+                            Ok(Ok(report)) => Ok(report),
+                            Ok(Err(cancelled)) => {
+                                Err(failure(lambda, cancelled.to_string()))
+                            },
+                            Err(payload) => {
+                                Err(failure(lambda, panic_message(payload)))
+                            },
+                        };
+                        if let Some(progress) = progress {
+                            progress.finish(lambda);
+                        }
+                        reports.push(result);
+                    }
+                });
+            }
+            if let Some(progress) = progress {
+                progress.clear();
+            }
+            reports
+        },
+        plan => {
+            match plan {
+                cpus::Plan::Sequential { .. } => log::debug!(
+                    "solving a small image of {} pixels sequentially",
+                    pixels
+                ),
+                cpus::Plan::Unavailable(message) => {
+                    log::warn!("no available parallelism: {}", message)
+                },
+                cpus::Plan::Parallel { .. } => unreachable!("matched above"),
+            }
+            let progress = args
+                .progress
+                .map(|style| progress::Progress::new(lambdas.len(), 1, style));
+            let progress = progress.as_ref();
+            let reports = lambdas
+                .iter()
+                .map(|&lambda| {
+                    let output_path = output_path_for(lambda);
+                    if let Some(progress) = progress {
+                        progress.start(lambda);
+                    }
+                    let result = match std::panic::catch_unwind(|| {
+                        logger::with_context(
+                            format!("lambda {:.10}", lambda),
+                            || {
+                                denoise_and_save(
+                                    source,
+                                    args,
+                                    Task {
+                                        lambda,
+                                        output_file_name: &output_path,
+                                        threads: 1,
+                                        progress,
+                                    },
+                                    float_output,
+                                    cancel,
+                                )
+                            },
+                        )
+                    }) {
+                        Ok(Ok(report)) => Ok(report),
+                        Ok(Err(cancelled)) => {
+                            Err(failure(lambda, cancelled.to_string()))
+                        },
+                        Err(payload) => {
+                            Err(failure(lambda, panic_message(payload)))
+                        },
+                    };
+                    if let Some(progress) = progress {
+                        progress.finish(lambda);
+                    }
+                    result
+                })
+                .collect();
+            if let Some(progress) = progress {
+                progress.clear();
+            }
+            reports
+        },
+    }
+}
+
+/// The message a worker panicked with.
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+/// Relative difference from the noise at which --discrepancy stops early.
+const DISCREPANCY_TOLERANCE: f64 = 0.01;
+
+/// Searches between the start and end λ values for the one whose output
+/// differs from the input by as much as the noise, as by the discrepancy
+/// principle: outputs closer to the input kept some of the noise, and
+/// those further from it lost some of the image as well. The difference
+/// falls as λ grows, so λ is bisected on a log scale, solving and saving
+/// each probe, for at most `steps` solves. Returns the results of the
+/// probes, and the one closest to the noise (if any was solved).
+fn discrepancy_search(
+    source: &Source,
+    args: &DenoiseArgs,
+    output_path_for: &dyn Fn(f64) -> PathBuf,
+    float_output: bool,
+    cancel: &cancel::Token,
+) -> (
+    Vec<Result<report::LambdaReport, report::LambdaFailure>>,
+    Option<report::Discrepancy>,
+) {
+    // outputs are compared to what they were solved from
+    let reference = match args.patch.filter(|_| !args.composite) {
+        Some(patch) => Cow::Owned(patch::crop(source.image, patch)),
+        None => Cow::Borrowed(&**source.image),
+    };
+    let noise = args.noise_sigma.unwrap_or_else(|| {
+        analysis::measure_sample_noise(&reference, args.noise_patch)
+    });
+    log::info!("discrepancy principle: looking for a difference of {}", noise);
+    let samples = (reference.len() as f64).sqrt();
+
+    let (mut low, mut high) = (
+        args.start_lambda.expect("required without --like").ln(),
+        args.end_lambda.expect("required without --like").ln(),
+    );
+    let mut reports = Vec::new();
+    let mut best: Option<report::Discrepancy> = None;
+    for _ in 0..args.steps.expect("required without --like").get() {
+        let lambda = ((low + high) / 2.0).exp();
+        let mut probe = sweep(
+            source,
+            args,
+            &[lambda],
+            output_path_for,
+            float_output,
+            cancel,
+        );
+        let result = probe.pop().expect("one result per lambda value");
+        let residual_rms = match &result {
+            Ok(entry) => entry.residual_norm / samples,
+            Err(_) => {
+                // nothing to bisect on without its output
+                reports.push(result);
+                break;
+            },
+        };
+        reports.push(result);
+        log::info!(
+            "lambda {:.10} differs from the input by {}",
+            lambda,
+            residual_rms
+        );
+        if best.as_ref().is_none_or(|best| {
+            (residual_rms - noise).abs() < (best.residual_rms - noise).abs()
+        }) {
+            best = Some(report::Discrepancy {
+                noise,
+                lambda,
+                residual_rms,
+            });
+        }
+        if (residual_rms - noise).abs() <= DISCREPANCY_TOLERANCE * noise {
+            break;
+        }
+        // too far from the input means too much smoothing, i.e. too
+        // small a λ
+        if residual_rms > noise {
+            low = lambda.ln();
+        } else {
+            high = lambda.ln();
+        }
+    }
+    (reports, best)
+}
+
+/// Collects the results of a sweep over `img_array` into its summary.
+fn summarize(
+    img_array: &ImageArray<Array3<f64>>,
+    args: &DenoiseArgs,
+    reports: Vec<Result<report::LambdaReport, report::LambdaFailure>>,
+) -> report::Report {
+    // without compositing, outputs are compared to the patch alone
+    let reference = match args.patch.filter(|_| !args.composite) {
+        Some(patch) => Cow::Owned(patch::crop(img_array, patch)),
+        None => Cow::Borrowed(&**img_array),
+    };
+    let mut report = report::Report::new(
+        args.input_image.clone(),
+        analysis::measure_noise(&reference, args.noise_patch),
+        args.noise_patch,
+        reports,
+        args.staircase_threshold,
+    );
+    report.references = args.reference.clone();
+    report
+}
+
+/// Runs each of the --compare-with methods on what the sweep denoised, and
+/// measures their results the same way as its outputs.
+fn compare_methods(
+    source: &Source,
+    args: &DenoiseArgs,
+    stem: &str,
+    float_output: bool,
+    cancel: &cancel::Token,
+) -> (Vec<report::MethodReport>, Vec<report::MethodFailure>) {
+    // without compositing, outputs are compared to the patch alone
+    let crop_like_output = |array| match args.patch.filter(|_| !args.composite)
+    {
+        Some(patch) => Cow::Owned(patch::crop(array, patch)),
+        None => Cow::Borrowed(array),
+    };
+    let reference = crop_like_output(source.image);
+    let clean = source.clean.map(crop_like_output);
+    let extension = if float_output { "exr" } else { "png" };
+
+    let (mut methods, mut failures) = (Vec::new(), Vec::new());
+    for method in &args.compare_with {
+        let path_of = |name: String| {
+            names::long_path(
+                args.output_folder
+                    .join(names::sanitize(&name, args.sanitize_names)),
+            )
+        };
+        let output = path_of(format!("{}_{}.{}", stem, method.name, extension));
+        let scratch = path_of(format!("{}_{}_scratch", stem, method.name));
+        let input = scratch.join(format!("input.{}", extension));
+        let started = std::time::Instant::now();
+        let ran = storage::get()
+            .create_dir_all(&scratch)
+            .map_err(|error| error.to_string())
+            .and_then(|()| {
+                save_image(&reference, &input, float_output)
+                    .map_err(|error| error.to_string())
+            })
+            .and_then(|_| method.run(&input, &output, cancel));
+        let seconds = started.elapsed().as_secs_f64();
+        if let Err(error) = storage::get().remove_dir_all(&scratch) {
+            log::warn!(
+                "scratch folder {} could not be removed: {}",
+                scratch.to_string_lossy(),
+                error
+            );
+        }
+        let denoised = ran.and_then(|()| {
+            let (denoised, _) = input::load(&output).map_err(|error| {
+                format!(
+                    "its output {} could not be open: {}",
+                    output.to_string_lossy(),
+                    error
+                )
+            })?;
+            match denoised.dim() == reference.dim() {
+                true => Ok(denoised),
+                false => Err(format!(
+                    "its output {} is not the size of its input",
+                    output.to_string_lossy()
+                )),
+            }
+        });
+        let denoised = match denoised {
+            Ok(denoised) => denoised,
+            Err(message) => {
+                log::error!("method {} failed: {}", method.name, message);
+                failures.push(report::MethodFailure {
+                    name: method.name.clone(),
+                    message,
+                });
+                continue;
+            },
+        };
+        log::info!(
+            "method {} denoised in {:.3} seconds: {}",
+            method.name,
+            seconds,
+            output.to_string_lossy()
+        );
+        methods.push(report::MethodReport {
+            name: method.name.clone(),
+            command: method.command.clone(),
+            output,
+            noise: analysis::measure_noise(&denoised, args.noise_patch),
+            edge_preservation: analysis::edge_preservation(
+                &reference, &denoised,
+            ),
+            staircasing: analysis::staircasing(&reference, &denoised),
+            psnr: clean.as_ref().map(|clean| analysis::psnr(clean, &denoised)),
+            ssim: clean.as_ref().map(|clean| analysis::ssim(clean, &denoised)),
+            delta_e: clean
+                .as_ref()
+                .map(|clean| analysis::delta_e(clean, &denoised, float_output)),
+            channels: clean
+                .as_ref()
+                .map(|clean| analysis::channel_metrics(clean, &denoised))
+                .unwrap_or_default(),
+            input_delta_e: analysis::delta_e(
+                &reference,
+                &denoised,
+                float_output,
+            ),
+            residual_norm: analysis::residual_norm(&reference, &denoised),
+            total_variation: analysis::total_variation(&denoised),
+            seconds,
+        });
+    }
+    (methods, failures)
+}
+
+/// Upper bound on the iterations chosen by --auto-iter without --max-iter.
+const AUTO_ITER_LIMIT: u32 = 10_000;
+/// Largest difference (in 8-bit levels) of a channel from the mean of its
+/// pixel for an image to be solved as a single gray channel.
+const GRAY_TOLERANCE: f64 = 0.5;
+
+/// A single λ value of a sweep, and how to solve it.
+#[derive(Clone, Copy)]
+struct Task<'a> {
+    lambda: f64,
+    output_file_name: &'a Path,
+    /// Threads the channels are solved on.
+    threads: usize,
+    progress: Option<&'a progress::Progress>,
+}
+
+fn denoise_and_save(
+    source: &Source,
+    args: &DenoiseArgs,
+    task: Task,
+    float_output: bool,
+    cancel: &cancel::Token,
+) -> Result<report::LambdaReport, cancel::Cancelled> {
+    let Task {
+        lambda,
+        output_file_name,
+        threads,
+        progress,
+    } = task;
+    cancel.check()?;
+    let started = std::time::Instant::now();
+    let image = source.image;
+
+    // when only a patch is denoised, the solver gets it with some context
+    let context = args.patch.map(|patch| {
+        let (width, height, _) = image.dim();
+        let context = patch::context(
+            patch,
+            args.patch_margin,
+            width as u32,
+            height as u32,
+        );
+        (patch, context)
+    });
+    let solver_input = match context {
+        Some((_, context)) => Cow::Owned(patch::crop(image, context)),
+        None => Cow::Borrowed(&**image),
+    };
+
+    let padded =
+        boundary::pad(&solver_input, args.boundary_padding, args.boundary);
+    let megapixels = (padded.dim().0 * padded.dim().1) as f64 / 1e6;
+    let convergence_threshold = match (args.stop_at_delta, args.stop_on_gap) {
+        (Some(delta), _) => delta.relative_threshold(&padded),
+        // the gap stops the solve instead
+        (None, Some(_)) => 0.0,
+        (None, None) => args
+            .convergence_threshold
+            .expect("clap requires a convergence threshold"),
+    };
+    log::debug!("convergence threshold: {}", convergence_threshold);
+
+    // channels that are all the same only need to be solved once
+    let channels = padded.dim().2;
+    let gray = !args.no_gray_shortcut
+        && channels > 1
+        && input::is_near_gray(&padded, GRAY_TOLERANCE);
+    let (padded, channel_copies) = if gray {
+        log::debug!("lambda {:.10}: solving a single gray channel", lambda);
+        let gray = padded
+            .mean_axis(image_recovery::ndarray::Axis(2))
+            .expect("image has at least one channel")
+            .insert_axis(image_recovery::ndarray::Axis(2));
+        (gray, channels)
+    } else {
+        (padded, 1)
+    };
+    // the fields of interlaced frames are solved as separate channels
+    let fields = args
+        .interlaced
+        .fixed()
+        .expect("interlacing is detected before solving");
+    let padded_height = padded.dim().1;
+    let padded = match fields {
+        true => interlace::split(&padded),
+        false => padded,
+    };
+
+    let data_weight = args
+        .protect_clipped
+        .then(|| clipped::data_weight(&padded, args.clipped_weight))
+        .flatten();
+
+    // now we can call the denoising solver with the chosen variables
+    let parameters = solver::Parameters {
+        lambda,
+        tau: solver::tau(),
+        sigma: solver::sigma(),
+        gamma: solver::gamma(lambda),
+        max_iter: args
+            .max_iter
+            .or(args.auto_iter.then_some(AUTO_ITER_LIMIT))
+            .expect("clap requires a maximum number of iterations"),
+        convergence_threshold,
+        acceleration: args.acceleration,
+        channel_copies,
+        threads,
+        precision: args
+            .precision
+            .fixed()
+            .expect("precision is chosen before solving"),
+        data_weight: data_weight.as_ref(),
+        data_band: args.deband.map(|step| step / 2.0),
+        stop_on_gap: args.stop_on_gap,
+        keep_dual: args.debug_export,
+    };
+    // what becomes of the solver's result to be saved, for both the final
+    // result and any snapshots of the iterations
+    let reference = match context {
+        Some((patch, _)) if !args.composite => {
+            Cow::Owned(patch::crop(image, patch))
+        },
+        _ => Cow::Borrowed(&**image),
+    };
+    let finish = |solved: &Array3<f64>| -> Array3<f64> {
+        let interleaved = match fields {
+            true => Cow::Owned(interlace::interleave(solved, padded_height)),
+            false => Cow::Borrowed(solved),
+        };
+        let solved = &*interleaved;
+        let solved = if gray {
+            let (width, height, _) = solved.dim();
+            Cow::Owned(
+                solved
+                    .broadcast((width, height, channels))
+                    .expect("a single channel broadcasts")
+                    .to_owned(),
+            )
+        } else {
+            Cow::Borrowed(solved)
+        };
+        let solved =
+            boundary::crop(&solved, args.boundary_padding, args.boundary);
+        match context {
+            Some((patch, context)) if args.composite => patch::composite(
+                image,
+                &solved,
+                patch,
+                context,
+                args.patch_margin,
+            ),
+            Some((patch, context)) => {
+                patch::crop_from_context(&solved, patch, context)
+            },
+            None => solved,
+        }
+    };
+
+    // intermediate files of this λ value, in a folder of its own if asked
+    let scratch = args.scratch_dir.as_ref().map(|folder| {
+        let scratch = folder.join(
+            output_file_name
+                .file_stem()
+                .expect("output file names have a stem"),
+        );
+        storage::get()
+            .create_dir_all(&scratch)
+            .expect("scratch folder could not be created");
+        scratch
+    });
+    let intermediate = |file_name: PathBuf| match &scratch {
+        Some(scratch) => scratch.join(
+            file_name
+                .file_name()
+                .expect("output file names have a name"),
+        ),
+        None => file_name,
+    };
+
+    let save_snapshot = |iteration: &solver::Iteration, suffix: &str| {
+        let snapshot_file_name =
+            intermediate(with_suffix(output_file_name, suffix));
+        save_image(
+            &finish(&iteration.image()),
+            &snapshot_file_name,
+            float_output,
+        )
+        .expect("snapshot could not be saved");
+        log::debug!(
+            "snapshot saved at iteration {}: {}",
+            iteration.number,
+            snapshot_file_name.to_string_lossy()
+        );
+    };
+
+    // residuals so far, to predict how many iterations are left
+    let mut residuals = Vec::new();
+    // energy of every iteration, for --log-energy
+    let mut energies = Vec::new();
+    // powers of ten the residual has been under, for --snapshot-at-residuals
+    let mut decades = 0;
+    let mut on_iteration = |iteration: &solver::Iteration| {
+        let energy = args.log_energy.then(|| iteration.energy());
+        log::trace!(
+            "lambda {:.10}: iteration {}, residual {:e}{}{}",
+            lambda,
+            iteration.number,
+            iteration.residual,
+            energy.map_or(String::new(), |energy| {
+                format!(", energy {:e}", energy.total)
+            }),
+            iteration
+                .gap
+                .map_or(String::new(), |gap| format!(", gap {:e}", gap))
+        );
+        if let Some(energy) = energy {
+            // --auto-iter solves again after its probe
+            if iteration.number == 1 {
+                energies.clear();
+            }
+            energies.push(energy);
+        }
+        if let Some(progress) = progress {
+            // --auto-iter solves again after its probe
+            if iteration.number == 1 {
+                residuals.clear();
+            }
+            residuals.push(iteration.residual);
+            if progress.is_due() {
+                let predicted = convergence::predict_iterations(
+                    &residuals,
+                    parameters.convergence_threshold,
+                )
+                .map_or(parameters.max_iter, |predicted| {
+                    predicted.min(parameters.max_iter)
+                });
+                progress.update(lambda, iteration.number, predicted);
+            }
+        }
+        if let Some(every) = args.snapshot_every {
+            if iteration.number.is_multiple_of(every.get()) {
+                save_snapshot(
+                    iteration,
+                    &format!("_iteration_{}", iteration.number),
+                );
+            }
+        }
+        if args.snapshot_at_residuals {
+            // --auto-iter solves again after its probe
+            if iteration.number == 1 {
+                decades = 0;
+            }
+            // only the smallest power of ten, if it fell under several
+            let under = (-iteration.residual.log10()).floor();
+            if under.is_finite() && under > decades as f64 {
+                decades = under as u64;
+                save_snapshot(iteration, &format!("_residual_1e-{}", decades));
+            }
+        }
+    };
+    let solve =
+        |parameters: solver::Parameters,
+         on_iteration: &mut dyn FnMut(&solver::Iteration)| {
+            let solution = if args.auto_iter {
+                solve_with_auto_iter(&padded, parameters, cancel, on_iteration)
+            } else {
+                solver::denoise(&padded, &parameters, cancel, on_iteration)
+            };
+            match solution {
+                Ok(solution) => Ok(solution),
+                Err(solver::Error::Cancelled(cancelled)) => Err(cancelled),
+                Err(error) => panic!("denoising failed: {}", error),
+            }
+        };
+    let mut solution = solve(parameters, &mut on_iteration)?;
+    if args.determinism_check {
+        let threads =
+            determinism::other_threads(parameters.threads, padded.dim().2);
+        let again = solve(
+            solver::Parameters {
+                threads,
+                ..parameters
+            },
+            &mut |_| {},
+        )?;
+        if let Some(mismatch) =
+            determinism::compare(&solution.image, &again.image)
+        {
+            panic!(
+                "not deterministic: solved on {} and {} threads, {}",
+                parameters.threads, threads, mismatch
+            );
+        }
+        log::info!(
+            "identical results on {} and {} threads",
+            parameters.threads,
+            threads
+        );
+    }
+    if !solution.converged {
+        match (solution.gap, parameters.stop_on_gap) {
+            (Some(gap), Some(threshold)) => log::warn!(
+                "lambda {:.10} stopped on max_iter = {} before converging, \
+                 with gap {:e} over the threshold of {:e}",
+                lambda,
+                solution.iterations(),
+                gap,
+                threshold
+            ),
+            _ => log::warn!(
+                "lambda {:.10} stopped on max_iter = {} before converging, \
+                 with residual {:e} over the threshold of {:e}",
+                lambda,
+                solution.iterations(),
+                solution.residual(),
+                parameters.convergence_threshold
+            ),
+        }
+    }
+    if data_weight.is_some() {
+        clipped::restore(&mut solution.image, &padded);
+    }
+    let denoised = ImageArray::from(&finish(&solution.image));
+
+    let scaled = args.output_scale.map(|factor| {
+        ImageArray::from(&resize::scale(&denoised, factor, args.resize_filter))
+    });
+    let to_save = scaled.as_ref().unwrap_or(&denoised);
+    let dithered = args
+        .deband
+        .filter(|_| !float_output)
+        .map(|_| ImageArray::from(&deband::dither(to_save, lambda.to_bits())));
+    let to_save = dithered.as_ref().unwrap_or(to_save);
+    let out_of_range = (!float_output).then(|| range::OutOfRange::of(to_save));
+    let fitted = match &out_of_range {
+        Some(out_of_range) => {
+            if !out_of_range.is_empty() {
+                log::info!(
+                    "{} out of range: {}",
+                    output_file_name.to_string_lossy(),
+                    out_of_range
+                );
+            }
+            range::apply(to_save, out_of_range, args.out_of_range)
+                .unwrap_or_else(|error| panic!("{}", error))
+        },
+        None => Cow::Borrowed(&**to_save),
+    };
+    cancel.check()?;
+
+    let saved = match source.placement {
+        Some(placement) => geotiff::save(output_file_name, &fitted, placement)
+            .expect("image could not be saved"),
+        None => save_image(&fitted, output_file_name, float_output)
+            .expect("image could not be saved"),
+    };
+    log::info!("image saved: {}", output_file_name.to_string_lossy());
+    // before the verification, so that it checks the file as it is left
+    embed_metadata(
+        source,
+        args,
+        output_file_name,
+        lambda,
+        solution.iterations(),
+    );
+    let verification_error = if args.verify_output {
+        let result = verify::check(
+            storage::get(),
+            output_file_name,
+            &saved,
+            args.verify_checksum,
+        );
+        if let Err(error) = &result {
+            log::error!("{} {}", output_file_name.to_string_lossy(), error);
+        }
+        result.err()
+    } else {
+        None
+    };
+
+    if let Some(folder) = &args.record_golden {
+        let golden_file_name =
+            golden::record(folder, lambda, &denoised, solution.iterations())
+                .expect("golden output could not be saved");
+        log::info!(
+            "golden output saved: {}",
+            golden_file_name.to_string_lossy()
+        );
+    }
+    let golden = args.compare_golden.as_ref().map(|folder| {
+        let comparison = golden::compare(
+            folder,
+            lambda,
+            &denoised,
+            solution.iterations(),
+            args.tolerance,
+        );
+        if !comparison.passed() {
+            log::error!(
+                "{} differs from its golden output {}",
+                output_file_name.to_string_lossy(),
+                comparison.golden.to_string_lossy()
+            );
+        }
+        comparison
+    });
+
+    if let Some(tone_map) = args.tone_map.filter(|_| float_output) {
+        let preview_file_name =
+            with_suffix(output_file_name, "_preview").with_extension("png");
+        storage::save_image(
+            storage::get(),
+            &tone_map.apply(to_save).into(),
+            &preview_file_name,
+        )
+        .expect("preview could not be saved");
+        log::info!("preview saved: {}", preview_file_name.to_string_lossy());
+    }
+
+    if args.diff_heatmap {
+        let heatmap_file_name =
+            with_suffix(output_file_name, "_heatmap").with_extension("png");
+        storage::save_image(
+            storage::get(),
+            &heatmap::render(&reference, &denoised).into(),
+            &heatmap_file_name,
+        )
+        .expect("heat map could not be saved");
+        log::info!("heat map saved: {}", heatmap_file_name.to_string_lossy());
+    }
+
+    if let Some(dual) = &solution.dual {
+        // as solved: padded for the boundary, with the fields of interlaced
+        // frames apart, and a single channel for grayscale images
+        let dual_file_name =
+            with_suffix(output_file_name, "_dual").with_extension("npy");
+        npy::save(&dual_file_name, dual)
+            .expect("dual variables could not be saved");
+        log::info!(
+            "dual variables saved: {}",
+            dual_file_name.to_string_lossy()
+        );
+    }
+
+    if let Some(count) = args.gallery {
+        let patches = gallery::most_changed(
+            &reference,
+            &denoised,
+            args.gallery_patch_size,
+            count.get(),
+        );
+        for (rank, patch) in patches.iter().enumerate() {
+            let gallery_file_name = with_suffix(
+                output_file_name,
+                &format!("_gallery_{}", rank + 1),
+            )
+            .with_extension("png");
+            storage::save_image(
+                storage::get(),
+                &gallery::render(&reference, &denoised, patch.region).into(),
+                &gallery_file_name,
+            )
+            .expect("gallery could not be saved");
+            log::info!(
+                "gallery patch {} saved, changed by {:.3} on average: {}",
+                patch.region,
+                patch.change,
+                gallery_file_name.to_string_lossy()
+            );
+        }
+    }
+
+    if let Some(scratch) = &scratch {
+        storage::get()
+            .remove_dir_all(scratch)
+            .expect("scratch folder could not be removed");
+        log::debug!("scratch folder removed: {}", scratch.to_string_lossy());
+    }
+
+    let seconds = started.elapsed().as_secs_f64();
+    let crop_like_output = |clean| match context {
+        Some((patch, _)) if !args.composite => {
+            Cow::Owned(patch::crop(clean, patch))
+        },
+        _ => Cow::Borrowed(clean),
+    };
+    let clean = source.clean.map(crop_like_output);
+    let references = source
+        .references
+        .iter()
+        .map(|clean| {
+            let clean = crop_like_output(clean);
+            report::ReferenceMetrics {
+                psnr: analysis::psnr(&clean, &denoised),
+                ssim: analysis::ssim(&clean, &denoised),
+                delta_e: analysis::delta_e(&clean, &denoised, float_output),
+            }
+        })
+        .collect();
+    Ok(report::LambdaReport {
+        lambda,
+        output: output_file_name.to_path_buf(),
+        noise: analysis::measure_noise(&denoised, args.noise_patch),
+        edge_preservation: analysis::edge_preservation(&reference, &denoised),
+        staircasing: analysis::staircasing(&reference, &denoised),
+        staircasing_flagged: false,
+        psnr: clean.as_ref().map(|clean| analysis::psnr(clean, &denoised)),
+        ssim: clean.as_ref().map(|clean| analysis::ssim(clean, &denoised)),
+        delta_e: clean
+            .as_ref()
+            .map(|clean| analysis::delta_e(clean, &denoised, float_output)),
+        channels: clean
+            .as_ref()
+            .map(|clean| analysis::channel_metrics(clean, &denoised))
+            .unwrap_or_default(),
+        references,
+        input_delta_e: analysis::delta_e(&reference, &denoised, float_output),
+        residual_norm: analysis::residual_norm(&reference, &denoised),
+        total_variation: analysis::total_variation(&denoised),
+        iterations: solution.iterations(),
+        megapixel_iterations: megapixels * solution.iterations() as f64,
+        residual: solution.residual(),
+        converged: solution.converged,
+        energy: solution.energy,
+        energies,
+        gap: solution.gap,
+        out_of_range,
+        verification_error,
+        golden,
+        seconds,
+    })
+}
+
+/// Adds the source hash and attribution asked for to the PNG output at
+/// `path`, of `lambda` solved in `iterations`.
+fn embed_metadata(
+    source: &Source,
+    args: &DenoiseArgs,
+    path: &Path,
+    lambda: f64,
+    iterations: u32,
+) {
+    let attribute = args.creator.is_some()
+        || args.copyright.is_some()
+        || args.processing_note;
+    if source.sha256.is_none() && !attribute {
+        return;
+    }
+    if path.extension().is_none_or(|extension| extension != "png") {
+        log::warn!(
+            "source hash and attribution not embedded in {}: only PNG outputs \
+             can hold them",
+            path.to_string_lossy()
+        );
+        return;
+    }
+    if let Some(sha256) = source.sha256 {
+        provenance::embed(path, sha256)
+            .expect("source hash could not be embedded");
+        log::info!("source hash embedded: {}", path.to_string_lossy());
+    }
+    if attribute {
+        let attribution = xmp::Attribution {
+            creator: args.creator.clone(),
+            rights: args.copyright.clone(),
+            description: args.processing_note.then(|| {
+                format!(
+                    "Denoised with denoise-cli {} by total variation, λ = {}, \
+                     {} iterations",
+                    env!("CARGO_PKG_VERSION"),
+                    lambda,
+                    iterations
+                )
+            }),
+        };
+        xmp::embed(path, &attribution)
+            .expect("attribution could not be embedded");
+        log::info!("attribution embedded: {}", path.to_string_lossy());
+    }
+}
+
+/// Converts the solution into an RGB image format, encodes it and saves it
+/// to a file, as EXR for floating point inputs. Returns the image as it was
+/// meant to be saved.
+fn save_image(
+    array: &Array3<f64>,
+    path: &Path,
+    float_output: bool,
+) -> image::ImageResult<image::DynamicImage> {
+    let img = if float_output {
+        image::DynamicImage::from(output::to_rgb32f(array))
+    } else {
+        image::DynamicImage::from(ImageArray::from(array).into_rgb())
+    };
+    storage::save_image(storage::get(), &img, path)?;
+    Ok(img)
+}
+
+/// Runs a short probe of the solve to measure its convergence rate, then,
+/// unless it already converged, solves again with the number of iterations
+/// predicted to reach the threshold (with some margin, as the prediction
+/// tends to fall short), capped by `parameters.max_iter`.
+fn solve_with_auto_iter(
+    array: &Array3<f64>,
+    parameters: solver::Parameters,
+    cancel: &cancel::Token,
+    on_iteration: &mut dyn FnMut(&solver::Iteration),
+) -> Result<solver::Solution, solver::Error> {
+    const PROBE_ITERATIONS: u32 = 40;
+    const MARGIN: f64 = 1.5;
+
+    let probe = solver::denoise(
+        array,
+        &solver::Parameters {
+            max_iter: PROBE_ITERATIONS.min(parameters.max_iter),
+            ..parameters
+        },
+        cancel,
+        on_iteration,
+    )?;
+    if probe.converged || probe.iterations() >= parameters.max_iter {
+        log::info!(
+            "lambda {:.10}: probe of {} iterations is final",
+            parameters.lambda,
+            probe.iterations()
+        );
+        return Ok(probe);
+    }
+
+    let max_iter = match convergence::predict_iterations(
+        &probe.residuals,
+        parameters.convergence_threshold,
+    ) {
+        Some(predicted) => {
+            let max_iter = ((predicted as f64 * MARGIN) as u32)
+                .clamp(PROBE_ITERATIONS + 1, parameters.max_iter);
+            log::info!(
+                "lambda {:.10}: residual {:e} after {} iterations, {} \
+                 predicted to reach {:e}, chose max_iter = {}",
+                parameters.lambda,
+                probe.residual(),
+                probe.iterations(),
+                predicted,
+                parameters.convergence_threshold,
+                max_iter
+            );
+            max_iter
+        },
+        None => {
+            log::warn!(
+                "lambda {:.10}: residual not decaying in the probe, chose \
+                 max_iter = {}",
+                parameters.lambda,
+                parameters.max_iter
+            );
+            parameters.max_iter
+        },
+    };
+    solver::denoise(
+        array,
+        &solver::Parameters {
+            max_iter,
+            ..parameters
+        },
+        cancel,
+        on_iteration,
+    )
+}
+
+/// Appends `suffix` to the file stem of `path`, keeping its extension.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => {
+            format!("{}{}.{}", stem, suffix, extension.to_string_lossy())
+        },
+        None => format!("{}{}", stem, suffix),
+    };
+    path.with_file_name(file_name)
+}
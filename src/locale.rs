@@ -0,0 +1,1941 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Translations of the messages shown to users (the help, errors and the
+//! report summary), in the language of their locale or of --lang.
+//!
+//! Messages are written in English at the call site, with `{}` for each
+//! value, and looked up in the catalog of the chosen language by that
+//! text; those without a translation are shown in English. The help of the
+//! arguments is looked up by the text of their doc comments, and the
+//! errors of clap are rendered by [`Formatter`] from their context; the
+//! labels clap adds to the help itself (e.g. `[default: ]`) stay in
+//! English.
+
+use std::{
+    ffi::{
+        OsStr,
+        OsString,
+    },
+    fmt::{
+        Display,
+        Write as _,
+    },
+    sync::RwLock,
+};
+
+use clap::{
+    builder::{
+        styling::Style,
+        EnumValueParser,
+        PossibleValue,
+        StyledStr,
+        Styles,
+        TypedValueParser,
+    },
+    error::{
+        ContextKind,
+        ContextValue,
+        ErrorKind,
+    },
+    ValueEnum,
+};
+
+/// Languages with a catalog of translations.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    /// English
+    En,
+    /// Portuguese
+    Pt,
+}
+
+impl Lang {
+    /// The language of a locale name such as `pt_BR.UTF-8`, if it has a
+    /// catalog.
+    fn of_locale(locale: &str) -> Option<Self> {
+        let language = locale.split(['_', '.', '@']).next()?;
+        match language {
+            "en" | "C" | "POSIX" => Some(Lang::En),
+            "pt" => Some(Lang::Pt),
+            _ => None,
+        }
+    }
+
+    /// The language of the first locale variable set, in the order of
+    /// precedence of POSIX (`LC_ALL`, `LC_MESSAGES`, then `LANG`), or
+    /// English if it has no catalog.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| Self::of_locale(&locale))
+            .unwrap_or(Lang::En)
+    }
+
+    /// The language given by --lang in `args` (the name of the program
+    /// first), if any, so that it can be set before they are parsed.
+    pub fn from_args(args: &[OsString]) -> Option<Self> {
+        let args: Vec<_> = args
+            .iter()
+            .skip(1)
+            .map(|arg| arg.to_string_lossy())
+            .take_while(|arg| arg != "--")
+            .collect();
+        args.iter().enumerate().find_map(|(index, arg)| {
+            let value = match arg.strip_prefix("--lang")? {
+                "" => args.get(index + 1)?,
+                value => value.strip_prefix('=')?,
+            };
+            <Self as ValueEnum>::from_str(value, false).ok()
+        })
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::En => &[],
+            Lang::Pt => PT,
+        }
+    }
+}
+
+static LANG: RwLock<Lang> = RwLock::new(Lang::En);
+
+/// Sets the language of every message shown from now on.
+pub fn set_lang(lang: Lang) {
+    *LANG
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = lang;
+}
+
+/// The language set with `set_lang`.
+pub fn lang() -> Lang {
+    *LANG.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The translation of `message` into the language set, or `message` itself
+/// if it has none.
+pub fn translate(message: &'static str) -> &'static str {
+    lookup(message).unwrap_or(message)
+}
+
+/// The translation of `message` into the language set, if it has one.
+fn lookup(message: &str) -> Option<&'static str> {
+    lang()
+        .catalog()
+        .iter()
+        .find(|(english, _)| *english == message)
+        .map(|(_, translation)| *translation)
+}
+
+/// Replaces each `{}` of `message` by the next of `values`, in order.
+pub fn fill(message: &str, values: &[&dyn std::fmt::Display]) -> String {
+    let mut filled = String::with_capacity(message.len());
+    let mut values = values.iter();
+    let mut parts = message.split("{}");
+    filled.push_str(parts.next().unwrap_or_default());
+    for part in parts {
+        if let Some(value) = values.next() {
+            filled.push_str(&value.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+/// Translates a message and fills in its values, which are formatted
+/// beforehand if they need a precision or width.
+macro_rules! tr {
+    ($message:literal $(, $value:expr)* $(,)?) => {
+        crate::locale::fill(
+            crate::locale::translate($message),
+            &[$(&$value as &dyn std::fmt::Display),*],
+        )
+    };
+}
+
+/// `command` with its help in the language set: the about of the commands,
+/// the help of their arguments and the headings of both.
+pub fn command(mut command: clap::Command) -> clap::Command {
+    if lang() == Lang::En {
+        return command;
+    }
+    // so that the arguments and commands clap adds (e.g. --help) are there
+    command.build();
+    localize(command)
+}
+
+fn localize(command: clap::Command) -> clap::Command {
+    let text = |text: Option<&StyledStr>| lookup(&text?.to_string());
+    let about = text(command.get_about());
+    let long_about = text(command.get_long_about());
+    let usage = command.get_styles().get_usage();
+    let template = format!(
+        "{{before-help}}{{about-with-newline}}\n{}{}{:#} \
+         {{usage}}\n\n{{all-args}}{{after-help}}",
+        usage,
+        translate("Usage:"),
+        usage
+    );
+    let mut command = command
+        .help_template(template)
+        .subcommand_help_heading(translate("Commands"))
+        .mut_args(|mut arg| {
+            if let Some(help) = text(arg.get_help()) {
+                arg = arg.help(help);
+            }
+            if let Some(long_help) = text(arg.get_long_help()) {
+                arg = arg.long_help(long_help);
+            }
+            match arg.is_positional() {
+                true => arg.help_heading(translate("Arguments")),
+                false => arg.help_heading(translate("Options")),
+            }
+        })
+        .mut_subcommands(localize);
+    if let Some(about) = about {
+        command = command.about(about);
+    }
+    if let Some(long_about) = long_about {
+        command = command.long_about(long_about);
+    }
+    command
+}
+
+/// Parses the values of `E` as clap does, with their help in the language
+/// set.
+#[derive(Clone)]
+pub struct Values<E: ValueEnum + Clone + Send + Sync + 'static>(
+    EnumValueParser<E>,
+);
+
+/// The parser of the values of `E`, for `value_parser`.
+pub fn values<E: ValueEnum + Clone + Send + Sync + 'static>() -> Values<E> {
+    Values(EnumValueParser::new())
+}
+
+impl<E: ValueEnum + Clone + Send + Sync + 'static> TypedValueParser
+    for Values<E>
+{
+    type Value = E;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<E, clap::Error> {
+        self.0.parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(
+        &self,
+    ) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        let values = self.0.possible_values()?.map(|value| {
+            match value.get_help().and_then(|help| lookup(&help.to_string())) {
+                Some(help) => value.help(help),
+                None => value,
+            }
+        });
+        Some(Box::new(values))
+    }
+}
+
+/// Renders the errors of the command line as clap does, in the language
+/// set.
+pub struct Formatter;
+
+/// An error of the command line, rendered in the language set.
+pub type Error = clap::error::Error<Formatter>;
+
+/// An error of `command` with `message`, as `clap::Command::error` makes
+/// them (which clap renders in English).
+pub fn error(
+    command: &mut clap::Command,
+    kind: ErrorKind,
+    message: impl Display,
+) -> Error {
+    let mut error = Error::new(kind).with_cmd(command);
+    error
+        .insert(ContextKind::Custom, ContextValue::String(message.to_string()));
+    error.insert(
+        ContextKind::Usage,
+        ContextValue::StyledStr(command.render_usage()),
+    );
+    error
+}
+
+impl clap::error::ErrorFormatter for Formatter {
+    fn format_error(error: &Error) -> StyledStr {
+        let styles = Styles::default();
+        let mut styled = StyledStr::new();
+        let _ = write!(
+            styled,
+            "{} {}",
+            paint(styles.get_error(), &tr!("error:")),
+            message(error, &styles)
+        );
+
+        let mut suggested = false;
+        for (kind, one, several) in [
+            (
+                ContextKind::SuggestedSubcommand,
+                translate("a similar subcommand exists: {}"),
+                translate("some similar subcommands exist: {}"),
+            ),
+            (
+                ContextKind::SuggestedArg,
+                translate("a similar argument exists: {}"),
+                translate("some similar arguments exist: {}"),
+            ),
+            (
+                ContextKind::SuggestedValue,
+                translate("a similar value exists: {}"),
+                translate("some similar values exist: {}"),
+            ),
+        ] {
+            let similar = match error.get(kind) {
+                Some(ContextValue::String(similar)) => vec![similar.clone()],
+                Some(ContextValue::Strings(similar)) => similar.clone(),
+                _ => continue,
+            };
+            styled.push_str(if suggested { "\n" } else { "\n\n" });
+            suggested = true;
+            let listed = similar
+                .iter()
+                .map(|similar| {
+                    format!("'{}'", paint(styles.get_valid(), similar))
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let _ = write!(
+                styled,
+                "  {} {}",
+                paint(styles.get_valid(), &tr!("tip:")),
+                fill(
+                    if similar.len() == 1 { one } else { several },
+                    &[&listed]
+                )
+            );
+        }
+        if let Some(ContextValue::StyledStrs(tips)) =
+            error.get(ContextKind::Suggested)
+        {
+            if !suggested {
+                styled.push_str("\n");
+            }
+            for tip in tips {
+                let _ = write!(
+                    styled,
+                    "\n  {} {}",
+                    paint(styles.get_valid(), &tr!("tip:")),
+                    tip.ansi()
+                );
+            }
+        }
+
+        if let Some(ContextValue::StyledStr(usage)) =
+            error.get(ContextKind::Usage)
+        {
+            let usage = usage.ansi().to_string();
+            let _ = write!(
+                styled,
+                "\n\n{}",
+                usage.replacen("Usage:", translate("Usage:"), 1)
+            );
+        }
+        let _ = writeln!(
+            styled,
+            "\n\n{}",
+            tr!(
+                "For more information, try '{}'.",
+                paint(styles.get_literal(), "--help")
+            )
+        );
+        styled
+    }
+}
+
+/// What went wrong in `error`, from its context.
+fn message(error: &Error, styles: &Styles) -> String {
+    let string = |kind| match error.get(kind) {
+        Some(ContextValue::String(value)) => Some(value.as_str()),
+        _ => None,
+    };
+    let strings = |kind| match error.get(kind) {
+        Some(ContextValue::Strings(values)) => Some(values.as_slice()),
+        _ => None,
+    };
+    let number = |kind| match error.get(kind) {
+        Some(ContextValue::Number(number)) => Some(number.to_owned()),
+        _ => None,
+    };
+    let listed = |name: &str, kind| match strings(kind) {
+        Some(values) if !values.is_empty() => format!(
+            "\n  [{}: {}]",
+            name,
+            values
+                .iter()
+                .map(|value| paint(styles.get_valid(), value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => String::new(),
+    };
+    if let Some(custom) = string(ContextKind::Custom) {
+        return custom.to_string();
+    }
+    let arg = string(ContextKind::InvalidArg);
+    let value = string(ContextKind::InvalidValue);
+
+    let message = match error.kind() {
+        ErrorKind::ArgumentConflict => {
+            let prior = error.get(ContextKind::PriorArg);
+            let (conflict, prior) =
+                match (arg, string(ContextKind::InvalidSubcommand)) {
+                    (Some(arg), _)
+                        if prior
+                            == Some(&ContextValue::String(arg.to_string())) =>
+                    {
+                        (
+                            tr!(
+                                "the argument '{}' cannot be used multiple \
+                                 times",
+                                paint(styles.get_invalid(), arg)
+                            ),
+                            None,
+                        )
+                    },
+                    (Some(arg), _) => (
+                        tr!(
+                            "the argument '{}' cannot be used with",
+                            paint(styles.get_invalid(), arg)
+                        ),
+                        Some(prior),
+                    ),
+                    (None, Some(subcommand)) => (
+                        tr!(
+                            "the subcommand '{}' cannot be used with",
+                            paint(styles.get_invalid(), subcommand)
+                        ),
+                        Some(prior),
+                    ),
+                    (None, None) => return fallback(error),
+                };
+            Some(match prior {
+                None => conflict,
+                Some(Some(ContextValue::Strings(prior))) => prior.iter().fold(
+                    format!("{}:", conflict),
+                    |listed, prior| {
+                        format!(
+                            "{}\n  {}",
+                            listed,
+                            paint(styles.get_invalid(), prior)
+                        )
+                    },
+                ),
+                Some(Some(ContextValue::String(prior))) => format!(
+                    "{} '{}'",
+                    conflict,
+                    paint(styles.get_invalid(), prior)
+                ),
+                Some(_) => format!(
+                    "{} {}",
+                    conflict,
+                    tr!("one or more of the other specified arguments")
+                ),
+            })
+        },
+        ErrorKind::NoEquals => arg.map(|arg| {
+            tr!(
+                "equal sign is needed when assigning values to '{}'",
+                paint(styles.get_invalid(), arg)
+            )
+        }),
+        ErrorKind::InvalidValue => arg.zip(value).map(|(arg, value)| {
+            let invalid = match value.is_empty() {
+                true => tr!(
+                    "a value is required for '{}' but none was supplied",
+                    paint(styles.get_invalid(), arg)
+                ),
+                false => tr!(
+                    "invalid value '{}' for '{}'",
+                    paint(styles.get_invalid(), value),
+                    paint(styles.get_literal(), arg)
+                ),
+            };
+            invalid
+                + &listed(translate("possible values"), ContextKind::ValidValue)
+        }),
+        ErrorKind::InvalidSubcommand => string(ContextKind::InvalidSubcommand)
+            .map(|subcommand| {
+                tr!(
+                    "unrecognized subcommand '{}'",
+                    paint(styles.get_invalid(), subcommand)
+                )
+            }),
+        ErrorKind::MissingRequiredArgument => strings(ContextKind::InvalidArg)
+            .map(|missing| {
+                missing.iter().fold(
+                    tr!("the following required arguments were not provided:"),
+                    |listed, missing| {
+                        format!(
+                            "{}\n  {}",
+                            listed,
+                            paint(styles.get_valid(), missing)
+                        )
+                    },
+                )
+            }),
+        ErrorKind::MissingSubcommand => string(ContextKind::InvalidSubcommand)
+            .map(|subcommand| {
+                tr!(
+                    "'{}' requires a subcommand but one was not provided",
+                    paint(styles.get_invalid(), subcommand)
+                ) + &listed(
+                    translate("subcommands"),
+                    ContextKind::ValidSubcommand,
+                )
+            }),
+        ErrorKind::TooManyValues => arg.zip(value).map(|(arg, value)| {
+            tr!(
+                "unexpected value '{}' for '{}' found; no more were expected",
+                paint(styles.get_invalid(), value),
+                paint(styles.get_literal(), arg)
+            )
+        }),
+        ErrorKind::TooFewValues => arg
+            .zip(number(ContextKind::ActualNumValues))
+            .zip(number(ContextKind::MinValues))
+            .map(|((arg, actual), min)| {
+                let values = (
+                    paint(styles.get_valid(), &min.to_string()),
+                    paint(styles.get_literal(), arg),
+                    paint(styles.get_invalid(), &actual.to_string()),
+                );
+                match actual > 1 {
+                    true => tr!(
+                        "{} values required by '{}'; only {} were provided",
+                        values.0,
+                        values.1,
+                        values.2
+                    ),
+                    false => tr!(
+                        "{} values required by '{}'; only {} was provided",
+                        values.0,
+                        values.1,
+                        values.2
+                    ),
+                }
+            }),
+        ErrorKind::ValueValidation => arg.zip(value).map(|(arg, value)| {
+            let invalid = tr!(
+                "invalid value '{}' for '{}'",
+                paint(styles.get_invalid(), value),
+                paint(styles.get_literal(), arg)
+            );
+            match std::error::Error::source(error) {
+                Some(source) => format!("{}: {}", invalid, source),
+                None => invalid,
+            }
+        }),
+        ErrorKind::WrongNumberOfValues => arg
+            .zip(number(ContextKind::ActualNumValues))
+            .zip(number(ContextKind::ExpectedNumValues))
+            .map(|((arg, actual), expected)| {
+                let values = (
+                    paint(styles.get_valid(), &expected.to_string()),
+                    paint(styles.get_literal(), arg),
+                    paint(styles.get_invalid(), &actual.to_string()),
+                );
+                match actual > 1 {
+                    true => tr!(
+                        "{} values required for '{}' but {} were provided",
+                        values.0,
+                        values.1,
+                        values.2
+                    ),
+                    false => tr!(
+                        "{} values required for '{}' but {} was provided",
+                        values.0,
+                        values.1,
+                        values.2
+                    ),
+                }
+            }),
+        ErrorKind::UnknownArgument => arg.map(|arg| {
+            tr!(
+                "unexpected argument '{}' found",
+                paint(styles.get_invalid(), arg)
+            )
+        }),
+        _ => None,
+    };
+    message.unwrap_or_else(|| fallback(error))
+}
+
+/// `text` in `style`, for the terminal.
+fn paint(style: &Style, text: &str) -> String {
+    format!("{}{}{:#}", style, text, style)
+}
+
+/// What went wrong in `error`, from its kind alone.
+fn fallback(error: &Error) -> String {
+    match (error.kind().as_str(), std::error::Error::source(error)) {
+        (Some(kind), _) => translate(kind).to_string(),
+        (None, Some(source)) => source.to_string(),
+        (None, None) => tr!("unknown cause"),
+    }
+}
+
+/// Portuguese, as spoken in Brazil.
+const PT: &[(&str, &str)] = &[
+    // validation errors
+    (
+        "`input_image` must be a valid file",
+        "`input_image` deve ser um arquivo válido",
+    ),
+    (
+        "`output_path` must be a valid directory",
+        "`output_path` deve ser uma pasta válida",
+    ),
+    ("`{}` must be a valid directory", "`{}` deve ser uma pasta válida"),
+    ("`{}` must be a valid file", "`{}` deve ser um arquivo válido"),
+    (
+        "`start_lambda` must be smaller than `end_lambda`",
+        "`start_lambda` deve ser menor que `end_lambda`",
+    ),
+    (
+        "{} `jobs` of {} `threads_per_task` each are more than the \
+         `max_parallelism` of {}",
+        "{} `jobs` de {} `threads_per_task` cada são mais que o \
+         `max_parallelism` de {}",
+    ),
+    (
+        "`patch` {} must fit in the {}x{} image",
+        "`patch` {} deve caber na imagem de {}x{}",
+    ),
+    (
+        "`noise_patch` is in the coordinates of the whole image, it requires \
+         `composite` when a `patch` is given",
+        "`noise_patch` está nas coordenadas da imagem inteira, e requer \
+         `composite` quando um `patch` é dado",
+    ),
+    ("`{}` must be smaller than {}", "`{}` deve ser menor que {}"),
+    (
+        "`{}` must have the same dimensions as the input, {}x{}",
+        "`{}` deve ter as mesmas dimensões da entrada, {}x{}",
+    ),
+    (
+        "output `{}` would overwrite an input",
+        "a saída `{}` sobrescreveria uma entrada",
+    ),
+    (
+        "{} {} is over the limits of --max-megapixels or --max-decoded-size",
+        "{} {} está acima dos limites de --max-megapixels ou \
+         --max-decoded-size",
+    ),
+    ("{} {} could not be open: {}", "{} {} não pôde ser aberto: {}"),
+    (
+        "`{}` is {}x{} ({} megapixels), over the limit of --max-megapixels",
+        "`{}` tem {}x{} ({} megapixels), acima do limite de --max-megapixels",
+    ),
+    (
+        "`noise_patch` {} must fit in the {}x{} image",
+        "`noise_patch` {} deve caber na imagem de {}x{}",
+    ),
+    (
+        "`restrict_paths` must be folders: {}",
+        "`restrict_paths` devem ser pastas: {}",
+    ),
+    (
+        "`{}` must be inside `restrict_paths`",
+        "`{}` deve estar dentro de `restrict_paths`",
+    ),
+    (
+        "`compare_with` runs commands, whose file access `restrict_paths` \
+         cannot confine",
+        "`compare_with` executa comandos, cujo acesso a arquivos \
+         `restrict_paths` não consegue confinar",
+    ),
+    (
+        "audit log could not be continued: {}",
+        "o registro de auditoria não pôde ser continuado: {}",
+    ),
+    (
+        "no parameters could be read for {} (was it saved with \
+         --save-params?): {}",
+        "nenhum parâmetro pôde ser lido de {} (foi salvo com --save-params?): \
+         {}",
+    ),
+    (
+        "--stop-on-gap cannot be combined with --auto-iter",
+        "--stop-on-gap não pode ser combinado com --auto-iter",
+    ),
+    (
+        "{} was solved with the `{}` {}, which --like does not take for \
+         another input: give its own",
+        "{} foi resolvido com o `{}` {}, que --like não usa para outra \
+         entrada: forneça o seu",
+    ),
+    ("{} could not be read: {}", "{} não pôde ser lido: {}"),
+    (
+        "output `{}` is outside of --restrict-paths",
+        "a saída `{}` está fora de --restrict-paths",
+    ),
+    // report summary
+    (
+        "summary for {} (estimated noise: {}{}):",
+        "resumo de {} (ruído estimado: {}{}):",
+    ),
+    (" in patch {}", " no recorte {}"),
+    (
+        "against each of the {} references (the metrics above are against \
+         their mean):",
+        "em relação a cada uma das {} referências (as métricas acima são em \
+         relação à média delas):",
+    ),
+    ("reference", "referência"),
+    (
+        "per channel, with the samples as they are denoised (gamma encoded, \
+         or linear for floating point inputs):",
+        "por canal, com as amostras como são tratadas (com codificação gama, \
+         ou lineares para entradas de ponto flutuante):",
+    ),
+    ("channel", "canal"),
+    ("`input` must be a valid file", "`input` deve ser um arquivo válido"),
+    (
+        "the clean images must have different names: {}",
+        "as imagens limpas devem ter nomes diferentes: {}",
+    ),
+    (
+        "`{}` must be a finite number greater than 0",
+        "`{}` deve ser um número finito maior que 0",
+    ),
+    ("{} could not be resumed: {}", "não foi possível retomar {}: {}"),
+    (
+        "`{}` cannot be {}: {} (or give --force to run it anyway)",
+        "`{}` não pode ser {}: {} (ou use --force para executar mesmo assim)",
+    ),
+    (
+        "lambda must be a finite number greater than 0, e.g. `-s 0.001`",
+        "lambda deve ser um número finito maior que 0, p. ex. `-s 0.001`",
+    ),
+    (
+        "lambda must be a finite number greater than 0, e.g. `-e 1`",
+        "lambda deve ser um número finito maior que 0, p. ex. `-e 1`",
+    ),
+    (
+        "--stop-on-gap cannot be measured with clipped samples left out of \
+         the data term: give a --clipped-weight greater than 0",
+        "--stop-on-gap não pode ser medido com amostras saturadas fora do \
+         termo de dados: use um --clipped-weight maior que 0",
+    ),
+    (
+        "it is the primal-dual gap relative to the energy to stop at, a \
+         finite number greater than 0, e.g. `--stop-on-gap 1e-4`",
+        "é a lacuna primal-dual relativa à energia na qual parar, um número \
+         finito maior que 0, p. ex. `--stop-on-gap 1e-4`",
+    ),
+    (
+        "it is the relative change between iterations to stop at, a finite \
+         number greater than 0, e.g. `-c 1e-6`",
+        "é a variação relativa entre iterações na qual parar, um número \
+         finito maior que 0, p. ex. `-c 1e-6`",
+    ),
+    (
+        "it is a standard deviation in 8-bit levels, greater than 0; leave it \
+         out to estimate it from the input",
+        "é um desvio padrão em níveis de 8 bits, maior que 0; omita-o para \
+         estimá-lo a partir da entrada",
+    ),
+    (
+        "it is a number of standard deviations greater than 0, e.g. 6",
+        "é um número de desvios padrão maior que 0, p. ex. 6",
+    ),
+    (
+        "it is a step in 8-bit levels, greater than 0, e.g. 8",
+        "é um degrau em níveis de 8 bits, maior que 0, p. ex. 8",
+    ),
+    (
+        "at least one iteration is needed, e.g. `-m 1000`",
+        "é preciso ao menos uma iteração, p. ex. `-m 1000`",
+    ),
+    (
+        "it is a difference in 8-bit levels, 0 or more",
+        "é uma diferença em níveis de 8 bits, 0 ou mais",
+    ),
+    (
+        "it is a fraction of the ramp patches, from 0 to 1",
+        "é uma fração dos recortes de rampa, de 0 a 1",
+    ),
+    (
+        "it is the step of the banding in 8-bit levels, greater than 0, e.g. \
+         1 for 8-bit gradients",
+        "é o degrau das faixas em níveis de 8 bits, maior que 0, p. ex. 1 \
+         para gradientes de 8 bits",
+    ),
+    (
+        "it is a weight, from 0 (left out) to 1 (as any other sample)",
+        "é um peso, de 0 (deixada de fora) a 1 (como qualquer outra amostra)",
+    ),
+    ("red", "vermelho"),
+    ("green", "verde"),
+    ("blue", "azul"),
+    ("gray", "cinza"),
+    ("noise", "ruído"),
+    ("edges", "bordas"),
+    ("shift", "desvio"),
+    ("staircasing", "degraus"),
+    ("iterations", "iterações"),
+    ("energy", "energia"),
+    ("compared with other methods:", "comparado com outros métodos:"),
+    ("method", "método"),
+    ("seconds", "segundos"),
+    (
+        "(!) staircasing above {} expected for lambda <= {}",
+        "(!) degraus acima de {} esperados para lambda <= {}",
+    ),
+    (
+        "lambda chosen by the discrepancy principle: {} (differing from the \
+         input by {} for a noise of {})",
+        "lambda escolhido pelo princípio da discrepância: {} (diferindo da \
+         entrada em {} para um ruído de {})",
+    ),
+    (
+        "suggested lambda (corner of the L-curve): {}",
+        "lambda sugerido (canto da curva L): {}",
+    ),
+    (
+        "(*) lambda {} stopped on max_iter before converging, residual {}: \
+         the output may not be fully converged",
+        "(*) lambda {} parou em max_iter antes de convergir, resíduo {}: a \
+         saída pode não ter convergido por completo",
+    ),
+    ("(v) lambda {} output {} {}", "(v) lambda {} saída {} {}"),
+    ("golden lambda", "lambda golden"),
+    ("max diff", "dif. máx."),
+    ("rms diff", "dif. rms"),
+    ("differing", "diferentes"),
+    ("{} (was {})", "{} (era {})"),
+    ("(g) lambda {} golden output {} {}", "(g) lambda {} saída golden {} {}"),
+    (
+        "(g) lambda {} differs from its golden output in {} samples, by more \
+         than {}",
+        "(g) lambda {} difere da sua saída golden em {} amostras, por mais de \
+         {}",
+    ),
+    ("(x) lambda {} failed: {}", "(x) lambda {} falhou: {}"),
+    ("(x) method {} failed: {}", "(x) método {} falhou: {}"),
+    (
+        "cost: {} seconds, {} CPU-seconds, {} joules, {} megapixel-iterations",
+        "custo: {} segundos, {} segundos de CPU, {} joules, {} \
+         megapixel-iterações",
+    ),
+    // errors of the command line
+    ("error:", "erro:"),
+    ("tip:", "dica:"),
+    ("a similar subcommand exists: {}", "existe um subcomando parecido: {}"),
+    ("some similar subcommands exist: {}", "existem subcomandos parecidos: {}"),
+    ("a similar argument exists: {}", "existe um argumento parecido: {}"),
+    ("some similar arguments exist: {}", "existem argumentos parecidos: {}"),
+    ("a similar value exists: {}", "existe um valor parecido: {}"),
+    ("some similar values exist: {}", "existem valores parecidos: {}"),
+    ("Usage:", "Uso:"),
+    ("For more information, try '{}'.", "Para mais informações, tente '{}'."),
+    (
+        "the argument '{}' cannot be used multiple times",
+        "o argumento '{}' não pode ser usado mais de uma vez",
+    ),
+    (
+        "the argument '{}' cannot be used with",
+        "o argumento '{}' não pode ser usado com",
+    ),
+    (
+        "the subcommand '{}' cannot be used with",
+        "o subcomando '{}' não pode ser usado com",
+    ),
+    (
+        "one or more of the other specified arguments",
+        "um ou mais dos outros argumentos dados",
+    ),
+    (
+        "equal sign is needed when assigning values to '{}'",
+        "é preciso um sinal de igual ao atribuir valores a '{}'",
+    ),
+    (
+        "a value is required for '{}' but none was supplied",
+        "um valor é exigido para '{}', mas nenhum foi dado",
+    ),
+    ("invalid value '{}' for '{}'", "valor inválido '{}' para '{}'"),
+    ("possible values", "valores possíveis"),
+    ("unrecognized subcommand '{}'", "subcomando não reconhecido '{}'"),
+    (
+        "the following required arguments were not provided:",
+        "os seguintes argumentos obrigatórios não foram dados:",
+    ),
+    (
+        "'{}' requires a subcommand but one was not provided",
+        "'{}' exige um subcomando, mas nenhum foi dado",
+    ),
+    ("subcommands", "subcomandos"),
+    (
+        "unexpected value '{}' for '{}' found; no more were expected",
+        "valor inesperado '{}' para '{}'; nenhum outro era esperado",
+    ),
+    (
+        "{} values required by '{}'; only {} were provided",
+        "{} valores exigidos por '{}'; só {} foram dados",
+    ),
+    (
+        "{} values required by '{}'; only {} was provided",
+        "{} valores exigidos por '{}'; só {} foi dado",
+    ),
+    (
+        "{} values required for '{}' but {} were provided",
+        "{} valores exigidos para '{}', mas {} foram dados",
+    ),
+    (
+        "{} values required for '{}' but {} was provided",
+        "{} valores exigidos para '{}', mas {} foi dado",
+    ),
+    ("unexpected argument '{}' found", "argumento inesperado '{}'"),
+    ("unknown cause", "causa desconhecida"),
+    (
+        "one of the values isn't valid for an argument",
+        "um dos valores não é válido para um argumento",
+    ),
+    ("unexpected argument found", "argumento inesperado"),
+    ("unrecognized subcommand", "subcomando não reconhecido"),
+    (
+        "equal is needed when assigning values to one of the arguments",
+        "é preciso um sinal de igual ao atribuir valores a um dos argumentos",
+    ),
+    (
+        "invalid value for one of the arguments",
+        "valor inválido para um dos argumentos",
+    ),
+    (
+        "unexpected value for an argument found",
+        "valor inesperado para um argumento",
+    ),
+    (
+        "more values required for an argument",
+        "mais valores exigidos para um argumento",
+    ),
+    (
+        "too many or too few values for an argument",
+        "valores demais ou de menos para um argumento",
+    ),
+    (
+        "an argument cannot be used with one or more of the other specified \
+         arguments",
+        "um argumento não pode ser usado com um ou mais dos outros argumentos \
+         dados",
+    ),
+    (
+        "one or more required arguments were not provided",
+        "um ou mais argumentos obrigatórios não foram dados",
+    ),
+    (
+        "a subcommand is required but one was not provided",
+        "um subcomando é exigido, mas nenhum foi dado",
+    ),
+    (
+        "invalid UTF-8 was detected in one or more arguments",
+        "UTF-8 inválido foi detectado em um ou mais argumentos",
+    ),
+    // help of the command line
+    ("Commands", "Comandos"),
+    ("Arguments", "Argumentos"),
+    ("Options", "Opções"),
+    (
+        "CLI wrapper for the denoising algorithm from image-recovery.",
+        "Interface de linha de comando para o algoritmo de remoção de ruído \
+         do image-recovery.",
+    ),
+    (
+        "CLI wrapper for the denoising algorithm from image-recovery.\n\nλ \
+         values:\n\nThe algorithm will run on the given input for as many λ \
+         values as given. Simply choose a start and end point, as well as how \
+         many steps there should be in between.\n\nStopping \
+         conditions:\n\nThe algorithm will run for at most `max_iter` number \
+         of iterations per λ value, but may stop earlier if the relative \
+         differente between the current candidate output and the previous \
+         iteration's candidate output becomes smaller than the given value \
+         for the `convergence_threshold` (or the one corresponding to the \
+         change per pixel given as `stop_at_delta`), or, with `stop_on_gap`, \
+         once the primal-dual gap relative to the energy becomes smaller than \
+         the given value\n\nWith `auto_iter`, `max_iter` is instead chosen \
+         from the convergence rate measured on the first iterations.",
+        "Interface de linha de comando para o algoritmo de remoção de ruído \
+         do image-recovery.\n\nValores de λ:\n\nO algoritmo é executado sobre \
+         a entrada para tantos valores de λ quantos forem dados. Basta \
+         escolher um ponto de início e um de fim, e quantos passos deve haver \
+         entre eles.\n\nCondições de parada:\n\nO algoritmo é executado por \
+         no máximo `max_iter` iterações por valor de λ, mas pode parar antes \
+         se a diferença relativa entre a saída candidata atual e a da \
+         iteração anterior ficar menor que o valor dado para \
+         `convergence_threshold` (ou o correspondente à mudança por pixel \
+         dada como `stop_at_delta`), ou, com `stop_on_gap`, quando a lacuna \
+         primal-dual relativa à energia ficar menor que o valor dado\n\nCom \
+         `auto_iter`, `max_iter` é escolhido a partir da taxa de convergência \
+         medida nas primeiras iterações.",
+    ),
+    ("Path of input image", "Caminho da imagem de entrada"),
+    (
+        "Path of folder in which output images should be saved",
+        "Caminho da pasta em que as imagens de saída devem ser salvas",
+    ),
+    (
+        "How to make the names of the outputs valid on the filesystem they \
+         are saved to",
+        "Como tornar os nomes das saídas válidos no sistema de arquivos em \
+         que são salvas",
+    ),
+    (
+        "Names as they are derived, e.g. `img_lambda_=_0.010.png`",
+        "Nomes como são derivados, por exemplo `img_lambda_=_0.010.png`",
+    ),
+    (
+        "Replace what Windows rejects (`<>:\"/\\|?*` and control characters, \
+         trailing dots and spaces, device names such as `CON` or `COM1`)",
+        "Substituir o que o Windows rejeita (`<>:\"/\\|?*` e caracteres de \
+         controle, pontos e espaços no fim, nomes de dispositivos como `CON` \
+         ou `COM1`)",
+    ),
+    (
+        "Only keep the POSIX portable characters (ASCII letters, digits, `.`, \
+         `_` and `-`), e.g. `img_lambda_0.010.png`",
+        "Manter só os caracteres portáveis do POSIX (letras ASCII, dígitos, \
+         `.`, `_` e `-`), por exemplo `img_lambda_0.010.png`",
+    ),
+    (
+        "Transliterate the accented letters of the input's name to ASCII in \
+         the names of the outputs (e.g. `é` as `e`), replacing other \
+         non-ASCII characters by `_`",
+        "Transliterar para ASCII as letras acentuadas do nome da entrada nos \
+         nomes das saídas (por exemplo `é` como `e`), substituindo outros \
+         caracteres não ASCII por `_`",
+    ),
+    (
+        "How to write the lambda value in the names of the outputs",
+        "Como escrever o valor de lambda nos nomes das saídas",
+    ),
+    (
+        "As many decimals as it takes to tell the values of the sweep apart, \
+         e.g. `0.010`",
+        "Tantas casas decimais quantas forem precisas para distinguir os \
+         valores da varredura, por exemplo `0.010`",
+    ),
+    (
+        "As many significant digits as it takes to tell the values of the \
+         sweep apart, in scientific notation, e.g. `1.0e-2`",
+        "Tantos algarismos significativos quantos forem precisos para \
+         distinguir os valores da varredura, em notação científica, por \
+         exemplo `1.0e-2`",
+    ),
+    (
+        "Always 10 decimals, e.g. `0.0100000000`",
+        "Sempre 10 casas decimais, por exemplo `0.0100000000`",
+    ),
+    ("Maximum number of iterations", "Número máximo de iterações"),
+    (
+        "Choose the maximum number of iterations for each lambda value from \
+         the convergence rate measured on a short probe, predicted to reach \
+         the threshold; --max-iter, if given, caps it",
+        "Escolher o número máximo de iterações de cada valor de lambda a \
+         partir da taxa de convergência medida em uma sondagem curta, \
+         previsto para atingir o limiar; --max-iter, se dado, o limita",
+    ),
+    (
+        "How the solver's step sizes evolve over the iterations",
+        "Como os tamanhos de passo do solver evoluem ao longo das iterações",
+    ),
+    (
+        "Fixed steps, as in the basic algorithm",
+        "Passos fixos, como no algoritmo básico",
+    ),
+    (
+        "Primal steps shrinking (and dual steps growing) with `gamma`, as in \
+         image-recovery",
+        "Passos primais diminuindo (e passos duais aumentando) com `gamma`, \
+         como no image-recovery",
+    ),
+    (
+        "Steps rebalanced whenever the primal or dual residual dominates the \
+         other, after Goldstein, T. et al. (2013); often converges in fewer \
+         iterations, each costing more",
+        "Passos reequilibrados sempre que o resíduo primal ou o dual domina o \
+         outro, segundo Goldstein, T. et al. (2013); costuma convergir em \
+         menos iterações, cada uma custando mais",
+    ),
+    (
+        "Floating point precision of the solver; `auto` solves a downscaled \
+         copy of the input in both first, and picks f32 if it makes no \
+         visible difference",
+        "Precisão de ponto flutuante do solver; `auto` resolve antes uma \
+         cópia reduzida da entrada em ambas, e escolhe f32 se não fizer \
+         diferença visível",
+    ),
+    (
+        "f32 if a quick check finds no visible difference from f64",
+        "f32 se uma verificação rápida não encontrar diferença visível de f64",
+    ),
+    (
+        "Solve all channels even when they are (nearly) the same, as in \
+         grayscale images stored as RGB, which are otherwise solved as a \
+         single gray channel replicated on output",
+        "Resolver todos os canais mesmo quando são (quase) iguais, como em \
+         imagens em tons de cinza armazenadas como RGB, que de outro modo são \
+         resolvidas como um único canal cinza replicado na saída",
+    ),
+    (
+        "Denoise the two fields (even and odd rows) of interlaced frames, as \
+         in broadcast material, separately so that the combing between them \
+         isn't smeared across both; `auto` does so if the input shows combing",
+        "Remover o ruído dos dois campos (linhas pares e ímpares) de quadros \
+         entrelaçados, como em material de transmissão, separadamente para \
+         que o pente entre eles não seja espalhado por ambos; `auto` faz isso \
+         se a entrada mostrar pente",
+    ),
+    ("Denoise the frame as a whole", "Remover o ruído do quadro como um todo"),
+    (
+        "Denoise the fields separately if the frame shows combing",
+        "Remover o ruído dos campos separadamente se o quadro mostrar pente",
+    ),
+    (
+        "Denoise the fields separately",
+        "Remover o ruído dos campos separadamente",
+    ),
+    (
+        "Also save a snapshot of the result after every this many iterations, \
+         as `_iteration_<n>` images",
+        "Salvar também um instantâneo do resultado a cada tantas iterações, \
+         como imagens `_iteration_<n>`",
+    ),
+    (
+        "Also save a snapshot of the result at the first iteration whose \
+         residual is under each power of ten (1e-1, 1e-2...), as \
+         `_residual_1e-<n>` images",
+        "Salvar também um instantâneo do resultado na primeira iteração cujo \
+         resíduo fica abaixo de cada potência de dez (1e-1, 1e-2...), como \
+         imagens `_residual_1e-<n>`",
+    ),
+    (
+        "Compute the energy (the objective the solver minimizes) after every \
+         iteration, logged with the residuals (-vvv) and kept in the \
+         --report, rather than only once it stops",
+        "Calcular a energia (o objetivo que o solver minimiza) após cada \
+         iteração, registrada com os resíduos (-vvv) e mantida no --report, \
+         em vez de só quando ele para",
+    ),
+    (
+        "Keep the intermediate files of each λ value (the images of \
+         --snapshot-every and --snapshot-at-residuals) in a folder of its own \
+         in this one instead of the output folder, removed once its output is \
+         saved",
+        "Manter os arquivos intermediários de cada valor de λ (as imagens de \
+         --snapshot-every e --snapshot-at-residuals) em uma pasta própria \
+         dentro desta em vez da pasta de saída, removida quando sua saída é \
+         salva",
+    ),
+    (
+        "Refuse to start unless this much space (e.g. `10G`) would be left \
+         free on each filesystem after saving the outputs, whose size is \
+         estimated before starting",
+        "Recusar-se a começar a menos que sobre esse espaço livre (por \
+         exemplo `10G`) em cada sistema de arquivos depois de salvar as \
+         saídas, cujo tamanho é estimado antes de começar",
+    ),
+    (
+        "Show the progress of the sweep on stderr, with an estimate of the \
+         time left from how fast the residual of each solve is falling: as a \
+         status line (`bar`, if given alone) or as lines of plain text",
+        "Mostrar o progresso da varredura no stderr, com uma estimativa do \
+         tempo restante a partir de quão rápido cai o resíduo de cada \
+         resolução: como uma linha de status (`bar`, se dado sozinho) ou como \
+         linhas de texto simples",
+    ),
+    (
+        "A status line redrawn in place, with terminal escape codes",
+        "Uma linha de status redesenhada no lugar, com códigos de escape do \
+         terminal",
+    ),
+    (
+        "A new line of plain text every 10 seconds and after every solve, for \
+         screen readers and logs that don't understand escape codes",
+        "Uma nova linha de texto simples a cada 10 segundos e após cada \
+         resolução, para leitores de tela e logs que não entendem códigos de \
+         escape",
+    ),
+    (
+        "Print how the λ values, the step sizes, the stopping criteria and \
+         the threads were derived, from what and with which formulas, before \
+         solving",
+        "Mostrar como os valores de λ, os tamanhos de passo, os critérios de \
+         parada e as threads foram derivados, de quê e com quais fórmulas, \
+         antes de resolver",
+    ),
+    (
+        "Re-open each saved output to check that it decodes with the expected \
+         dimensions and bit depth",
+        "Reabrir cada saída salva para verificar que ela decodifica com as \
+         dimensões e a profundidade de bits esperadas",
+    ),
+    (
+        "With `--verify-output`, also check that the checksum of the saved \
+         samples matches the result's",
+        "Com `--verify-output`, verificar também que o checksum das amostras \
+         salvas corresponde ao do resultado",
+    ),
+    (
+        "Solve each λ value a second time on a different number of threads, \
+         failing it unless both results are bit-identical",
+        "Resolver cada valor de λ uma segunda vez em um número diferente de \
+         threads, falhando-o a menos que ambos os resultados sejam idênticos \
+         bit a bit",
+    ),
+    (
+        "Save the exact outputs (at full precision, before --output-scale) in \
+         this folder, for later runs to be checked against with \
+         --compare-golden",
+        "Salvar as saídas exatas (em precisão total, antes de --output-scale) \
+         nesta pasta, para que execuções futuras sejam comparadas a elas com \
+         --compare-golden",
+    ),
+    (
+        "Check the outputs against those saved with --record-golden in this \
+         folder, failing if any sample differs by more than --tolerance",
+        "Comparar as saídas com as salvas com --record-golden nesta pasta, \
+         falhando se alguma amostra diferir por mais que --tolerance",
+    ),
+    (
+        "Largest difference from the golden outputs allowed by \
+         --compare-golden, in 8-bit levels",
+        "Maior diferença das saídas golden permitida por --compare-golden, em \
+         níveis de 8 bits",
+    ),
+    ("Convergence threshold", "Limiar de convergência"),
+    (
+        "Stop once the root-mean-square change per pixel between iterations \
+         is below this fraction of the full range, given as a fraction or as \
+         levels out of a full scale (e.g. `0.1/255`); translated into a \
+         --convergence-threshold for the input",
+        "Parar quando a variação quadrática média por pixel entre iterações \
+         ficar abaixo desta fração da faixa completa, dada como fração ou \
+         como níveis de uma escala completa (por exemplo `0.1/255`); \
+         traduzida em um --convergence-threshold para a entrada",
+    ),
+    (
+        "Stop once the primal-dual gap (the energy less the objective of the \
+         dual problem, which bounds the smallest energy from below) relative \
+         to the energy is below this, rather than on the change between \
+         iterations, which may be small while the solve is still far from the \
+         minimum; measured every iteration",
+        "Parar quando a lacuna primal-dual (a energia menos o objetivo do \
+         problema dual, que limita por baixo a menor energia) relativa à \
+         energia ficar abaixo disto, em vez de pela variação entre iterações, \
+         que pode ser pequena enquanto a resolução ainda está longe do \
+         mínimo; medida a cada iteração",
+    ),
+    (
+        "Starting range for lambda values",
+        "Início da faixa de valores de lambda",
+    ),
+    ("End range for lambda values", "Fim da faixa de valores de lambda"),
+    (
+        "Number of steps, i.e. lambda values to use; Cannot be zero. `-t=1` \
+         will produce a single output using the --start-lambda value",
+        "Número de passos, isto é, de valores de lambda a usar; não pode ser \
+         zero. `-t=1` produz uma única saída usando o valor de --start-lambda",
+    ),
+    (
+        "Run with values the solver has no meaning for (e.g. a lambda of 0, \
+         or a convergence threshold that can never be met), which are refused \
+         otherwise, to study edge cases on purpose",
+        "Executar com valores que não fazem sentido para o solver (por \
+         exemplo um lambda de 0, ou um limiar de convergência que nunca pode \
+         ser atingido), recusados de outro modo, para estudar casos extremos \
+         de propósito",
+    ),
+    (
+        "Leave blown highlights and crushed blacks (samples at either end of \
+         the 8-bit range) out of the data term, so that they do not pull \
+         their neighbours toward them into halos, and keep them as they were",
+        "Deixar altas luzes estouradas e pretos esmagados (amostras em \
+         qualquer extremo da faixa de 8 bits) fora do termo de dados, para \
+         que não puxem seus vizinhos em halos, e mantê-los como estavam",
+    ),
+    (
+        "Weight of the data term of the samples of --protect-clipped, from 0 \
+         (left out) to 1 (as any other)",
+        "Peso do termo de dados das amostras de --protect-clipped, de 0 \
+         (deixadas de fora) a 1 (como qualquer outra)",
+    ),
+    (
+        "Deband smooth gradients (e.g. skies of heavily compressed JPEGs) \
+         whose steps are this many levels apart (1 by default): each sample \
+         is kept within half a step of the input, so that the smoothing fills \
+         in the steps without moving past them, and integer outputs are \
+         dithered so that they do not band again; best with small lambda \
+         values",
+        "Remover as faixas de gradientes suaves (por exemplo céus de JPEGs \
+         muito comprimidos) cujos degraus estão a essa quantidade de níveis \
+         de distância (1 por padrão): cada amostra é mantida a menos de meio \
+         degrau da entrada, para que a suavização preencha os degraus sem \
+         passar deles, e saídas inteiras recebem dithering para que não \
+         formem faixas de novo; melhor com valores pequenos de lambda",
+    ),
+    (
+        "Solve the input with the exact λ value and settings of a previous \
+         output saved with --save-params, instead of a sweep; only the \
+         settings of the solver and the processing are taken from it, and \
+         only those not given on the command line",
+        "Resolver a entrada com o valor exato de λ e as configurações de uma \
+         saída anterior salva com --save-params, em vez de uma varredura; só \
+         as configurações do solver e do processamento são tiradas dela, e só \
+         as que não forem dadas na linha de comando",
+    ),
+    (
+        "Save the λ value and settings of each output next to it, as a \
+         `.json` sidecar, to be read back by `params-of` or `--like`",
+        "Salvar o valor de λ e as configurações de cada saída ao lado dela, \
+         como um arquivo `.json` auxiliar, para ser lido de volta por \
+         `params-of` ou `--like`",
+    ),
+    (
+        "Embed the SHA-256 of the input file in each PNG output, as a `Source \
+         SHA-256` text chunk (it is also recorded in the sidecars of \
+         --save-params and in the --journal)",
+        "Incorporar o SHA-256 do arquivo de entrada em cada saída PNG, como \
+         um bloco de texto `Source SHA-256` (ele também é registrado nos \
+         arquivos auxiliares de --save-params e no --journal)",
+    ),
+    (
+        "Creator stamped into each PNG output, in its XMP metadata",
+        "Criador registrado em cada saída PNG, em seus metadados XMP",
+    ),
+    (
+        "Copyright notice stamped into each PNG output, in its XMP metadata",
+        "Aviso de direitos autorais registrado em cada saída PNG, em seus \
+         metadados XMP",
+    ),
+    (
+        "Stamp a note of how each PNG output was processed (the program, its \
+         version and the λ value and iterations solved with) into its XMP \
+         metadata",
+        "Registrar uma nota de como cada saída PNG foi processada (o \
+         programa, sua versão e o valor de λ e as iterações com que foi \
+         resolvida) em seus metadados XMP",
+    ),
+    (
+        "Refuse to run if any output (image, sidecar, report, plot, journal, \
+         session or manifest) would overwrite one of the input files",
+        "Recusar-se a executar se alguma saída (imagem, arquivo auxiliar, \
+         relatório, gráfico, diário, sessão ou manifesto) sobrescrever um dos \
+         arquivos de entrada",
+    ),
+    (
+        "Path of a newline-delimited JSON journal to which to append a record \
+         of each output (settings, input hash, timing and metrics), kept \
+         across runs",
+        "Caminho de um diário JSON delimitado por linhas ao qual acrescentar \
+         um registro de cada saída (configurações, hash da entrada, tempo e \
+         métricas), mantido entre execuções",
+    ),
+    (
+        "Maximum parallelism to use If larger than the available parallelism \
+         it won't have any effect",
+        "Paralelismo máximo a usar. Se for maior que o paralelismo \
+         disponível, não terá efeito",
+    ),
+    (
+        "Solve images with fewer pixels than this sequentially, without \
+         spawning any threads; measured on this machine by default",
+        "Resolver imagens com menos pixels que isto sequencialmente, sem \
+         criar threads; medido nesta máquina por padrão",
+    ),
+    (
+        "How many lambda values to solve at the same time; by default as many \
+         as there are threads to spare",
+        "Quantos valores de lambda resolver ao mesmo tempo; por padrão tantos \
+         quantas threads houver de sobra",
+    ),
+    (
+        "How many threads each lambda value is solved with (splitting its \
+         color channels); by default the threads left over by the jobs",
+        "Com quantas threads cada valor de lambda é resolvido (dividindo seus \
+         canais de cor); por padrão as threads que sobram dos jobs",
+    ),
+    (
+        "Also save a false-color heat map of the per-pixel change between the \
+         input and each output, with a scale bar",
+        "Salvar também um mapa de calor em falsa cor da mudança por pixel \
+         entre a entrada e cada saída, com uma barra de escala",
+    ),
+    (
+        "Also save the dual variables of the final iterate of each lambda \
+         value, as `_dual.npy` arrays of f64 indexed by x, y, channel and the \
+         axis of the gradient (0 for x, 1 for y), for research use",
+        "Salvar também as variáveis duais do iterado final de cada valor de \
+         lambda, como arrays `_dual.npy` de f64 indexados por x, y, canal e o \
+         eixo do gradiente (0 para x, 1 para y), para uso em pesquisa",
+    ),
+    (
+        "Also save this many patches of each output that changed the most \
+         from the input, each next to the same patch of the input, as \
+         `_gallery_<n>` images from the most changed",
+        "Salvar também essa quantidade de trechos de cada saída que mais \
+         mudaram em relação à entrada, cada um ao lado do mesmo trecho da \
+         entrada, como imagens `_gallery_<n>` a partir do mais mudado",
+    ),
+    (
+        "Side in pixels of the patches of --gallery",
+        "Lado em pixels dos trechos de --gallery",
+    ),
+    (
+        "Also save this many blends between the outputs of each pair of \
+         adjacent lambda values, for previews (e.g. smooth animations of the \
+         sweep) without solving more, as `_approximation` images; denoising \
+         is not linear in lambda, so they only approximate what solving would \
+         give",
+        "Salvar também essa quantidade de misturas entre as saídas de cada \
+         par de valores de lambda adjacentes, para prévias (por exemplo \
+         animações suaves da varredura) sem resolver mais, como imagens \
+         `_approximation`; a remoção de ruído não é linear em lambda, então \
+         elas só aproximam o que a resolução daria",
+    ),
+    (
+        "Staircasing score (fraction of smooth ramp patches turned into flat \
+         plateaus) above which a lambda value is flagged",
+        "Pontuação de degraus (fração de trechos de rampa suave transformados \
+         em platôs planos) acima da qual um valor de lambda é sinalizado",
+    ),
+    (
+        "Path of a JSON file in which to save the sweep summary",
+        "Caminho de um arquivo JSON em que salvar o resumo da varredura",
+    ),
+    (
+        "Path of a CSV file in which to save the sweep summary, with one row \
+         per lambda value",
+        "Caminho de um arquivo CSV em que salvar o resumo da varredura, com \
+         uma linha por valor de lambda",
+    ),
+    (
+        "Path of a clean version of the input (e.g. from before noise was \
+         added to it) against which to measure the PSNR and SSIM of each \
+         output; given several times or separated by commas (e.g. clean \
+         frames of a static scene), against their mean and each of them",
+        "Caminho de uma versão limpa da entrada (por exemplo de antes de o \
+         ruído ser adicionado a ela) em relação à qual medir o PSNR e o SSIM \
+         de cada saída; dado várias vezes ou separado por vírgulas (por \
+         exemplo quadros limpos de uma cena estática), em relação à média \
+         deles e a cada um",
+    ),
+    (
+        "Also denoise the input with an external method (e.g. a script \
+         running a learned model), given as `NAME=COMMAND`, and report its \
+         result next to those of the sweep; the command is run by the shell \
+         with `{input}` and `{output}` replaced by the path of the image to \
+         denoise and the path to save its result at (as PNG, or EXR for \
+         floating point inputs), and may be given several times; only taken \
+         from the command line, never from saved parameters",
+        "Remover também o ruído da entrada com um método externo (por exemplo \
+         um script que executa um modelo aprendido), dado como \
+         `NOME=COMANDO`, e relatar seu resultado ao lado dos da varredura; o \
+         comando é executado pelo shell com `{input}` e `{output}` \
+         substituídos pelo caminho da imagem a tratar e pelo caminho em que \
+         salvar seu resultado (como PNG, ou EXR para entradas de ponto \
+         flutuante), e pode ser dado várias vezes; só é tirado da linha de \
+         comando, nunca de parâmetros salvos",
+    ),
+    (
+        "Path of an SVG file in which to plot the PSNR, SSIM and time taken \
+         against the lambda values of the sweep",
+        "Caminho de um arquivo SVG em que traçar o PSNR, o SSIM e o tempo \
+         gasto em função dos valores de lambda da varredura",
+    ),
+    (
+        "Path of an SVG file in which to plot the L-curve of the sweep \
+         (residual norm against total variation), with its corner",
+        "Caminho de um arquivo SVG em que traçar a curva L da varredura \
+         (norma do resíduo em função da variação total), com seu canto",
+    ),
+    (
+        "Instead of a sweep, search between the start and end lambda values \
+         for the one whose output differs from the input by as much as the \
+         noise, with at most --steps solves",
+        "Em vez de uma varredura, procurar entre os valores de lambda de \
+         início e de fim aquele cuja saída difere da entrada tanto quanto o \
+         ruído, com no máximo --steps resoluções",
+    ),
+    (
+        "Standard deviation of the noise per sample (in 8-bit levels) for \
+         --discrepancy, if known; estimated from the input otherwise",
+        "Desvio padrão do ruído por amostra (em níveis de 8 bits) para \
+         --discrepancy, se conhecido; estimado a partir da entrada de outro \
+         modo",
+    ),
+    (
+        "Region `x,y,w,h` of the input known to be flat (e.g. a gray card or \
+         a defocused sky) in which to measure the noise, instead of \
+         estimating it over the whole image",
+        "Região `x,y,w,h` da entrada sabidamente plana (por exemplo um cartão \
+         cinza ou um céu desfocado) em que medir o ruído, em vez de estimá-lo \
+         sobre a imagem inteira",
+    ),
+    (
+        "Path of a dark frame (taken with the same exposure and no light) to \
+         subtract from the input before denoising",
+        "Caminho de um quadro escuro (tirado com a mesma exposição e sem luz) \
+         a subtrair da entrada antes de remover o ruído",
+    ),
+    (
+        "Path of a flat field (an evenly lit exposure) with which to correct \
+         the input's uneven sensitivity before denoising",
+        "Caminho de um campo plano (uma exposição iluminada por igual) com \
+         que corrigir a sensibilidade desigual da entrada antes de remover o \
+         ruído",
+    ),
+    (
+        "Comma separated x coordinates of defective sensor columns to \
+         reconstruct from their neighbours before denoising",
+        "Coordenadas x, separadas por vírgulas, de colunas defeituosas do \
+         sensor a reconstruir a partir das vizinhas antes de remover o ruído",
+    ),
+    (
+        "Comma separated y coordinates of defective sensor rows to \
+         reconstruct from their neighbours before denoising",
+        "Coordenadas y, separadas por vírgulas, de linhas defeituosas do \
+         sensor a reconstruir a partir das vizinhas antes de remover o ruído",
+    ),
+    (
+        "Detect defective sensor rows and columns (whose mean stands out from \
+         their neighbours') and reconstruct them before denoising",
+        "Detectar linhas e colunas defeituosas do sensor (cuja média se \
+         destaca da das vizinhas) e reconstruí-las antes de remover o ruído",
+    ),
+    (
+        "How many robust standard deviations a line must stand out from its \
+         neighbours to be detected as defective",
+        "Quantos desvios padrão robustos uma linha deve se destacar das \
+         vizinhas para ser detectada como defeituosa",
+    ),
+    (
+        "For JPEG inputs, smooth the steps across the boundaries of their 8x8 \
+         blocks before denoising, so that they are not kept as if they were \
+         edges of the scene; steps over this many levels (8 by default) are \
+         taken as edges that happen to fall on a boundary",
+        "Para entradas JPEG, suavizar os degraus nas bordas de seus blocos \
+         8x8 antes de remover o ruído, para que não sejam mantidos como se \
+         fossem contornos da cena; degraus acima dessa quantidade de níveis \
+         (8 por padrão) são tomados como contornos que por acaso caem em uma \
+         borda",
+    ),
+    (
+        "For floating point (e.g. EXR) inputs, whose results are saved as \
+         EXR, also save a tone-mapped PNG preview of each result",
+        "Para entradas de ponto flutuante (por exemplo EXR), cujos resultados \
+         são salvos como EXR, salvar também uma prévia PNG com mapeamento de \
+         tons de cada resultado",
+    ),
+    (
+        "Reinhard, E. et al. (2002), applied on luminance to keep hues",
+        "Reinhard, E. et al. (2002), aplicado sobre a luminância para manter \
+         as matizes",
+    ),
+    (
+        "Filmic curve, as Narkowicz, K. (2015)'s fit of the ACES curve",
+        "Curva fílmica, como o ajuste de Narkowicz, K. (2015) da curva ACES",
+    ),
+    (
+        "What to do with values of the results outside of the 8-bit range, as \
+         the solver may leave near bright edges: the samples out of range are \
+         logged with -vv, and saved in the --report (floating point results \
+         are saved as they are)",
+        "O que fazer com valores dos resultados fora da faixa de 8 bits, como \
+         o solver pode deixar perto de contornos claros: as amostras fora da \
+         faixa são registradas com -vv, e salvas no --report (resultados de \
+         ponto flutuante são salvos como estão)",
+    ),
+    (
+        "Clip them to the nearest end of the range",
+        "Cortá-los no extremo mais próximo da faixa",
+    ),
+    (
+        "Rescale the whole output linearly so that they fit, if any don't",
+        "Reescalar linearmente toda a saída para que caibam, se algum não \
+         couber",
+    ),
+    ("Fail the λ value", "Falhar o valor de λ"),
+    (
+        "Downscale the input before denoising so that neither side is larger \
+         than this many pixels",
+        "Reduzir a entrada antes de remover o ruído para que nenhum lado seja \
+         maior que essa quantidade de pixels",
+    ),
+    (
+        "Resize each result before saving it, as a percentage (`50%`) or a \
+         factor (`0.5`)",
+        "Redimensionar cada resultado antes de salvá-lo, como porcentagem \
+         (`50%`) ou fator (`0.5`)",
+    ),
+    (
+        "Filter used for --max-dimension and --output-scale",
+        "Filtro usado por --max-dimension e --output-scale",
+    ),
+    (
+        "Lanczos with a window of 3, the sharpest",
+        "Lanczos com uma janela de 3, o mais nítido",
+    ),
+    (
+        "Bicubic (Catmull-Rom), with less ringing around edges",
+        "Bicúbico (Catmull-Rom), com menos anéis em volta dos contornos",
+    ),
+    (
+        "Boundary condition for the gradient operator at the edges of the \
+         image; other than periodic, it is applied by padding the image \
+         before denoising and cropping the result afterwards",
+        "Condição de contorno do operador gradiente nas bordas da imagem; \
+         exceto a periódica, é aplicada preenchendo a imagem antes de remover \
+         o ruído e recortando o resultado depois",
+    ),
+    (
+        "Wrap around to the opposite edge, as the solver does natively",
+        "Dar a volta até a borda oposta, como o solver faz nativamente",
+    ),
+    (
+        "Repeat the pixels on the edges outwards",
+        "Repetir os pixels das bordas para fora",
+    ),
+    ("Mirror the image at its edges", "Espelhar a imagem em suas bordas"),
+    ("Surround the image with black", "Cercar a imagem de preto"),
+    (
+        "Pixels of padding added on each side for --boundary",
+        "Pixels de preenchimento adicionados em cada lado para --boundary",
+    ),
+    (
+        "Region `x,y,w,h` of the input to denoise, leaving the rest out; the \
+         output holds only this region unless --composite is given",
+        "Região `x,y,w,h` da entrada da qual remover o ruído, deixando o \
+         resto de fora; a saída contém só essa região a menos que --composite \
+         seja dado",
+    ),
+    (
+        "Composite the denoised --patch back into the untouched original, \
+         blending it in smoothly over --patch-margin pixels",
+        "Compor o --patch tratado de volta no original intocado, misturando-o \
+         suavemente ao longo de --patch-margin pixels",
+    ),
+    (
+        "Pixels around the --patch also given to the solver as context, and \
+         over which the result is blended in for --composite",
+        "Pixels em volta do --patch também dados ao solver como contexto, e \
+         sobre os quais o resultado é misturado para --composite",
+    ),
+    (
+        "First solve the sweep on a downscaled draft of the input, then \
+         prompt for the λ value to solve at full resolution (or to skip)",
+        "Resolver primeiro a varredura em um rascunho reduzido da entrada, \
+         depois pedir o valor de λ a resolver em resolução total (ou para \
+         pular)",
+    ),
+    (
+        "Largest side in pixels of the drafts for --interactive",
+        "Maior lado em pixels dos rascunhos de --interactive",
+    ),
+    (
+        "Path of a session file in which to record the choice made for the \
+         input with --interactive, to replay with --apply-session",
+        "Caminho de um arquivo de sessão em que registrar a escolha feita \
+         para a entrada com --interactive, para repetir com --apply-session",
+    ),
+    (
+        "Only record the choice in the --session, leaving the full resolution \
+         solve to --apply-session",
+        "Só registrar a escolha na --session, deixando a resolução em \
+         resolução total para --apply-session",
+    ),
+    (
+        "Instead of denoising, add the planned outputs with their settings to \
+         a manifest, to review and execute with `apply`",
+        "Em vez de remover o ruído, adicionar as saídas planejadas com suas \
+         configurações a um manifesto, para revisar e executar com `apply`",
+    ),
+    (
+        "Replay the choices recorded in a session file by --session, solving \
+         each chosen input at full resolution",
+        "Repetir as escolhas registradas em um arquivo de sessão por \
+         --session, resolvendo cada entrada escolhida em resolução total",
+    ),
+    (
+        "Seconds after which to stop all work in progress, as on Ctrl-C",
+        "Segundos após os quais parar todo o trabalho em andamento, como com \
+         Ctrl-C",
+    ),
+    (
+        "Path of a file to write the log to, except for warnings and errors \
+         which always go to stderr; stdout by default",
+        "Caminho de um arquivo em que escrever o log, exceto avisos e erros, \
+         que sempre vão para o stderr; stdout por padrão",
+    ),
+    (
+        "Limit the rate at which files are read and written together (e.g. \
+         `50MB/s`), so as not to saturate storage shared with others",
+        "Limitar a taxa com que arquivos são lidos e escritos juntos (por \
+         exemplo `50MB/s`), para não saturar armazenamento compartilhado com \
+         outros",
+    ),
+    (
+        "Refuse to read or write any file outside these folders (separated by \
+         commas), the log file included",
+        "Recusar-se a ler ou escrever qualquer arquivo fora destas pastas \
+         (separadas por vírgulas), incluindo o arquivo de log",
+    ),
+    (
+        "Path of a tamper-evident log to which to append a record (with the \
+         SHA-256 of the contents, the user and the time) of every file read \
+         and written, kept across runs and checked by `check-audit`",
+        "Caminho de um log à prova de adulteração ao qual acrescentar um \
+         registro (com o SHA-256 do conteúdo, o usuário e a hora) de cada \
+         arquivo lido e escrito, mantido entre execuções e verificado por \
+         `check-audit`",
+    ),
+    (
+        "Refuse to decode images with more than this many megapixels",
+        "Recusar-se a decodificar imagens com mais que essa quantidade de \
+         megapixels",
+    ),
+    (
+        "Refuse to decode images taking more than this much memory to decode \
+         (e.g. `2G`)",
+        "Recusar-se a decodificar imagens que precisem de mais que essa \
+         quantidade de memória para decodificar (por exemplo `2G`)",
+    ),
+    (
+        "Window of modality values (after the rescale slope and intercept) \
+         mapped onto the levels of DICOM inputs: `stored` for every value \
+         their samples can hold, `file` for the one saved in the file, or \
+         `center,width`",
+        "Janela de valores de modalidade (após a inclinação e o intercepto de \
+         reescala) mapeada nos níveis das entradas DICOM: `stored` para todo \
+         valor que suas amostras podem conter, `file` para a salva no \
+         arquivo, ou `center,width`",
+    ),
+    (
+        "Format to save the outputs of DICOM inputs in",
+        "Formato em que salvar as saídas das entradas DICOM",
+    ),
+    (
+        "Language of the help, errors and summaries, by default that of the \
+         locale (`LC_ALL`, `LC_MESSAGES` or `LANG`)",
+        "Idioma da ajuda, dos erros e dos resumos, por padrão o da localidade \
+         (`LC_ALL`, `LC_MESSAGES` ou `LANG`)",
+    ),
+    ("English", "Inglês"),
+    ("Portuguese", "Português"),
+    ("Verbosity (from -v to -vvvv)", "Verbosidade (de -v a -vvvv)"),
+    (
+        "Print help (see more with '--help')",
+        "Mostrar a ajuda (veja mais com '--help')",
+    ),
+    (
+        "Print help (see a summary with '-h')",
+        "Mostrar a ajuda (veja um resumo com '-h')",
+    ),
+    ("Print version", "Mostrar a versão"),
+    (
+        "Print per-channel statistics, histograms and estimated noise of an \
+         image",
+        "Mostrar estatísticas por canal, histogramas e o ruído estimado de \
+         uma imagem",
+    ),
+    ("Path of image to analyse", "Caminho da imagem a analisar"),
+    ("Number of histogram bins", "Número de classes do histograma"),
+    ("Print the statistics as JSON", "Mostrar as estatísticas como JSON"),
+    (
+        "Region `x,y,w,h` of the image known to be flat in which to measure \
+         the noise, instead of estimating it over the whole image",
+        "Região `x,y,w,h` da imagem sabidamente plana em que medir o ruído, \
+         em vez de estimá-lo sobre a imagem inteira",
+    ),
+    (
+        "Execute a manifest written by --write-manifest (or a session file), \
+         with the paths and settings recorded in it for each input",
+        "Executar um manifesto escrito por --write-manifest (ou um arquivo de \
+         sessão), com os caminhos e as configurações registrados nele para \
+         cada entrada",
+    ),
+    ("Path of manifest to execute", "Caminho do manifesto a executar"),
+    (
+        "Order in which to solve the entries",
+        "Ordem em que resolver as entradas",
+    ),
+    ("As they were recorded", "Como foram registradas"),
+    (
+        "Longest first, by the megapixels solved times the iterations they \
+         may take",
+        "A mais longa primeiro, pelos megapixels resolvidos vezes as \
+         iterações que podem levar",
+    ),
+    ("By the path of the input", "Pelo caminho da entrada"),
+    (
+        "Path of a file in which to record the progress through the entries, \
+         to resume from if it already exists (e.g. after an interruption), \
+         skipping the entries done",
+        "Caminho de um arquivo em que registrar o progresso pelas entradas, \
+         para retomar a partir dele se já existir (por exemplo após uma \
+         interrupção), pulando as entradas feitas",
+    ),
+    (
+        "Times to attempt each entry of the --queue, across runs, before \
+         giving up on it",
+        "Vezes que tentar cada entrada da --queue, entre execuções, antes de \
+         desistir dela",
+    ),
+    (
+        "Denoise a synthetic noisy image and check the result against known \
+         bounds, as a smoke test of the installation",
+        "Remover o ruído de uma imagem ruidosa sintética e comparar o \
+         resultado com limites conhecidos, como teste rápido da instalação",
+    ),
+    (
+        "Check properties the solver must have (outputs within the range of \
+         the input, smoothing increasing as λ decreases, denoising an output \
+         again changing it no more than the first time) on random synthetic \
+         images",
+        "Verificar propriedades que o solver deve ter (saídas dentro da faixa \
+         da entrada, suavização aumentando conforme λ diminui, remover o \
+         ruído de uma saída de novo mudando-a não mais que da primeira vez) \
+         em imagens sintéticas aleatórias",
+    ),
+    (
+        "Number of random images to check",
+        "Número de imagens aleatórias a verificar",
+    ),
+    (
+        "Seed of the random images, printed by every run so that it can be \
+         repeated [default: from the clock]",
+        "Semente das imagens aleatórias, mostrada por cada execução para que \
+         possa ser repetida [padrão: do relógio]",
+    ),
+    (
+        "Check that the chain of hashes of an audit log written by \
+         --audit-log is intact, i.e. that no entry was modified or removed",
+        "Verificar que a cadeia de hashes de um log de auditoria escrito por \
+         --audit-log está intacta, isto é, que nenhuma entrada foi modificada \
+         ou removida",
+    ),
+    ("Path of the audit log", "Caminho do log de auditoria"),
+    (
+        "Print the λ value and settings a result was solved with, saved next \
+         to it by --save-params",
+        "Mostrar o valor de λ e as configurações com que um resultado foi \
+         resolvido, salvos ao lado dele por --save-params",
+    ),
+    (
+        "Path of a result saved with --save-params",
+        "Caminho de um resultado salvo com --save-params",
+    ),
+    (
+        "Print the input and output formats, sample formats and features \
+         supported by this build",
+        "Mostrar os formatos de entrada e de saída, os formatos de amostra e \
+         os recursos suportados por esta compilação",
+    ),
+    ("Print the capabilities as JSON", "Mostrar as capacidades como JSON"),
+    (
+        "Time the operations the solver is made of (gradient, divergence, \
+         dual and primal steps, whole iterations) and the conversions around \
+         it on images of several sizes, printing the timings as JSON",
+        "Cronometrar as operações de que o solver é feito (gradiente, \
+         divergência, passos dual e primal, iterações inteiras) e as \
+         conversões em volta dele em imagens de vários tamanhos, mostrando os \
+         tempos como JSON",
+    ),
+    (
+        "Sides in pixels of the square images to time on, separated by commas",
+        "Lados em pixels das imagens quadradas em que cronometrar, separados \
+         por vírgulas",
+    ),
+    (
+        "Times to run each operation, of which the median and the minimum are \
+         reported",
+        "Vezes que executar cada operação, das quais a mediana e o mínimo são \
+         relatados",
+    ),
+    (
+        "Convert an image to another format (that of the extension of the \
+         output) or sample depth, with the same decoding and encoding as \
+         denoising",
+        "Converter uma imagem para outro formato (o da extensão da saída) ou \
+         profundidade de amostra, com a mesma decodificação e codificação da \
+         remoção de ruído",
+    ),
+    ("Path of the image to convert", "Caminho da imagem a converter"),
+    (
+        "Path to save the converted image to, in the format of its extension",
+        "Caminho em que salvar a imagem convertida, no formato de sua extensão",
+    ),
+    (
+        "Bits per sample of the output (32 for floating point), as supported \
+         by its format; those of the input by default",
+        "Bits por amostra da saída (32 para ponto flutuante), conforme \
+         suportado por seu formato; os da entrada por padrão",
+    ),
+    ("Floating point", "Ponto flutuante"),
+    (
+        "Make aligned (noisy, denoised, clean) triples from clean images, \
+         with noise of known standard deviations, and an index of them, as \
+         datasets for learned denoisers",
+        "Fazer triplas alinhadas (ruidosa, tratada, limpa) a partir de \
+         imagens limpas, com ruído de desvios padrão conhecidos, e um índice \
+         delas, como conjuntos de dados para removedores de ruído aprendidos",
+    ),
+    ("Paths of the clean images", "Caminhos das imagens limpas"),
+    (
+        "Path of folder in which the triples and their index should be saved",
+        "Caminho da pasta em que as triplas e seu índice devem ser salvos",
+    ),
+    (
+        "Comma separated standard deviations (in 8-bit levels) of the \
+         gaussian noise to add, each making triples of its own",
+        "Desvios padrão (em níveis de 8 bits), separados por vírgulas, do \
+         ruído gaussiano a adicionar, cada um fazendo triplas próprias",
+    ),
+    (
+        "Number of noisy copies of each clean image for each standard \
+         deviation, each with noise of its own",
+        "Número de cópias ruidosas de cada imagem limpa para cada desvio \
+         padrão, cada uma com ruído próprio",
+    ),
+    (
+        "Lambda value to denoise the noisy copies with",
+        "Valor de lambda com que remover o ruído das cópias ruidosas",
+    ),
+    (
+        "Seed of the noise of the first triple, each of the others taking the \
+         next one, so that a dataset can be made again identically",
+        "Semente do ruído da primeira tripla, cada uma das outras tomando a \
+         seguinte, para que um conjunto de dados possa ser feito de novo de \
+         forma idêntica",
+    ),
+    (
+        "Print this message or the help of the given subcommand(s)",
+        "Mostrar esta mensagem ou a ajuda do(s) subcomando(s) dado(s)",
+    ),
+];
@@ -0,0 +1,139 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Logging of the program, buffered so that heavy (e.g. per-iteration)
+//! logging doesn't slow the solver threads down. Warnings and errors go to
+//! stderr as they happen, everything else to a buffered sink (stdout or a
+//! file). Lines logged by workers are prefixed with what they work on, so
+//! that the interleaved lines of concurrent workers can be told apart.
+
+use std::{
+    cell::RefCell,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    sync::Mutex,
+};
+
+thread_local! {
+    /// What the current thread is working on, if set.
+    static CONTEXT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with the lines it logs on the current thread prefixed with
+/// `context` (e.g. the λ value being solved).
+pub fn with_context<T>(context: String, f: impl FnOnce() -> T) -> T {
+    /// Restores the previous context, also if `f` panics.
+    struct Restore(Option<String>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CONTEXT.with(|current| *current.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore =
+        Restore(CONTEXT.with(|current| current.replace(Some(context))));
+    f()
+}
+
+/// The prefix of the lines logged on the current thread.
+fn prefix() -> String {
+    CONTEXT.with(|context| match &*context.borrow() {
+        Some(context) => format!("[{}] ", context),
+        None => String::new(),
+    })
+}
+
+pub struct Logger {
+    sink: Mutex<BufWriter<Box<dyn Write + Send>>>,
+}
+
+impl Logger {
+    /// Installs the logger, writing below warnings to `sink`, and flushing
+    /// it on panics too so that the lines leading up to them are kept.
+    pub fn init(
+        log_level: log::LevelFilter,
+        sink: Box<dyn Write + Send>,
+    ) -> Result<(), log::SetLoggerError> {
+        let logger = Box::leak(Box::new(Logger {
+            sink: Mutex::new(BufWriter::new(sink)),
+        }));
+        log::set_logger(logger)?;
+        log::set_max_level(log_level);
+
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            log::logger().flush();
+            default_hook(info);
+        }));
+        Ok(())
+    }
+
+    fn sink(
+        &self,
+    ) -> std::sync::MutexGuard<'_, BufWriter<Box<dyn Write + Send>>> {
+        // a panic while holding the lock doesn't leave the buffer invalid
+        self.sink
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        match log::max_level().to_level() {
+            Some(level) => metadata.level() <= level,
+            None => false,
+        }
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            level @ (log::Level::Error | log::Level::Warn) => {
+                // what was logged before them comes first
+                let mut sink = self.sink();
+                sink.flush().ok();
+                writeln!(
+                    io::stderr(),
+                    "{}: {}{}",
+                    level,
+                    prefix(),
+                    record.args()
+                )
+                .ok();
+            },
+            level => {
+                writeln!(
+                    self.sink(),
+                    "{}: {}{}",
+                    level,
+                    prefix(),
+                    record.args()
+                )
+                .ok();
+            },
+        }
+    }
+
+    fn flush(&self) {
+        self.sink().flush().ok();
+    }
+}
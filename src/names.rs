@@ -0,0 +1,260 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Making the names of outputs valid on the filesystems they are saved to:
+//! those derived from the input and the λ value may hold characters that
+//! some filesystems reject or that are awkward to type (e.g. `=`), and
+//! full paths may exceed the length limit of Windows.
+
+use std::path::PathBuf;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    /// Names as they are derived, e.g. `img_lambda_=_0.010.png`
+    Keep,
+    /// Replace what Windows rejects (`<>:"/\|?*` and control characters,
+    /// trailing dots and spaces, device names such as `CON` or `COM1`)
+    Windows,
+    /// Only keep the POSIX portable characters (ASCII letters, digits,
+    /// `.`, `_` and `-`), e.g. `img_lambda_0.010.png`
+    Portable,
+}
+
+/// Names of devices on Windows, reserved with any extension.
+const RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6",
+    "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6",
+    "LPT7", "LPT8", "LPT9",
+];
+
+/// `file_name` made valid under `policy`, with any replaced characters as
+/// `_` (a run of them as a single one, with `portable`).
+pub fn sanitize(file_name: &str, policy: Policy) -> String {
+    let name: String = match policy {
+        Policy::Keep => return file_name.to_string(),
+        Policy::Windows => file_name
+            .chars()
+            .map(|c| match c {
+                '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect(),
+        Policy::Portable => {
+            let mut name = String::with_capacity(file_name.len());
+            for c in file_name.chars() {
+                let c = match c {
+                    'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+                    _ => '_',
+                };
+                if !(c == '_' && name.ends_with('_')) {
+                    name.push(c);
+                }
+            }
+            name
+        },
+    };
+
+    // both policies are meant to be valid on Windows
+    let name = name.trim_end_matches(['.', ' ']);
+    let stem = name.split('.').next().unwrap_or_default();
+    if name.is_empty() {
+        "_".to_string()
+    } else if RESERVED
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        format!("_{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Longest path most Windows APIs accept without the `\\?\` prefix.
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+/// `path` made absolute with the `\\?\` prefix if it is too long for
+/// Windows otherwise; unchanged elsewhere.
+#[cfg(windows)]
+pub fn long_path(path: PathBuf) -> PathBuf {
+    use std::ffi::OsString;
+
+    if path.as_os_str().len() < MAX_PATH {
+        return path;
+    }
+    let absolute = match std::path::absolute(&path) {
+        Ok(absolute) => absolute,
+        Err(_) => return path,
+    };
+    let absolute = absolute.as_os_str().to_string_lossy();
+    let prefixed = if absolute.starts_with(r"\\?\") {
+        return path;
+    } else if let Some(share) = absolute.strip_prefix(r"\\") {
+        format!(r"\\?\UNC\{}", share)
+    } else {
+        format!(r"\\?\{}", absolute)
+    };
+    PathBuf::from(OsString::from(prefixed))
+}
+
+/// `path` made absolute with the `\\?\` prefix if it is too long for
+/// Windows otherwise; unchanged elsewhere.
+#[cfg(not(windows))]
+pub fn long_path(path: PathBuf) -> PathBuf {
+    path
+}
+
+/// `name` with the accents of Latin letters removed and the other
+/// non-ASCII characters transliterated where there is a usual ASCII
+/// spelling (e.g. `ß` as `ss`), or replaced by `_` otherwise.
+pub fn ascii(name: &str) -> String {
+    let mut ascii = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii() {
+            ascii.push(c);
+            continue;
+        }
+        let replacement = match c {
+            'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+            'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+            'Æ' => "AE",
+            'æ' => "ae",
+            'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+            'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+            'Ð' | 'Ď' | 'Đ' => "D",
+            'ð' | 'ď' | 'đ' => "d",
+            'È'..='Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+            'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+            'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+            'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+            'Ĥ' | 'Ħ' => "H",
+            'ĥ' | 'ħ' => "h",
+            'Ì'..='Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+            'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+            'Ĵ' => "J",
+            'ĵ' => "j",
+            'Ķ' => "K",
+            'ķ' => "k",
+            'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+            'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+            'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+            'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+            'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+            'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+            'Œ' => "OE",
+            'œ' => "oe",
+            'Ŕ' | 'Ŗ' | 'Ř' => "R",
+            'ŕ' | 'ŗ' | 'ř' => "r",
+            'Ś' | 'Ŝ' | 'Ş' | 'Š' => "S",
+            'ś' | 'ŝ' | 'ş' | 'š' => "s",
+            'ß' => "ss",
+            'Ţ' | 'Ť' | 'Ŧ' => "T",
+            'ţ' | 'ť' | 'ŧ' => "t",
+            'Þ' => "TH",
+            'þ' => "th",
+            'Ù'..='Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+            'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+            'Ŵ' => "W",
+            'ŵ' => "w",
+            'Ý' | 'Ŷ' | 'Ÿ' => "Y",
+            'ý' | 'ÿ' | 'ŷ' => "y",
+            'Ź' | 'Ż' | 'Ž' => "Z",
+            'ź' | 'ż' | 'ž' => "z",
+            _ => "_",
+        };
+        ascii.push_str(replacement);
+    }
+    ascii
+}
+
+/// How the λ value is written in the names of the outputs.
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LambdaFormat {
+    /// As many decimals as it takes to tell the values of the sweep apart,
+    /// e.g. `0.010`
+    Decimal,
+    /// As many significant digits as it takes to tell the values of the
+    /// sweep apart, in scientific notation, e.g. `1.0e-2`
+    Scientific,
+    /// Always 10 decimals, e.g. `0.0100000000`
+    Fixed,
+}
+
+/// Digits of `f64` beyond which more do not tell values apart.
+const MAX_DIGITS: usize = 17;
+
+/// Writes the λ values of a sweep with the same number of digits, the
+/// fewest with which none of them are written the same (as far as digits
+/// can tell them apart, see `MAX_DIGITS`).
+#[derive(Debug, Clone, Copy)]
+pub struct LambdaFormatter {
+    format: LambdaFormat,
+    digits: usize,
+}
+
+impl LambdaFormatter {
+    pub fn new(format: LambdaFormat, lambdas: &[f64]) -> Self {
+        let digits = match format {
+            LambdaFormat::Fixed => 10,
+            // enough for the first significant digit of the smallest
+            LambdaFormat::Decimal => lambdas
+                .iter()
+                .cloned()
+                .filter(|&lambda| lambda > 0.0)
+                .reduce(f64::min)
+                .map_or(0, |smallest| {
+                    (-smallest.log10()).ceil().max(0.0) as usize
+                }),
+            LambdaFormat::Scientific => 0,
+        };
+        let mut formatter = Self { format, digits };
+        if format != LambdaFormat::Fixed {
+            while formatter.digits < MAX_DIGITS && !formatter.distinct(lambdas)
+            {
+                formatter.digits += 1;
+            }
+        }
+        formatter
+    }
+
+    pub fn format(&self, lambda: f64) -> String {
+        match self.format {
+            LambdaFormat::Decimal | LambdaFormat::Fixed => {
+                format!("{:.*}", self.digits, lambda)
+            },
+            LambdaFormat::Scientific => format!("{:.*e}", self.digits, lambda),
+        }
+    }
+
+    /// Whether no two of `lambdas` are written the same.
+    fn distinct(&self, lambdas: &[f64]) -> bool {
+        let mut written: Vec<String> =
+            lambdas.iter().map(|&lambda| self.format(lambda)).collect();
+        written.sort();
+        written.windows(2).all(|pair| pair[0] != pair[1])
+    }
+}
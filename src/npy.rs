@@ -0,0 +1,76 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Arrays saved in the NPY format of NumPy (version 1.0), as plain
+//! little-endian f64 in C order, for analysis outside of this program.
+
+use std::{
+    io,
+    path::Path,
+};
+
+use image_recovery::ndarray::{
+    ArrayBase,
+    Data,
+    Dimension,
+};
+
+use crate::storage;
+
+/// Start of every NPY file, followed by the format version.
+const MAGIC: &[u8; 8] = b"\x93NUMPY\x01\x00";
+/// What the magic, the length of the header and the header itself are
+/// padded to.
+const ALIGNMENT: usize = 64;
+
+/// Saves `array` to `path`, with the same shape (the first axis varying
+/// slowest).
+pub fn save<S, D>(path: &Path, array: &ArrayBase<S, D>) -> io::Result<()>
+where
+    S: Data<Elem = f64>,
+    D: Dimension,
+{
+    let shape = match array.shape() {
+        [length] => format!("({},)", length),
+        shape => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|length| length.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': {}, }}",
+        shape
+    );
+    // padded with spaces, and ended by a newline
+    let unpadded = MAGIC.len() + 2 + header.len() + 1;
+    let padding = (ALIGNMENT - unpadded % ALIGNMENT) % ALIGNMENT;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut bytes =
+        Vec::with_capacity(MAGIC.len() + 2 + header.len() + array.len() * 8);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    // iterated in logical order, which is C order whatever the layout
+    for value in array.iter() {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    storage::get().write(path, &bytes)
+}
@@ -0,0 +1,52 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Conversion of denoised arrays back into images.
+
+use image_recovery::{
+    image::{
+        ImageBuffer,
+        Rgb,
+        Rgb32FImage,
+    },
+    ndarray::Array3,
+};
+
+/// Converts an array with values on the 8-bit scale back into a floating
+/// point image on the scale it was loaded from (0 to 1 for the nominal
+/// range), without clamping, so that high dynamic range is kept.
+pub fn to_rgb32f(array: &Array3<f64>) -> Rgb32FImage {
+    let (width, height, channels) = array.dim();
+    Rgb32FImage::from_fn(width as u32, height as u32, |x, y| {
+        let value = |z: usize| {
+            (array[[x as usize, y as usize, z % channels]] / 255.0) as f32
+        };
+        Rgb([value(0), value(1), value(2)])
+    })
+}
+
+/// Converts an array with values on the 8-bit scale into a 16-bit image,
+/// rounding and clamping to its range.
+pub fn to_rgb16(array: &Array3<f64>) -> ImageBuffer<Rgb<u16>, Vec<u16>> {
+    let (width, height, channels) = array.dim();
+    ImageBuffer::from_fn(width as u32, height as u32, |x, y| {
+        let value = |z: usize| {
+            (array[[x as usize, y as usize, z % channels]] * 257.0)
+                .round()
+                .clamp(0.0, u16::MAX as f64) as u16
+        };
+        Rgb([value(0), value(1), value(2)])
+    })
+}
@@ -0,0 +1,50 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Parameters of each output, saved next to it as a JSON sidecar so that
+//! they can be read back (`params-of`) and reused for other inputs
+//! (`--like`).
+
+use std::{
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use crate::{
+    session::Entry,
+    storage,
+};
+
+/// Path of the sidecar of an output, e.g. `result.png.json`.
+pub fn sidecar_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+/// Saves the λ value and settings an output was solved with, as
+/// pretty-printed JSON in its sidecar.
+pub fn save(output: &Path, entry: &Entry) -> io::Result<()> {
+    storage::save_json(storage::get(), &sidecar_path(output), entry)
+}
+
+/// Loads the λ value and settings an output was solved with from its
+/// sidecar.
+pub fn load(output: &Path) -> io::Result<Entry> {
+    storage::load_json(storage::get(), &sidecar_path(output))
+}
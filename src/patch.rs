@@ -0,0 +1,104 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Denoising of a single region of an image, optionally composited back
+//! into the untouched original.
+
+use image_recovery::ndarray::{
+    s,
+    Array3,
+};
+
+use crate::region::Region;
+
+/// The region actually solved for a patch: the patch grown by `margin`
+/// pixels on each side, within an image of the given size. The margin gives
+/// the solver context around the patch, and room to blend the result into
+/// the original.
+pub fn context(patch: Region, margin: u32, width: u32, height: u32) -> Region {
+    let x = patch.x.saturating_sub(margin);
+    let y = patch.y.saturating_sub(margin);
+    Region {
+        x,
+        y,
+        width: (patch.x + patch.width + margin).min(width) - x,
+        height: (patch.y + patch.height + margin).min(height) - y,
+    }
+}
+
+/// Copies the given region out of `array`.
+pub fn crop(array: &Array3<f64>, region: Region) -> Array3<f64> {
+    array
+        .slice(s![region.columns(), region.rows(), ..])
+        .to_owned()
+}
+
+/// Copies `patch` out of the result of solving `context`.
+pub fn crop_from_context(
+    solved: &Array3<f64>,
+    patch: Region,
+    context: Region,
+) -> Array3<f64> {
+    crop(
+        solved,
+        Region {
+            x: patch.x - context.x,
+            y: patch.y - context.y,
+            ..patch
+        },
+    )
+}
+
+/// Blends the result of solving `context` back into `original`: the patch
+/// itself is fully replaced, and the weight of the result fades out
+/// smoothly over `margin` pixels around it, so that no seam is visible.
+pub fn composite(
+    original: &Array3<f64>,
+    solved: &Array3<f64>,
+    patch: Region,
+    context: Region,
+    margin: u32,
+) -> Array3<f64> {
+    let mut composited = original.clone();
+    let distance = |value: usize, start: u32, len: u32| -> f64 {
+        let (start, end) = (start as f64, (start + len - 1) as f64);
+        let value = value as f64;
+        (start - value).max(value - end).max(0.0)
+    };
+
+    for ((x, y, z), &value) in solved.indexed_iter() {
+        let (x, y) = (x + context.x as usize, y + context.y as usize);
+        let d = distance(x, patch.x, patch.width).hypot(distance(
+            y,
+            patch.y,
+            patch.height,
+        ));
+        let weight = if margin == 0 {
+            if d == 0.0 {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            let t = (1.0 - d / margin as f64).clamp(0.0, 1.0);
+            // smoothstep, for a seamless transition
+            t * t * (3.0 - 2.0 * t)
+        };
+        let pixel = &mut composited[[x, y, z]];
+        *pixel = weight * value + (1.0 - weight) * *pixel;
+    }
+
+    composited
+}
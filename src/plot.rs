@@ -0,0 +1,196 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Charts of a sweep, to choose λ from: its metrics against λ (e.g. the
+//! "knee" of the PSNR curve), and its L-curve.
+
+use std::{
+    error::Error,
+    path::Path,
+};
+
+use plotters::{
+    coord::Shift,
+    prelude::*,
+};
+
+use crate::{
+    report::Report,
+    storage,
+};
+
+const WIDTH: u32 = 800;
+/// Height of each of the charts, stacked vertically.
+const CHART_HEIGHT: u32 = 300;
+
+/// Renders the PSNR, SSIM and time taken against λ (on a log scale) for
+/// each solved λ value of the report, as an SVG image.
+pub fn render(report: &Report, path: &Path) -> Result<(), Box<dyn Error>> {
+    let charts: [(&str, Vec<(f64, f64)>); 3] = [
+        (
+            "PSNR (dB)",
+            report
+                .lambdas
+                .iter()
+                .filter_map(|entry| Some((entry.lambda, entry.psnr?)))
+                .filter(|(_, psnr)| psnr.is_finite())
+                .collect(),
+        ),
+        (
+            "SSIM",
+            report
+                .lambdas
+                .iter()
+                .filter_map(|entry| Some((entry.lambda, entry.ssim?)))
+                .collect(),
+        ),
+        (
+            "time (s)",
+            report
+                .lambdas
+                .iter()
+                .map(|entry| (entry.lambda, entry.seconds))
+                .collect(),
+        ),
+    ];
+
+    let size = (WIDTH, CHART_HEIGHT * charts.len() as u32);
+    save_svg(path, size, |root| {
+        root.fill(&WHITE)?;
+        for (area, (name, points)) in
+            root.split_evenly((charts.len(), 1)).iter().zip(&charts)
+        {
+            if points.is_empty() {
+                continue;
+            }
+            let (lambdas, values) = (
+                padded_range(points.iter().map(|&(lambda, _)| lambda), true),
+                padded_range(points.iter().map(|&(_, value)| value), false),
+            );
+            let mut chart = ChartBuilder::on(area)
+                .caption(format!("{} against lambda", name), ("sans-serif", 20))
+                .margin(10)
+                .x_label_area_size(40)
+                .y_label_area_size(60)
+                .build_cartesian_2d(lambdas.log_scale(), values)?;
+            chart
+                .configure_mesh()
+                .x_desc("lambda")
+                .x_label_formatter(&|lambda| format!("{:.1e}", lambda))
+                .y_desc(*name)
+                .draw()?;
+            chart
+                .draw_series(LineSeries::new(points.iter().cloned(), &BLUE))?;
+            chart.draw_series(
+                points
+                    .iter()
+                    .map(|&point| Circle::new(point, 3, BLUE.filled())),
+            )?;
+        }
+        Ok(())
+    })
+}
+
+/// Renders the L-curve of the report, the residual norm against the total
+/// variation of each solved λ value on log-log scales, marking its corner.
+pub fn render_l_curve(
+    report: &Report,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let points: Vec<(f64, f64)> = report
+        .lambdas
+        .iter()
+        .map(|entry| (entry.residual_norm, entry.total_variation))
+        .filter(|&(residual, tv)| residual > 0.0 && tv > 0.0)
+        .collect();
+    save_svg(path, (WIDTH, WIDTH), |root| {
+        root.fill(&WHITE)?;
+        if points.is_empty() {
+            return Ok(());
+        }
+
+        let mut chart = ChartBuilder::on(root)
+            .caption("L-curve", ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(
+                padded_range(points.iter().map(|&(x, _)| x), true).log_scale(),
+                padded_range(points.iter().map(|&(_, y)| y), true).log_scale(),
+            )?;
+        chart
+            .configure_mesh()
+            .x_desc("residual norm")
+            .y_desc("total variation")
+            .x_label_formatter(&|value| format!("{:.1e}", value))
+            .y_label_formatter(&|value| format!("{:.1e}", value))
+            .draw()?;
+        chart.draw_series(LineSeries::new(points.iter().cloned(), &BLUE))?;
+        chart.draw_series(
+            points
+                .iter()
+                .map(|&point| Circle::new(point, 3, BLUE.filled())),
+        )?;
+        let corner = report
+            .lambdas
+            .iter()
+            .find(|entry| Some(entry.lambda) == report.l_curve_corner);
+        if let Some(entry) = corner {
+            let point = (entry.residual_norm, entry.total_variation);
+            chart.draw_series([Circle::new(point, 6, RED.filled())])?;
+            chart.draw_series([Text::new(
+                format!("  lambda {:.3e}", entry.lambda),
+                point,
+                ("sans-serif", 14),
+            )])?;
+        }
+        Ok(())
+    })
+}
+
+/// Draws an SVG image of the given size with `draw`, and saves it.
+fn save_svg(
+    path: &Path,
+    size: (u32, u32),
+    draw: impl FnOnce(&DrawingArea<SVGBackend, Shift>) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, size).into_drawing_area();
+        draw(&root)?;
+        root.present()?;
+    }
+    storage::get().write(path, svg.as_bytes())?;
+    Ok(())
+}
+
+/// Range covering all the values with some room around them, also when
+/// there is a single one; multiplicative for ranges on a log scale.
+fn padded_range(
+    values: impl Iterator<Item = f64>,
+    logarithmic: bool,
+) -> std::ops::Range<f64> {
+    let (min, max) = values
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+    if logarithmic {
+        let room = (max / min).powf(0.05).max(1.1);
+        min / room..max * room
+    } else {
+        let room = ((max - min) * 0.05).max(max.abs() * 0.01).max(1e-6);
+        min - room..max + room
+    }
+}
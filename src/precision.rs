@@ -0,0 +1,137 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Choice of the precision of the solver: f32 is faster, but only worth it
+//! when it gives the same output as f64, which `--precision auto` checks
+//! on a downscaled copy of the input before solving.
+
+use image_recovery::ndarray::{
+    Array3,
+    Zip,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    cancel,
+    resize,
+    solver::{
+        self,
+        Precision,
+    },
+};
+
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Choice {
+    /// f32 if a quick check finds no visible difference from f64
+    Auto,
+    F32,
+    F64,
+}
+
+impl Choice {
+    /// The precision chosen, `None` for `Auto` until it is checked.
+    pub fn fixed(self) -> Option<Precision> {
+        match self {
+            Choice::Auto => None,
+            Choice::F32 => Some(Precision::F32),
+            Choice::F64 => Some(Precision::F64),
+        }
+    }
+}
+
+/// Largest side in pixels of the copy of the input the check is solved on.
+const CHECK_DIMENSION: u32 = 128;
+const CHECK_ITERATIONS: u32 = 200;
+/// Largest difference (in 8-bit levels) between the f32 and f64 results of
+/// the check for f32 to be chosen: under half a level, they round to the
+/// same 8-bit output almost everywhere.
+const VISUAL_THRESHOLD: f64 = 0.5;
+/// Convergence thresholds below this are within the rounding of f32, whose
+/// residuals would never fall below them.
+const F32_RESIDUAL_FLOOR: f64 = 1e-6;
+
+/// Chooses f32 if solving a downscaled copy of `image` at `lambda` in both
+/// precisions gives results no further apart than `VISUAL_THRESHOLD`, and
+/// f64 otherwise (or if the threshold is too fine for f32 to reach).
+pub fn choose(
+    image: &Array3<f64>,
+    lambda: f64,
+    convergence_threshold: f64,
+    acceleration: solver::Acceleration,
+    cancel: &cancel::Token,
+) -> Result<Precision, cancel::Cancelled> {
+    if convergence_threshold < F32_RESIDUAL_FLOOR {
+        log::info!(
+            "precision: f64, as f32 cannot reach the convergence threshold of \
+             {:e}",
+            convergence_threshold
+        );
+        return Ok(Precision::F64);
+    }
+
+    let draft = resize::fit_within(
+        image,
+        CHECK_DIMENSION,
+        resize::ResizeFilter::Bicubic,
+    );
+    let draft = draft.as_ref().unwrap_or(image);
+    let solve = |precision| -> Result<Array3<f64>, cancel::Cancelled> {
+        let parameters = solver::Parameters {
+            lambda,
+            tau: solver::tau(),
+            sigma: solver::sigma(),
+            gamma: solver::gamma(lambda),
+            max_iter: CHECK_ITERATIONS,
+            convergence_threshold,
+            acceleration,
+            channel_copies: 1,
+            threads: 1,
+            precision,
+            data_weight: None,
+            data_band: None,
+            stop_on_gap: None,
+            keep_dual: false,
+        };
+        match solver::denoise(draft, &parameters, cancel, &mut |_| {}) {
+            Ok(solution) => Ok(solution.image),
+            Err(solver::Error::Cancelled(cancelled)) => Err(cancelled),
+            Err(error) => panic!("precision check failed: {}", error),
+        }
+    };
+    let single = solve(Precision::F32)?;
+    let double = solve(Precision::F64)?;
+
+    let mut difference: f64 = 0.0;
+    Zip::from(&single).and(&double).for_each(|a, b| {
+        difference = difference.max((a - b).abs());
+    });
+    let precision = match difference <= VISUAL_THRESHOLD {
+        true => Precision::F32,
+        false => Precision::F64,
+    };
+    log::info!(
+        "precision: {}, as f32 differs from f64 by up to {:e} levels on a \
+         downscaled copy of the input",
+        precision,
+        difference
+    );
+    Ok(precision)
+}
@@ -0,0 +1,243 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Progress of a sweep, drawn as a status line on stderr (or printed as
+//! plain lines of text) with an estimate of the time left. The iterations a
+//! solve has left are predicted from the decay of its residual, as most solves
+//! converge well before `max_iter`.
+
+use std::{
+    io::Write,
+    sync::Mutex,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// Time between redraws of the status line.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// Time between the lines printed in the plain style, few enough for a
+/// screen reader to keep up with.
+const PLAIN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How progress is shown.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// A status line redrawn in place, with terminal escape codes
+    Bar,
+    /// A new line of plain text every 10 seconds and after every solve,
+    /// for screen readers and logs that don't understand escape codes
+    Plain,
+}
+
+impl Style {
+    fn interval(self) -> Duration {
+        match self {
+            Style::Bar => INTERVAL,
+            Style::Plain => PLAIN_INTERVAL,
+        }
+    }
+}
+
+pub struct Progress {
+    state: Mutex<State>,
+}
+
+struct State {
+    style: Style,
+    total: usize,
+    jobs: usize,
+    /// Seconds taken by each finished solve.
+    finished: Vec<f64>,
+    running: Vec<Solve>,
+    drawn: Option<Instant>,
+}
+
+struct Solve {
+    lambda: f64,
+    started: Instant,
+    iteration: u32,
+    /// Predicted total iterations.
+    predicted: u32,
+}
+
+impl Solve {
+    fn seconds_left(&self) -> f64 {
+        let per_iteration =
+            self.started.elapsed().as_secs_f64() / self.iteration.max(1) as f64;
+        self.predicted.saturating_sub(self.iteration) as f64 * per_iteration
+    }
+
+    fn seconds_total(&self) -> f64 {
+        self.started.elapsed().as_secs_f64() + self.seconds_left()
+    }
+}
+
+impl Progress {
+    /// Progress of `total` solves, `jobs` of them at a time.
+    pub fn new(total: usize, jobs: usize, style: Style) -> Self {
+        Self {
+            state: Mutex::new(State {
+                style,
+                total,
+                jobs: jobs.max(1),
+                finished: Vec::new(),
+                running: Vec::new(),
+                drawn: None,
+            }),
+        }
+    }
+
+    /// Starts timing the solve of `lambda`.
+    pub fn start(&self, lambda: f64) {
+        self.state().running.push(Solve {
+            lambda,
+            started: Instant::now(),
+            iteration: 0,
+            predicted: 0,
+        });
+    }
+
+    /// Whether the status line is due for a redraw, to only predict the
+    /// iterations left when it is.
+    pub fn is_due(&self) -> bool {
+        self.state().is_due()
+    }
+
+    /// Updates the solve of `lambda` after `iteration` out of `predicted`,
+    /// and redraws the status line if due.
+    pub fn update(&self, lambda: f64, iteration: u32, predicted: u32) {
+        let mut state = self.state();
+        if let Some(solve) = state
+            .running
+            .iter_mut()
+            .find(|solve| solve.lambda == lambda)
+        {
+            solve.iteration = iteration;
+            solve.predicted = predicted.max(iteration);
+        }
+        if state.is_due() {
+            state.draw();
+        }
+    }
+
+    /// Marks the solve of `lambda` as finished, successfully or not.
+    pub fn finish(&self, lambda: f64) {
+        let mut state = self.state();
+        if let Some(index) = state
+            .running
+            .iter()
+            .position(|solve| solve.lambda == lambda)
+        {
+            let solve = state.running.remove(index);
+            state.finished.push(solve.started.elapsed().as_secs_f64());
+        }
+        state.draw();
+    }
+
+    /// Erases the status line, once the sweep is done.
+    pub fn clear(&self) {
+        if self.state().style == Style::Bar {
+            eprint!("\r\x1b[K");
+            std::io::stderr().flush().ok();
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl State {
+    fn is_due(&self) -> bool {
+        self.drawn
+            .is_none_or(|drawn| drawn.elapsed() >= self.style.interval())
+    }
+
+    /// Seconds left for the whole sweep: those of the slowest running
+    /// solve, plus rounds of `jobs` solves for the ones not started yet,
+    /// each taking as long as those seen so far on average.
+    fn seconds_left(&self) -> Option<f64> {
+        let seen: Vec<f64> = self
+            .finished
+            .iter()
+            .cloned()
+            .chain(self.running.iter().map(Solve::seconds_total))
+            .collect();
+        if seen.is_empty() {
+            return None;
+        }
+        let average = seen.iter().sum::<f64>() / seen.len() as f64;
+        let pending = self.total - self.finished.len() - self.running.len();
+        let running = self
+            .running
+            .iter()
+            .map(Solve::seconds_left)
+            .fold(0.0, f64::max);
+        Some(running + pending.div_ceil(self.jobs) as f64 * average)
+    }
+
+    fn draw(&mut self) {
+        self.drawn = Some(Instant::now());
+        let eta = match self.seconds_left() {
+            Some(seconds) => {
+                format!(", about {} left", format_seconds(seconds))
+            },
+            None => String::new(),
+        };
+        let solving = self
+            .running
+            .iter()
+            .map(|solve| {
+                format!(
+                    "lambda {:.10} at {}/~{}",
+                    solve.lambda, solve.iteration, solve.predicted
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let line = format!(
+            "{}/{} done{}{}",
+            self.finished.len(),
+            self.total,
+            if solving.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", solving)
+            },
+            eta
+        );
+        match self.style {
+            Style::Bar => {
+                eprint!("\r\x1b[K{}", line);
+                std::io::stderr().flush().ok();
+            },
+            Style::Plain => eprintln!("progress: {}", line),
+        }
+    }
+}
+
+fn format_seconds(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds / 60 % 60),
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The SHA-256 of the input embedded in each PNG output, as a `tEXt`
+//! chunk readable by the usual metadata tools (e.g. `exiftool`), so that
+//! it can later be checked which file an output was denoised from.
+
+use std::{
+    io,
+    path::Path,
+};
+
+use crate::storage;
+
+/// Keyword of the text chunk holding the hash.
+pub const KEYWORD: &str = "Source SHA-256";
+
+/// The chunk that ends every PNG file.
+const IEND: [u8; 12] =
+    [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82];
+
+/// Adds a `tEXt` chunk with `sha256` to the PNG file at `path`, right
+/// before its end.
+pub fn embed(path: &Path, sha256: &str) -> io::Result<()> {
+    let mut chunk = b"tEXt".to_vec();
+    chunk.extend_from_slice(KEYWORD.as_bytes());
+    chunk.push(0);
+    chunk.extend_from_slice(sha256.as_bytes());
+    add_chunk(path, &chunk)
+}
+
+/// Adds `chunk` (its type, then its data) to the PNG file at `path`, right
+/// before its end.
+pub fn add_chunk(path: &Path, chunk: &[u8]) -> io::Result<()> {
+    let mut bytes = storage::get().read(path)?;
+    if !bytes.ends_with(&IEND) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a PNG file",
+        ));
+    }
+    let end = bytes.len() - IEND.len();
+    let mut encoded = ((chunk.len() - 4) as u32).to_be_bytes().to_vec();
+    encoded.extend_from_slice(chunk);
+    encoded.extend_from_slice(&crc32(chunk).to_be_bytes());
+    bytes.splice(end..end, encoded);
+    storage::get().write(path, &bytes)
+}
+
+/// CRC-32 of a chunk's type and data, as required by the PNG format.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(u32::MAX, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| match crc & 1 {
+            1 => (crc >> 1) ^ 0xedb8_8320,
+            _ => crc >> 1,
+        })
+    })
+}
@@ -0,0 +1,170 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Progress through the entries of a manifest being applied, persisted as
+//! newline-delimited JSON events appended as they happen, so that a long
+//! batch that was interrupted (even by a reboot) resumes where it left
+//! off, knowing how many times each entry was attempted.
+
+use std::{
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    journal,
+    storage,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    /// The entry is about to be solved; a start with no end means the run
+    /// was interrupted while solving it.
+    Started,
+    Done,
+    Failed,
+    /// Solving it was cancelled, which does not count as an attempt.
+    Cancelled,
+}
+
+/// One line of the queue.
+#[derive(Serialize, Deserialize, Debug)]
+struct Line {
+    /// Seconds since the Unix epoch at which the event happened.
+    timestamp: u64,
+    /// Index of the entry in the manifest.
+    entry: usize,
+    /// Input of the entry, to tell if the manifest changed.
+    input: PathBuf,
+    event: Event,
+}
+
+/// How far an entry of the manifest got.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Times it was started, including any interrupted other than by
+    /// cancelling.
+    pub attempts: u32,
+    pub done: bool,
+}
+
+impl Progress {
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::Started => self.attempts += 1,
+            Event::Done => self.done = true,
+            Event::Failed => (),
+            Event::Cancelled => self.attempts = self.attempts.saturating_sub(1),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Queue {
+    path: PathBuf,
+    progress: Vec<Progress>,
+}
+
+impl Queue {
+    /// Replays the events of the queue at `path`, if there is one yet,
+    /// for a manifest whose entries have the given inputs. The last line
+    /// is dropped if it is incomplete, as left by an interrupted write, and
+    /// ended if only its newline is missing.
+    pub fn open(path: &Path, inputs: &[&Path]) -> io::Result<Self> {
+        let mut queue = Self {
+            path: path.to_path_buf(),
+            progress: vec![Progress::default(); inputs.len()],
+        };
+        if !storage::get().is_file(path) {
+            return Ok(queue);
+        }
+
+        let bytes = storage::get().read(path)?;
+        let text = String::from_utf8_lossy(&bytes);
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        // bytes of the lines read so far
+        let mut complete = 0;
+        for (number, text) in lines.iter().enumerate() {
+            if text.trim().is_empty() {
+                complete += text.len();
+                continue;
+            }
+            let line: Line = match serde_json::from_str(text) {
+                Ok(line) => line,
+                Err(_) if number + 1 == lines.len() => {
+                    log::warn!(
+                        "dropping the incomplete last line of the queue: {}",
+                        path.to_string_lossy()
+                    );
+                    // so that the next event starts a line of its own
+                    storage::get().write(path, &bytes[..complete])?;
+                    return Ok(queue);
+                },
+                Err(error) => return Err(error.into()),
+            };
+            complete += text.len();
+            if inputs.get(line.entry) != Some(&line.input.as_path()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "entry {} of the queue is for {}, which is not in the \
+                         manifest at that place",
+                        line.entry,
+                        line.input.to_string_lossy()
+                    ),
+                ));
+            }
+            queue.progress[line.entry].apply(line.event);
+        }
+        if !bytes.is_empty() && !bytes.ends_with(b"\n") {
+            // so that the next event starts a line of its own
+            storage::get().append(path, b"\n")?;
+        }
+        Ok(queue)
+    }
+
+    pub fn progress(&self, entry: usize) -> Progress {
+        self.progress[entry]
+    }
+
+    /// Appends an event of an entry to the queue.
+    pub fn record(
+        &mut self,
+        entry: usize,
+        input: &Path,
+        event: Event,
+    ) -> io::Result<()> {
+        let line = Line {
+            timestamp: journal::now(),
+            entry,
+            input: input.to_path_buf(),
+            event,
+        };
+        let mut bytes = serde_json::to_vec(&line)?;
+        bytes.push(b'\n');
+        storage::get().append(&self.path, &bytes)?;
+        self.progress[entry].apply(event);
+        Ok(())
+    }
+}
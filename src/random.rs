@@ -0,0 +1,56 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A small seeded random number generator for synthetic test images, so
+//! that runs can be repeated exactly.
+
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// A generator from `seed`, which may be anything (zero included).
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on a zero state
+        Self {
+            state: seed ^ 0x2545f4914f6cdd1d,
+        }
+    }
+
+    /// The next value from xorshift64*.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    /// Uniformly distributed in (0, 1].
+    pub fn uniform(&mut self) -> f64 {
+        // the top 53 bits, as many as a float holds
+        ((self.next_u64() >> 11) as f64 + 1.0) / (1_u64 << 53) as f64
+    }
+
+    /// Uniformly distributed in `[low, high)`.
+    pub fn range(&mut self, low: f64, high: f64) -> f64 {
+        high - (high - low) * self.uniform()
+    }
+
+    /// Standard normally distributed, by the Box-Muller transform.
+    pub fn gaussian(&mut self) -> f64 {
+        (-2.0 * self.uniform().ln()).sqrt()
+            * (2.0 * std::f64::consts::PI * self.uniform()).cos()
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! What becomes of the values the solver leaves outside of the 8-bit range
+//! (as it may near bright edges, overshooting them) when they are saved.
+
+use std::borrow::Cow;
+
+use image_recovery::ndarray::Array3;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    /// Clip them to the nearest end of the range
+    Clamp,
+    /// Rescale the whole output linearly so that they fit, if any don't
+    Normalize,
+    /// Fail the λ value
+    Error,
+}
+
+/// Samples of an output outside of the 8-bit range by a full level or
+/// more, which is as much as the conversion to 8 bits (truncating each
+/// value) gets wrong.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct OutOfRange {
+    /// Samples under 0 (or not a number).
+    pub below: u64,
+    /// Samples over 255.
+    pub above: u64,
+    pub samples: u64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl OutOfRange {
+    pub fn of(array: &Array3<f64>) -> Self {
+        let (mut below, mut above) = (0, 0);
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for &value in array {
+            if value <= -1.0 || value.is_nan() {
+                below += 1;
+            } else if value >= 256.0 {
+                above += 1;
+            }
+            min = min.min(value);
+            max = max.max(value);
+        }
+        Self {
+            below,
+            above,
+            samples: array.len() as u64,
+            min,
+            max,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.below + self.above == 0
+    }
+
+    /// Percentage of samples out of range.
+    pub fn percentage(&self) -> f64 {
+        (self.below + self.above) as f64 / self.samples.max(1) as f64 * 100.0
+    }
+}
+
+impl std::fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} samples under 0 and {} over 255 ({:.3}%), from {:.3} to {:.3}",
+            self.below,
+            self.above,
+            self.percentage(),
+            self.min,
+            self.max
+        )
+    }
+}
+
+/// `array` as it is to be converted to 8 bits under `policy`, given how
+/// much of it is `out_of_range`; an error under `Policy::Error` if any of
+/// it is.
+pub fn apply<'a>(
+    array: &'a Array3<f64>,
+    out_of_range: &OutOfRange,
+    policy: Policy,
+) -> Result<Cow<'a, Array3<f64>>, String> {
+    if out_of_range.is_empty() {
+        return Ok(Cow::Borrowed(array));
+    }
+    match policy {
+        // clipped by the conversion itself
+        Policy::Clamp => Ok(Cow::Borrowed(array)),
+        Policy::Normalize => {
+            let low = out_of_range.min.min(0.0);
+            let high = out_of_range.max.max(255.0);
+            let scale = 255.0 / (high - low);
+            Ok(Cow::Owned(array.mapv(|value| (value - low) * scale)))
+        },
+        Policy::Error => Err(format!("output out of range: {}", out_of_range)),
+    }
+}
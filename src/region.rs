@@ -0,0 +1,98 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Rectangular regions of an image, given on the command line as
+//! `x,y,w,h` in pixels from the top left corner.
+
+use std::str::FromStr;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// Whether the region lies entirely inside an image of the given size.
+    pub fn fits_in(&self, width: u32, height: u32) -> bool {
+        self.x
+            .checked_add(self.width)
+            .is_some_and(|right| right <= width)
+            && self
+                .y
+                .checked_add(self.height)
+                .is_some_and(|bottom| bottom <= height)
+    }
+
+    /// The same region on a copy of the image resized by the given
+    /// factors, keeping at least one pixel.
+    pub fn scaled(&self, x_factor: f64, y_factor: f64) -> Self {
+        let scale = |value: u32, factor: f64| (value as f64 * factor).round();
+        Self {
+            x: scale(self.x, x_factor) as u32,
+            y: scale(self.y, y_factor) as u32,
+            width: scale(self.width, x_factor).max(1.0) as u32,
+            height: scale(self.height, y_factor).max(1.0) as u32,
+        }
+    }
+
+    /// Range of x coordinates covered by the region.
+    pub fn columns(&self) -> std::ops::Range<usize> {
+        self.x as usize..(self.x + self.width) as usize
+    }
+
+    /// Range of y coordinates covered by the region.
+    pub fn rows(&self) -> std::ops::Range<usize> {
+        self.y as usize..(self.y + self.height) as usize
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|error| format!("invalid region `{}`: {}", s, error))?;
+        match values[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(Self {
+                x,
+                y,
+                width,
+                height,
+            }),
+            [_, _, _, _] => Err(format!(
+                "invalid region `{}`: width and height cannot be zero",
+                s
+            )),
+            _ => Err(format!("invalid region `{}`: expected `x,y,w,h`", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
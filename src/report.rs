@@ -0,0 +1,706 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Summary of a sweep over λ values, printed to the console at the end of a
+//! run and optionally saved as JSON.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use serde::Serialize;
+
+use crate::{
+    analysis,
+    cost,
+    golden,
+    locale,
+    range,
+    region::Region,
+    solver,
+    storage,
+    DenoiseArgs,
+};
+
+/// Results for a single λ value of the sweep.
+#[derive(Serialize, Debug)]
+pub struct LambdaReport {
+    pub lambda: f64,
+    pub output: PathBuf,
+    /// Estimated standard deviation of the noise left in the output,
+    /// measured the same way as for the input.
+    pub noise: f64,
+    /// Correlation of the input and output gradients on the strong edges
+    /// of the input, if it has any.
+    pub edge_preservation: Option<f64>,
+    /// Fraction of smooth ramp patches turned into flat plateaus, if the
+    /// input had any ramps to judge by.
+    pub staircasing: Option<f64>,
+    /// Whether `staircasing` is over the configured threshold.
+    pub staircasing_flagged: bool,
+    /// Peak signal-to-noise ratio relative to the clean reference, in dB,
+    /// if one was given.
+    pub psnr: Option<f64>,
+    /// Structural similarity to the clean reference, if one was given.
+    pub ssim: Option<f64>,
+    /// Color difference from the clean reference, if one was given.
+    pub delta_e: Option<analysis::ColorDifference>,
+    /// PSNR and SSIM of each channel relative to the clean reference, if
+    /// one was given.
+    pub channels: Vec<analysis::ChannelMetrics>,
+    /// Metrics against each of the references, if several were given (the
+    /// others being against their mean), in the order they were given.
+    pub references: Vec<ReferenceMetrics>,
+    /// Color difference from the input, the colors shifted by the
+    /// denoising.
+    pub input_delta_e: analysis::ColorDifference,
+    /// Norm of the difference between the output and the input.
+    pub residual_norm: f64,
+    /// Total variation of the output.
+    pub total_variation: f64,
+    pub iterations: u32,
+    /// Pixels solved (with their margins and padding) times `iterations`,
+    /// in millions.
+    pub megapixel_iterations: f64,
+    /// Relative change of the last iteration.
+    pub residual: f64,
+    /// Whether the solve stopped on the convergence threshold; if not it
+    /// stopped on `max_iter`, and the output may not be fully converged.
+    pub converged: bool,
+    /// Value of the objective at the last iteration.
+    pub energy: solver::Energy,
+    /// Value of the objective at every iteration, with --log-energy.
+    pub energies: Vec<solver::Energy>,
+    /// Primal-dual gap at the last iteration relative to `energy`, with
+    /// --stop-on-gap.
+    pub gap: Option<f64>,
+    /// Samples of the output outside of the 8-bit range, before
+    /// --out-of-range applied to them, unless it was saved as floating
+    /// point.
+    pub out_of_range: Option<range::OutOfRange>,
+    /// Why the saved output failed verification, if it was verified.
+    pub verification_error: Option<String>,
+    /// How the output compares to its golden output, if it was compared.
+    pub golden: Option<golden::Comparison>,
+    /// Wall-clock time taken to solve and save the output.
+    pub seconds: f64,
+}
+
+/// How an output compares to one of several clean references.
+#[derive(Serialize, Debug)]
+pub struct ReferenceMetrics {
+    pub psnr: f64,
+    pub ssim: f64,
+    pub delta_e: analysis::ColorDifference,
+}
+
+/// Results of an external method run on the same input as the sweep,
+/// measured the same way as its outputs.
+#[derive(Serialize, Debug)]
+pub struct MethodReport {
+    pub name: String,
+    pub command: String,
+    pub output: PathBuf,
+    pub noise: f64,
+    pub edge_preservation: Option<f64>,
+    pub staircasing: Option<f64>,
+    pub psnr: Option<f64>,
+    pub ssim: Option<f64>,
+    pub delta_e: Option<analysis::ColorDifference>,
+    pub channels: Vec<analysis::ChannelMetrics>,
+    pub input_delta_e: analysis::ColorDifference,
+    pub residual_norm: f64,
+    pub total_variation: f64,
+    /// Wall-clock time taken by the command.
+    pub seconds: f64,
+}
+
+/// An external method that failed to run or to give a usable result.
+#[derive(Serialize, Debug)]
+pub struct MethodFailure {
+    pub name: String,
+    pub message: String,
+}
+
+/// A λ value of the sweep whose solve failed.
+#[derive(Serialize, Debug)]
+pub struct LambdaFailure {
+    pub lambda: f64,
+    pub output: PathBuf,
+    pub message: String,
+}
+
+/// Results for a whole sweep.
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub input: PathBuf,
+    /// Estimated standard deviation of the noise in the input.
+    pub input_noise: f64,
+    /// Flat region the noise was measured in, if not estimated globally.
+    pub noise_patch: Option<Region>,
+    /// Clean references the outputs were measured against.
+    pub references: Vec<PathBuf>,
+    /// Sorted by ascending λ.
+    pub lambdas: Vec<LambdaReport>,
+    /// Sorted by ascending λ.
+    pub failures: Vec<LambdaFailure>,
+    /// External methods compared with the sweep, in the order they were
+    /// given.
+    pub methods: Vec<MethodReport>,
+    pub method_failures: Vec<MethodFailure>,
+    pub staircasing_threshold: f64,
+    /// Largest λ flagged for staircasing; smaller λ values smooth more, so
+    /// artifacts are to be expected at and below this value.
+    pub staircasing_onset: Option<f64>,
+    /// λ at the corner of the L-curve (residual norm against total
+    /// variation), suggested as a balance of fidelity and smoothing.
+    pub l_curve_corner: Option<f64>,
+    /// λ chosen by the discrepancy principle, if searched for.
+    pub discrepancy: Option<Discrepancy>,
+    /// What the whole run cost to compute, once it is done.
+    pub cost: Option<cost::Cost>,
+}
+
+/// Search for the λ value whose output differs from the input by as much
+/// as the noise.
+#[derive(Serialize, Debug)]
+pub struct Discrepancy {
+    /// Standard deviation of the noise per sample, given or estimated.
+    pub noise: f64,
+    pub lambda: f64,
+    /// Root-mean-square difference of the chosen output from the input.
+    pub residual_rms: f64,
+}
+
+impl Report {
+    /// Sorts the per-λ results and flags those over `staircasing_threshold`.
+    pub fn new(
+        input: PathBuf,
+        input_noise: f64,
+        noise_patch: Option<Region>,
+        results: Vec<Result<LambdaReport, LambdaFailure>>,
+        staircasing_threshold: f64,
+    ) -> Self {
+        let (mut lambdas, mut failures) = (Vec::new(), Vec::new());
+        for result in results {
+            match result {
+                Ok(entry) => lambdas.push(entry),
+                Err(failure) => failures.push(failure),
+            }
+        }
+        lambdas.sort_by(|a, b| a.lambda.total_cmp(&b.lambda));
+        failures.sort_by(|a, b| a.lambda.total_cmp(&b.lambda));
+        for entry in lambdas.iter_mut() {
+            entry.staircasing_flagged = entry
+                .staircasing
+                .is_some_and(|score| score > staircasing_threshold);
+        }
+        let staircasing_onset = lambdas
+            .iter()
+            .filter(|entry| entry.staircasing_flagged)
+            .map(|entry| entry.lambda)
+            .next_back();
+        let l_curve_corner = analysis::l_curve_corner(
+            &lambdas
+                .iter()
+                .map(|entry| {
+                    (entry.lambda, entry.residual_norm, entry.total_variation)
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        Self {
+            input,
+            input_noise,
+            noise_patch,
+            references: Vec::new(),
+            lambdas,
+            failures,
+            methods: Vec::new(),
+            method_failures: Vec::new(),
+            staircasing_threshold,
+            staircasing_onset,
+            l_curve_corner,
+            discrepancy: None,
+            cost: None,
+        }
+    }
+
+    /// Whether every λ value was solved and its output passed verification,
+    /// and every external method ran.
+    pub fn succeeded(&self) -> bool {
+        self.failures.is_empty()
+            && self.method_failures.is_empty()
+            && self.lambdas.iter().all(|entry| {
+                entry.verification_error.is_none()
+                    && entry
+                        .golden
+                        .as_ref()
+                        .is_none_or(|golden| golden.passed())
+            })
+    }
+
+    /// Prints a table with one line per λ value to stdout.
+    pub fn print_summary(&self) {
+        // after the log lines buffered so far
+        log::logger().flush();
+        println!(
+            "{}",
+            tr!(
+                "summary for {} (estimated noise: {}{}):",
+                self.input.to_string_lossy(),
+                format!("{:.3}", self.input_noise),
+                match self.noise_patch {
+                    Some(patch) => tr!(" in patch {}", patch),
+                    None => String::new(),
+                }
+            )
+        );
+        // measured against a clean reference only if one was given
+        let referenced = self.lambdas.iter().any(|entry| entry.psnr.is_some());
+        println!(
+            "{:>14}  {:>8}  {:>8}  {:>8}  {:>12}  {:>11}  {:>10}{}",
+            "lambda",
+            tr!("noise"),
+            tr!("edges"),
+            tr!("shift"),
+            tr!("staircasing"),
+            tr!("iterations"),
+            tr!("energy"),
+            if referenced {
+                format!("  {:>8}  {:>8}  {:>8}", "psnr", "ssim", "ΔE")
+            } else {
+                String::new()
+            }
+        );
+        for entry in &self.lambdas {
+            let flag = if entry.staircasing_flagged {
+                " (!)"
+            } else {
+                ""
+            };
+            let iterations = if entry.converged {
+                format!("{} ", entry.iterations)
+            } else {
+                format!("{}*", entry.iterations)
+            };
+            println!(
+                "{:>14.10}  {:>8.3}  {:>8}  {:>8.3}  {:>12}  {:>11}  \
+                 {:>10.4e}{}{}",
+                entry.lambda,
+                entry.noise,
+                format_optional(entry.edge_preservation),
+                entry.input_delta_e.mean,
+                format_optional(entry.staircasing),
+                iterations,
+                entry.energy.total,
+                if referenced {
+                    format!(
+                        "  {:>8}  {:>8}  {:>8}",
+                        format_optional(entry.psnr),
+                        format_optional(entry.ssim),
+                        format_optional(entry.delta_e.map(|delta| delta.mean))
+                    )
+                } else {
+                    String::new()
+                },
+                flag
+            );
+        }
+        if referenced {
+            println!(
+                "{}",
+                tr!("per channel, with the samples as they are denoised \
+                     (gamma encoded, or linear for floating point inputs):")
+            );
+            println!(
+                "{:>14}  {:>8}  {:>8}  {:>8}",
+                "lambda",
+                tr!("channel"),
+                "psnr",
+                "ssim"
+            );
+            for entry in &self.lambdas {
+                for metrics in &entry.channels {
+                    println!(
+                        "{:>14.10}  {:>8}  {:>8.3}  {:>8.3}",
+                        entry.lambda,
+                        locale::translate(metrics.channel),
+                        metrics.psnr,
+                        metrics.ssim
+                    );
+                }
+            }
+        }
+        if self
+            .lambdas
+            .iter()
+            .any(|entry| !entry.references.is_empty())
+        {
+            println!(
+                "{}",
+                tr!(
+                    "against each of the {} references (the metrics above are \
+                     against their mean):",
+                    self.references.len()
+                )
+            );
+            for (index, reference) in self.references.iter().enumerate() {
+                println!("{:>5}. {}", index + 1, reference.to_string_lossy());
+            }
+            println!(
+                "{:>14}  {:>10}  {:>8}  {:>8}  {:>8}",
+                "lambda",
+                tr!("reference"),
+                "psnr",
+                "ssim",
+                "ΔE"
+            );
+            for entry in &self.lambdas {
+                for (index, metrics) in entry.references.iter().enumerate() {
+                    println!(
+                        "{:>14.10}  {:>10}  {:>8.3}  {:>8.3}  {:>8.3}",
+                        entry.lambda,
+                        index + 1,
+                        metrics.psnr,
+                        metrics.ssim,
+                        metrics.delta_e.mean
+                    );
+                }
+            }
+        }
+        if !self.methods.is_empty() {
+            println!("{}", tr!("compared with other methods:"));
+            println!(
+                "{:>14}  {:>8}  {:>8}  {:>8}  {:>12}  {:>11}{}",
+                tr!("method"),
+                tr!("noise"),
+                tr!("edges"),
+                tr!("shift"),
+                tr!("staircasing"),
+                tr!("seconds"),
+                if referenced {
+                    format!("  {:>8}  {:>8}  {:>8}", "psnr", "ssim", "ΔE")
+                } else {
+                    String::new()
+                }
+            );
+            for method in &self.methods {
+                println!(
+                    "{:>14}  {:>8.3}  {:>8}  {:>8.3}  {:>12}  {:>11.3}{}",
+                    method.name,
+                    method.noise,
+                    format_optional(method.edge_preservation),
+                    method.input_delta_e.mean,
+                    format_optional(method.staircasing),
+                    method.seconds,
+                    if referenced {
+                        format!(
+                            "  {:>8}  {:>8}  {:>8}",
+                            format_optional(method.psnr),
+                            format_optional(method.ssim),
+                            format_optional(
+                                method.delta_e.map(|delta| delta.mean)
+                            )
+                        )
+                    } else {
+                        String::new()
+                    }
+                );
+            }
+        }
+        if let Some(lambda) = self.staircasing_onset {
+            println!(
+                "{}",
+                tr!(
+                    "(!) staircasing above {} expected for lambda <= {}",
+                    self.staircasing_threshold,
+                    format!("{:.10}", lambda)
+                )
+            );
+        }
+        if let Some(discrepancy) = &self.discrepancy {
+            println!(
+                "{}",
+                tr!(
+                    "lambda chosen by the discrepancy principle: {} \
+                     (differing from the input by {} for a noise of {})",
+                    format!("{:.10}", discrepancy.lambda),
+                    format!("{:.3}", discrepancy.residual_rms),
+                    format!("{:.3}", discrepancy.noise)
+                )
+            );
+        }
+        if let Some(lambda) = self.l_curve_corner {
+            println!(
+                "{}",
+                tr!(
+                    "suggested lambda (corner of the L-curve): {}",
+                    format!("{:.10}", lambda)
+                )
+            );
+        }
+        for entry in self.lambdas.iter().filter(|entry| !entry.converged) {
+            println!(
+                "{}",
+                tr!(
+                    "(*) lambda {} stopped on max_iter before converging, \
+                     residual {}: the output may not be fully converged",
+                    format!("{:.10}", entry.lambda),
+                    format!("{:.3e}", entry.residual)
+                )
+            );
+        }
+        for entry in &self.lambdas {
+            if let Some(error) = &entry.verification_error {
+                println!(
+                    "{}",
+                    tr!(
+                        "(v) lambda {} output {} {}",
+                        format!("{:.10}", entry.lambda),
+                        entry.output.to_string_lossy(),
+                        error
+                    )
+                );
+            }
+        }
+        let compared: Vec<_> = self
+            .lambdas
+            .iter()
+            .filter_map(|entry| Some((entry.lambda, entry.golden.as_ref()?)))
+            .collect();
+        if compared.iter().any(|(_, golden)| golden.error.is_none()) {
+            println!(
+                "{:>14}  {:>12}  {:>12}  {:>9}  {:>17}",
+                tr!("golden lambda"),
+                tr!("max diff"),
+                tr!("rms diff"),
+                tr!("differing"),
+                tr!("iterations")
+            );
+        }
+        for (lambda, golden) in
+            compared.iter().filter(|(_, golden)| golden.error.is_none())
+        {
+            println!(
+                "{:>14.10}  {:>12.3e}  {:>12.3e}  {:>9}  {:>17}",
+                lambda,
+                golden.max_difference,
+                golden.rms_difference,
+                golden.differing_samples,
+                tr!("{} (was {})", golden.iterations, golden.golden_iterations)
+            );
+        }
+        for (lambda, golden) in
+            compared.iter().filter(|(_, golden)| !golden.passed())
+        {
+            match &golden.error {
+                Some(error) => println!(
+                    "{}",
+                    tr!(
+                        "(g) lambda {} golden output {} {}",
+                        format!("{:.10}", lambda),
+                        golden.golden.to_string_lossy(),
+                        error
+                    )
+                ),
+                None => println!(
+                    "{}",
+                    tr!(
+                        "(g) lambda {} differs from its golden output in {} \
+                         samples, by more than {}",
+                        format!("{:.10}", lambda),
+                        golden.differing_samples,
+                        format!("{:e}", golden.tolerance)
+                    )
+                ),
+            }
+        }
+        for failure in &self.failures {
+            println!(
+                "{}",
+                tr!(
+                    "(x) lambda {} failed: {}",
+                    format!("{:.10}", failure.lambda),
+                    failure.message
+                )
+            );
+        }
+        for failure in &self.method_failures {
+            println!(
+                "{}",
+                tr!("(x) method {} failed: {}", failure.name, failure.message)
+            );
+        }
+        if let Some(cost) = &self.cost {
+            println!(
+                "{}",
+                tr!(
+                    "cost: {} seconds, {} CPU-seconds, {} joules, {} \
+                     megapixel-iterations",
+                    format!("{:.3}", cost.seconds),
+                    format_optional(cost.cpu_seconds),
+                    format_optional(cost.joules),
+                    format!("{:.3}", cost.megapixel_iterations)
+                )
+            );
+        }
+    }
+
+    /// Saves the report as CSV, with one row per solved λ value along with
+    /// the solver settings it was solved with, and one per external method
+    /// (with the columns of the solver left empty), told apart by `method`.
+    pub fn save_csv(
+        &self,
+        path: &Path,
+        settings: &DenoiseArgs,
+    ) -> std::io::Result<()> {
+        let mut csv = String::from(
+            "input,lambda,output,max_iter,auto_iter,convergence_threshold,\
+             stop_at_delta,acceleration,iterations,converged,residual,seconds,\
+             megapixel_iterations,input_noise,noise,edge_preservation,\
+             staircasing,staircasing_flagged,psnr,ssim,delta_e_mean,\
+             delta_e_p95,delta_e_max,psnr_red,psnr_green,psnr_blue,ssim_red,\
+             ssim_green,ssim_blue,input_delta_e_mean,input_delta_e_p95,\
+             input_delta_e_max,residual_norm,total_variation,energy,\
+             energy_data,energy_total_variation,gap,method\n",
+        );
+        let acceleration = serde_json::to_value(settings.acceleration)?;
+        for entry in &self.lambdas {
+            // gray outputs have the same metrics for every channel
+            let channel = |index: usize| match &entry.channels[..] {
+                [gray] => Some(gray),
+                channels => channels.get(index),
+            };
+            let row = [
+                csv_field(&self.input.to_string_lossy()),
+                entry.lambda.to_string(),
+                csv_field(&entry.output.to_string_lossy()),
+                csv_optional(settings.max_iter),
+                settings.auto_iter.to_string(),
+                csv_optional(settings.convergence_threshold),
+                csv_optional(settings.stop_at_delta.map(|delta| delta.0)),
+                acceleration.as_str().unwrap_or_default().to_string(),
+                entry.iterations.to_string(),
+                entry.converged.to_string(),
+                entry.residual.to_string(),
+                entry.seconds.to_string(),
+                entry.megapixel_iterations.to_string(),
+                self.input_noise.to_string(),
+                entry.noise.to_string(),
+                csv_optional(entry.edge_preservation),
+                csv_optional(entry.staircasing),
+                entry.staircasing_flagged.to_string(),
+                csv_optional(entry.psnr),
+                csv_optional(entry.ssim),
+                csv_optional(entry.delta_e.map(|delta| delta.mean)),
+                csv_optional(entry.delta_e.map(|delta| delta.p95)),
+                csv_optional(entry.delta_e.map(|delta| delta.max)),
+                csv_optional(channel(0).map(|metrics| metrics.psnr)),
+                csv_optional(channel(1).map(|metrics| metrics.psnr)),
+                csv_optional(channel(2).map(|metrics| metrics.psnr)),
+                csv_optional(channel(0).map(|metrics| metrics.ssim)),
+                csv_optional(channel(1).map(|metrics| metrics.ssim)),
+                csv_optional(channel(2).map(|metrics| metrics.ssim)),
+                entry.input_delta_e.mean.to_string(),
+                entry.input_delta_e.p95.to_string(),
+                entry.input_delta_e.max.to_string(),
+                entry.residual_norm.to_string(),
+                entry.total_variation.to_string(),
+                entry.energy.total.to_string(),
+                entry.energy.data.to_string(),
+                entry.energy.total_variation.to_string(),
+                csv_optional(entry.gap),
+                "tv".to_string(),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        for method in &self.methods {
+            let channel = |index: usize| match &method.channels[..] {
+                [gray] => Some(gray),
+                channels => channels.get(index),
+            };
+            let mut row = vec![
+                csv_field(&self.input.to_string_lossy()),
+                String::new(),
+                csv_field(&method.output.to_string_lossy()),
+            ];
+            // the settings and convergence of the solver
+            row.extend(std::iter::repeat_n(String::new(), 8));
+            row.extend([
+                method.seconds.to_string(),
+                String::new(),
+                self.input_noise.to_string(),
+                method.noise.to_string(),
+                csv_optional(method.edge_preservation),
+                csv_optional(method.staircasing),
+                String::new(),
+                csv_optional(method.psnr),
+                csv_optional(method.ssim),
+                csv_optional(method.delta_e.map(|delta| delta.mean)),
+                csv_optional(method.delta_e.map(|delta| delta.p95)),
+                csv_optional(method.delta_e.map(|delta| delta.max)),
+                csv_optional(channel(0).map(|metrics| metrics.psnr)),
+                csv_optional(channel(1).map(|metrics| metrics.psnr)),
+                csv_optional(channel(2).map(|metrics| metrics.psnr)),
+                csv_optional(channel(0).map(|metrics| metrics.ssim)),
+                csv_optional(channel(1).map(|metrics| metrics.ssim)),
+                csv_optional(channel(2).map(|metrics| metrics.ssim)),
+                method.input_delta_e.mean.to_string(),
+                method.input_delta_e.p95.to_string(),
+                method.input_delta_e.max.to_string(),
+                method.residual_norm.to_string(),
+                method.total_variation.to_string(),
+                // not solved by this model
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                csv_field(&method.name),
+            ]);
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        storage::get().write(path, csv.as_bytes())
+    }
+
+    /// Saves the report as pretty-printed JSON.
+    pub fn save_json(&self, path: &Path) -> std::io::Result<()> {
+        storage::save_json(storage::get(), path, self)
+    }
+}
+
+/// Quotes a CSV field if it holds a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Formats an optional value for CSV, empty if there is none.
+fn csv_optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn format_optional(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:.3}", value),
+        None => "-".to_string(),
+    }
+}
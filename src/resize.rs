@@ -0,0 +1,149 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Resampling of image arrays, to denoise at delivery rather than sensor
+//! resolution.
+
+use std::str::FromStr;
+
+use image_recovery::{
+    image::{
+        imageops::{
+            self,
+            FilterType,
+        },
+        Rgb,
+        Rgb32FImage,
+    },
+    ndarray::Array3,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeFilter {
+    /// Lanczos with a window of 3, the sharpest
+    Lanczos,
+    /// Bicubic (Catmull-Rom), with less ringing around edges
+    Bicubic,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Lanczos => FilterType::Lanczos3,
+            ResizeFilter::Bicubic => FilterType::CatmullRom,
+        }
+    }
+}
+
+/// A positive scaling factor, given as a percentage (`50%`) or as a plain
+/// factor (`0.5`).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Scale(pub f64);
+
+impl FromStr for Scale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, divisor) = match s.strip_suffix('%') {
+            Some(number) => (number, 100.0),
+            None => (s, 1.0),
+        };
+        let factor = number
+            .trim()
+            .parse::<f64>()
+            .map_err(|error| format!("invalid scale `{}`: {}", s, error))?
+            / divisor;
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(format!("invalid scale `{}`: must be positive", s));
+        }
+        Ok(Scale(factor))
+    }
+}
+
+/// Downscales `array` so that neither side is larger than `max_dimension`,
+/// keeping the aspect ratio. Returns `None` if it already fits.
+pub fn fit_within(
+    array: &Array3<f64>,
+    max_dimension: u32,
+    filter: ResizeFilter,
+) -> Option<Array3<f64>> {
+    let (width, height, _) = array.dim();
+    let longest = width.max(height) as f64;
+    if longest <= max_dimension as f64 {
+        return None;
+    }
+    Some(scale(array, Scale(max_dimension as f64 / longest), filter))
+}
+
+/// Resizes `array` by the given factor, keeping at least one pixel.
+pub fn scale(
+    array: &Array3<f64>,
+    factor: Scale,
+    filter: ResizeFilter,
+) -> Array3<f64> {
+    let (width, height, _) = array.dim();
+    let new_width = ((width as f64 * factor.0).round() as u32).max(1);
+    let new_height = ((height as f64 * factor.0).round() as u32).max(1);
+    resize(array, new_width, new_height, filter)
+}
+
+/// Resizes `array` to exactly `width` by `height` pixels. Values keep their
+/// scale, limited to the range they had before resampling.
+pub fn resize(
+    array: &Array3<f64>,
+    width: u32,
+    height: u32,
+    filter: ResizeFilter,
+) -> Array3<f64> {
+    // resampling clamps floating point pixels to `[0, 1]`, so values are
+    // mapped from their own range into it and back
+    let min = array.fold(f64::INFINITY, |acc, &v| acc.min(v));
+    let max = array.fold(f64::NEG_INFINITY, |acc, &v| acc.max(v));
+    let range = if max > min { max - min } else { 1.0 };
+
+    let channels = array.dim().2;
+    let img = Rgb32FImage::from_fn(
+        array.dim().0 as u32,
+        array.dim().1 as u32,
+        |x, y| {
+            let value = |z: usize| {
+                ((array[[x as usize, y as usize, z % channels]] - min) / range)
+                    as f32
+            };
+            Rgb([value(0), value(1), value(2)])
+        },
+    );
+    log::debug!(
+        "resizing from {}x{} to {}x{}",
+        img.width(),
+        img.height(),
+        width,
+        height
+    );
+    let resized = imageops::resize(&img, width, height, filter.into());
+    Array3::from_shape_fn(
+        (width as usize, height as usize, channels),
+        |(x, y, z)| {
+            resized.get_pixel(x as u32, y as u32)[z.min(2)] as f64 * range + min
+        },
+    )
+}
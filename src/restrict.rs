@@ -0,0 +1,141 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Confinement of all file access to an allow-list of folders, for running
+//! jobs whose paths come from others.
+
+use std::{
+    io,
+    path::{
+        Component,
+        Path,
+        PathBuf,
+    },
+};
+
+use crate::storage::{
+    Space,
+    Storage,
+};
+
+/// Another storage, refusing every access outside the allowed folders
+/// (and their subfolders), once symbolic links and `..` are resolved.
+pub struct Restricted<S> {
+    inner: S,
+    allowed: Vec<PathBuf>,
+}
+
+impl<S: Storage> Restricted<S> {
+    /// Fails if any of the `allowed` folders does not exist.
+    pub fn new(inner: S, allowed: &[PathBuf]) -> io::Result<Self> {
+        let allowed = allowed
+            .iter()
+            .map(|folder| folder.canonicalize())
+            .collect::<io::Result<_>>()?;
+        Ok(Self { inner, allowed })
+    }
+
+    fn check(&self, path: &Path) -> io::Result<()> {
+        match self.allows(path) {
+            true => Ok(()),
+            false => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "{} is outside of --restrict-paths",
+                    path.to_string_lossy()
+                ),
+            )),
+        }
+    }
+}
+
+/// The absolute path `path` refers to, with the part of it that exists
+/// resolved, or `None` if a part that does not exist yet goes up with `..`
+/// (where it ends up depends on folders yet to be created).
+fn resolve(path: &Path) -> Option<PathBuf> {
+    let absolute = std::path::absolute(path).ok()?;
+    let mut existing = absolute.as_path();
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            let mut resolved = resolved;
+            for component in missing.into_iter().rev() {
+                match component {
+                    Component::Normal(name) => resolved.push(name),
+                    Component::CurDir => {},
+                    _ => return None,
+                }
+            }
+            return Some(resolved);
+        }
+        missing.push(existing.components().next_back()?);
+        existing = existing.parent()?;
+    }
+}
+
+impl<S: Storage> Storage for Restricted<S> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.check(path)?;
+        self.inner.read(path)
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.check(path)?;
+        self.inner.write(path, bytes)
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.check(path)?;
+        self.inner.append(path, bytes)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.allows(path) && self.inner.is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.allows(path) && self.inner.is_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.check(path)?;
+        self.inner.create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.check(path)?;
+        self.inner.remove_dir_all(path)
+    }
+
+    fn free_space(&self, path: &Path) -> Option<Space> {
+        self.allows(path).then(|| self.inner.free_space(path))?
+    }
+
+    fn before_command(&self, command: &str, paths: &[&Path]) -> io::Result<()> {
+        // what else the command accesses cannot be confined
+        for path in paths {
+            self.check(path)?;
+        }
+        self.inner.before_command(command, paths)
+    }
+
+    fn allows(&self, path: &Path) -> bool {
+        resolve(path).is_some_and(|resolved| {
+            self.allowed
+                .iter()
+                .any(|folder| resolved.starts_with(folder))
+        })
+    }
+}
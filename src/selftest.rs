@@ -0,0 +1,179 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Smoke test of an installed binary: denoises a synthetic noisy image
+//! through the same solver, analysis and encoding as a real run, and checks
+//! the result against known bounds.
+
+use std::path::Path;
+
+use image_recovery::{
+    image::DynamicImage,
+    ndarray::Array3,
+    ImageArray,
+};
+
+use crate::{
+    analysis,
+    cancel,
+    determinism,
+    random::Random,
+    solver,
+    storage,
+    verify,
+};
+
+/// Side in pixels of the synthetic image.
+const SIZE: usize = 96;
+/// Standard deviation of the noise added to it, in 8-bit levels.
+const NOISE: f64 = 20.0;
+const LAMBDA: f64 = 0.03;
+const MAX_ITER: u32 = 500;
+
+/// Runs every check, printing how each went, and returns whether all of
+/// them passed.
+pub fn run(cancel: &cancel::Token) -> bool {
+    let clean = synthetic_image();
+    let noisy = add_noise(&clean, NOISE);
+
+    let parameters = solver::Parameters {
+        lambda: LAMBDA,
+        tau: solver::tau(),
+        sigma: solver::sigma(),
+        gamma: solver::gamma(LAMBDA),
+        max_iter: MAX_ITER,
+        convergence_threshold: 1e-4,
+        acceleration: solver::Acceleration::Gamma,
+        channel_copies: 1,
+        threads: 1,
+        precision: solver::Precision::F64,
+        data_weight: None,
+        data_band: None,
+        stop_on_gap: None,
+        keep_dual: false,
+    };
+    let solution =
+        match solver::denoise(&noisy, &parameters, cancel, &mut |_| {}) {
+            Ok(solution) => solution,
+            Err(error) => {
+                println!("FAILED: solve: {}", error);
+                return false;
+            },
+        };
+    let threaded = solver::Parameters {
+        threads: 3,
+        ..parameters
+    };
+    let mismatch = match solver::denoise(&noisy, &threaded, cancel, &mut |_| {})
+    {
+        Ok(again) => determinism::compare(&solution.image, &again.image),
+        Err(error) => {
+            println!("FAILED: solve on 3 threads: {}", error);
+            return false;
+        },
+    };
+    let denoised = solution.image.mapv(|value| value.clamp(0.0, 255.0));
+
+    let input_noise = analysis::estimate_noise(&noisy);
+    let output_noise = analysis::estimate_noise(&denoised);
+    let edges = analysis::edge_preservation(&noisy, &denoised).unwrap_or(0.0);
+    let (noisy_error, denoised_error) =
+        (rms_difference(&clean, &noisy), rms_difference(&clean, &denoised));
+    let round_trip = round_trip(&denoised);
+    let checks = [
+        (
+            format!("converged in {} iterations", solution.iterations()),
+            solution.converged,
+        ),
+        (
+            format!(
+                "noise reduced from {:.3} to {:.3}",
+                input_noise, output_noise
+            ),
+            output_noise < input_noise / 4.0,
+        ),
+        (
+            format!(
+                "error to the clean image reduced from {:.3} to {:.3}",
+                noisy_error, denoised_error
+            ),
+            denoised_error < noisy_error / 2.0,
+        ),
+        (format!("edge preservation of {:.3}", edges), edges > 0.9),
+        (
+            match mismatch {
+                None => "identical results on 1 and 3 threads".to_string(),
+                Some(mismatch) => {
+                    format!("results on 1 and 3 threads: {}", mismatch)
+                },
+            },
+            mismatch.is_none(),
+        ),
+        (
+            match &round_trip {
+                Ok(()) => "PNG encoding round trip".to_string(),
+                Err(error) => format!("PNG encoding round trip: {}", error),
+            },
+            round_trip.is_ok(),
+        ),
+    ];
+
+    for (description, passed) in &checks {
+        println!("{}: {}", if *passed { "ok" } else { "FAILED" }, description);
+    }
+    checks.iter().all(|(_, passed)| *passed)
+}
+
+/// A gray background with a horizontal ramp, and a bright square and a
+/// dark disc on it, so that there are both smooth areas and sharp edges.
+fn synthetic_image() -> Array3<f64> {
+    let center = SIZE as f64 * 0.7;
+    Array3::from_shape_fn((SIZE, SIZE, 3), |(x, y, z)| {
+        let (fx, fy) = (x as f64, y as f64);
+        let base = if (SIZE / 6..SIZE / 2).contains(&x)
+            && (SIZE / 6..SIZE / 2).contains(&y)
+        {
+            200.0
+        } else if (fx - center).hypot(fy - center) < SIZE as f64 / 6.0 {
+            30.0
+        } else {
+            80.0 + 60.0 * fx / SIZE as f64
+        };
+        // slightly different channels, so it is not solved as gray
+        base + 10.0 * z as f64
+    })
+}
+
+/// Adds gaussian noise from a fixed seed, so that every run sees the same
+/// image.
+fn add_noise(array: &Array3<f64>, std_dev: f64) -> Array3<f64> {
+    let mut random = Random::new(0);
+    array.mapv(|value| (value + std_dev * random.gaussian()).clamp(0.0, 255.0))
+}
+
+fn rms_difference(a: &Array3<f64>, b: &Array3<f64>) -> f64 {
+    let difference = a - b;
+    (difference.mapv(|v| v * v).sum() / difference.len() as f64).sqrt()
+}
+
+/// Saves the image as PNG in memory and checks that it reads back the same.
+fn round_trip(array: &Array3<f64>) -> Result<(), String> {
+    let memory = storage::Memory::default();
+    let path = Path::new("self-test.png");
+    let img = DynamicImage::from(ImageArray::from(array).into_rgb());
+    storage::save_image(&memory, &img, path)
+        .map_err(|error| error.to_string())?;
+    verify::check(&memory, path, &img, true)
+}
@@ -0,0 +1,111 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Session files, recording the λ value chosen for each input during
+//! interactive runs so that the full resolution solves can be replayed
+//! later, possibly on another machine. Manifests planning the solves of
+//! whole sweeps share the same format.
+
+use std::{
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    storage,
+    DenoiseArgs,
+};
+
+/// Order in which the entries of a session or manifest are solved.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// As they were recorded
+    Manifest,
+    /// Longest first, by the megapixels solved times the iterations they
+    /// may take
+    Cost,
+    /// By the path of the input
+    Name,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Session {
+    pub entries: Vec<Entry>,
+}
+
+/// The choice made for one input, with the rest of its settings.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Entry {
+    /// Chosen λ value, `None` if the input was skipped.
+    pub lambda: Option<f64>,
+    /// Path to save the result to, instead of the one derived from the
+    /// settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    /// SHA-256 of the input file, in hexadecimal, recorded in the sidecars
+    /// of --save-params.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_sha256: Option<String>,
+    #[serde(flatten)]
+    pub settings: DenoiseArgs,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        storage::load_json(storage::get(), path)
+    }
+
+    /// Loads the session at `path`, or starts a new one if there is no
+    /// file there yet.
+    pub fn load_or_default(path: &Path) -> io::Result<Self> {
+        match Self::load(path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Ok(Self::default())
+            },
+            session => session,
+        }
+    }
+
+    /// Records the choice made for an input, replacing any earlier one.
+    pub fn record(&mut self, entry: Entry) {
+        self.entries.retain(|recorded| {
+            recorded.settings.input_image != entry.settings.input_image
+        });
+        self.entries.push(entry);
+    }
+
+    /// Adds a planned solve, replacing any earlier one of the same input
+    /// and λ value.
+    pub fn plan(&mut self, entry: Entry) {
+        self.entries.retain(|planned| {
+            planned.settings.input_image != entry.settings.input_image
+                || planned.lambda != entry.lambda
+        });
+        self.entries.push(entry);
+    }
+
+    /// Saves the session as pretty-printed JSON.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::save_json(storage::get(), path, self)
+    }
+}
@@ -0,0 +1,821 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The primal-dual denoising algorithm of image-recovery, from
+//! Chambolle, A. and Pock, T. (2011), carried over so that its iterations
+//! can be observed.
+//!
+//! With the `gamma` acceleration it computes the same iterates as
+//! `ImageArray::denoise`, which only returns the final one.
+
+use std::{
+    hint::black_box,
+    thread,
+    time::Instant,
+};
+
+use image_recovery::ndarray::{
+    concatenate,
+    stack,
+    Array2,
+    Array3,
+    Array4,
+    Axis,
+    ErrorKind,
+    NdFloat,
+    ShapeError,
+    Zip,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::cancel;
+
+/// How the step sizes evolve over the iterations.
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Acceleration {
+    /// Fixed steps, as in the basic algorithm
+    None,
+    /// Primal steps shrinking (and dual steps growing) with `gamma`, as in
+    /// image-recovery
+    Gamma,
+    /// Steps rebalanced whenever the primal or dual residual dominates
+    /// the other, after Goldstein, T. et al. (2013); often converges in
+    /// fewer iterations, each costing more
+    Adaptive,
+}
+
+/// Floating point type the iterates are computed in; residuals and the
+/// final image are always given as f64.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// Half the memory traffic of f64, and usually the same 8-bit output
+    F32,
+    F64,
+}
+
+impl std::fmt::Display for Precision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Precision::F32 => write!(f, "f32"),
+            Precision::F64 => write!(f, "f64"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Shape(ShapeError),
+    Cancelled(cancel::Cancelled),
+}
+
+impl From<ShapeError> for Error {
+    fn from(error: ShapeError) -> Self {
+        Error::Shape(error)
+    }
+}
+
+impl From<cancel::Cancelled> for Error {
+    fn from(cancelled: cancel::Cancelled) -> Self {
+        Error::Cancelled(cancelled)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Shape(error) => write!(f, "{}", error),
+            Error::Cancelled(cancelled) => write!(f, "{}", cancelled),
+        }
+    }
+}
+
+/// Acceleration `gamma` per unit of lambda, the choice of Chambolle, A. and
+/// Pock, T. (2011).
+pub const GAMMA_PER_LAMBDA: f64 = 0.35;
+
+/// Primal step of every solve. Chambolle, A. and Pock, T. (2011) need
+/// `tau * sigma * L2 norm^2 <= 1`, where `L2 norm^2 <= 8` for the gradient;
+/// `tau = 1 / sqrt(2)` and [`sigma`] make it an equality.
+pub fn tau() -> f64 {
+    1.0 / 2_f64.sqrt()
+}
+
+/// Dual step of every solve, `1 / (8 tau)`; see [`tau`].
+pub fn sigma() -> f64 {
+    1.0 / (8.0 * tau())
+}
+
+/// Acceleration of the solve for `lambda`, with which the primal step
+/// shrinks and the dual one grows every iteration.
+pub fn gamma(lambda: f64) -> f64 {
+    GAMMA_PER_LAMBDA * lambda
+}
+
+/// Parameters of a solve, with the step sizes given by [`tau`], [`sigma`]
+/// and [`gamma`].
+#[derive(Debug, Clone, Copy)]
+pub struct Parameters<'a> {
+    pub lambda: f64,
+    pub tau: f64,
+    pub sigma: f64,
+    pub gamma: f64,
+    pub max_iter: u32,
+    pub convergence_threshold: f64,
+    pub acceleration: Acceleration,
+    /// How many identical channels each channel of the image stands for,
+    /// so that a grayscale image stored as RGB may be solved (exactly) as
+    /// a single channel; 1 otherwise.
+    pub channel_copies: usize,
+    /// Threads to split the work on the channels across.
+    pub threads: usize,
+    pub precision: Precision,
+    /// Weight of the data term for each sample of the image, from 0 (left
+    /// to the smoothing alone) to 1; 1 for all of them if `None`.
+    pub data_weight: Option<&'a Array3<f64>>,
+    /// Farthest (in 8-bit levels) each sample of the solution may be from
+    /// the image, on top of the data term; as far as it goes if `None`.
+    pub data_band: Option<f64>,
+    /// Stop once the primal-dual gap relative to the energy is below this,
+    /// rather than on `convergence_threshold`.
+    pub stop_on_gap: Option<f64>,
+    /// Whether to return the dual variables of the final iterate.
+    pub keep_dual: bool,
+}
+
+/// Value of the objective the solver minimizes, `TV(u) + λ/2 ‖u - f‖²`
+/// (the model of Rudin, Osher and Fatemi, with λ weighing the data term),
+/// and its terms, on the image as it is solved (padded for its boundary,
+/// and with the fields of interlaced frames apart).
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Energy {
+    /// `½ ‖u - f‖²`, weighed per sample by the weights of the data term if
+    /// any.
+    pub data: f64,
+    /// Sum over the pixels of the norm of the gradient of all channels
+    /// together, with differences wrapping around the borders as in the
+    /// solver.
+    pub total_variation: f64,
+    /// `total_variation + λ · data`.
+    pub total: f64,
+}
+
+#[derive(Debug)]
+pub struct Solution {
+    pub image: Array3<f64>,
+    /// Relative change of each iteration, `‖current - previous‖ /
+    /// ‖previous‖`, in order.
+    pub residuals: Vec<f64>,
+    /// Whether the solve stopped on the convergence threshold rather than
+    /// on `max_iter`.
+    pub converged: bool,
+    /// Of the final iterate.
+    pub energy: Energy,
+    /// Primal-dual gap of the final iterate relative to its energy, if
+    /// measured to stop on.
+    pub gap: Option<f64>,
+    /// Dual variables of the final iterate, if asked for, indexed by x, y,
+    /// channel and the axis of the gradient they are paired with (0 for x,
+    /// 1 for y). Their norm over all channels is at most 1, and reaches it
+    /// where the constraint of the total variation is active.
+    pub dual: Option<Array4<f64>>,
+}
+
+impl Solution {
+    pub fn iterations(&self) -> u32 {
+        self.residuals.len() as u32
+    }
+
+    /// Relative change of the last iteration.
+    pub fn residual(&self) -> f64 {
+        self.residuals.last().cloned().unwrap_or(f64::INFINITY)
+    }
+}
+
+/// State of a solve after one of its iterations.
+pub struct Iteration<'a> {
+    /// Starting from 1.
+    pub number: u32,
+    /// Relative change of the iteration.
+    pub residual: f64,
+    /// Primal-dual gap relative to the energy, if measured to stop on.
+    pub gap: Option<f64>,
+    image: &'a dyn Fn() -> Array3<f64>,
+    energy: &'a dyn Fn() -> Energy,
+}
+
+impl Iteration<'_> {
+    /// The current iterate, assembled from its channels on demand.
+    pub fn image(&self) -> Array3<f64> {
+        (self.image)()
+    }
+
+    /// The energy of the current iterate, computed on demand.
+    pub fn energy(&self) -> Energy {
+        (self.energy)()
+    }
+}
+
+/// Denoises `image` with the total variation model, fails if it is not at
+/// least 2 pixels wide and high, or if cancelled (checked every iteration).
+/// `on_iteration` is called after every iteration, to follow the progress.
+pub fn denoise(
+    image: &Array3<f64>,
+    parameters: &Parameters,
+    cancel: &cancel::Token,
+    on_iteration: &mut dyn FnMut(&Iteration),
+) -> Result<Solution, Error> {
+    match parameters.precision {
+        Precision::F32 => solve::<f32>(image, parameters, cancel, on_iteration),
+        Precision::F64 => solve::<f64>(image, parameters, cancel, on_iteration),
+    }
+}
+
+/// Floating point types the solver can compute in.
+trait Scalar: NdFloat {
+    fn from_f64(value: f64) -> Self;
+    fn into_f64(self) -> f64;
+}
+
+impl Scalar for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Scalar for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn into_f64(self) -> f64 {
+        self
+    }
+}
+
+/// `denoise` in the precision of `F`; the step sizes are kept in f64.
+fn solve<F: Scalar>(
+    image: &Array3<f64>,
+    parameters: &Parameters,
+    cancel: &cancel::Token,
+    on_iteration: &mut dyn FnMut(&Iteration),
+) -> Result<Solution, Error> {
+    let Parameters {
+        lambda,
+        mut tau,
+        mut sigma,
+        gamma,
+        max_iter,
+        convergence_threshold,
+        acceleration,
+        channel_copies,
+        threads,
+        precision: _,
+        data_weight,
+        data_band,
+        stop_on_gap,
+        keep_dual,
+    } = *parameters;
+    // how much the steps of --acceleration adaptive change at first, and
+    // how fast that decays, so that the steps eventually settle
+    let mut adaptive_change = 0.5;
+    const ADAPTIVE_DECAY: f64 = 0.95;
+    // how far apart the residuals may be before the steps are rebalanced
+    const ADAPTIVE_RATIO: f64 = 1.5;
+
+    let (width, height, _) = image.dim();
+    if width < 2 || height < 2 {
+        return Err(ShapeError::from_kind(ErrorKind::Unsupported).into());
+    }
+    let adaptive = acceleration == Acceleration::Adaptive;
+    let mut channels: Vec<Channel<F>> = match data_weight {
+        Some(data_weight) => image
+            .axis_iter(Axis(2))
+            .zip(data_weight.axis_iter(Axis(2)))
+            .map(|(channel, weight)| Channel::new(channel).weighted(weight))
+            .collect(),
+        None => image.axis_iter(Axis(2)).map(Channel::new).collect(),
+    };
+    let mut residuals = Vec::new();
+
+    loop {
+        cancel.check()?;
+
+        // dual ascent, projected back onto the unit ball
+        let squares = in_parallel(&mut channels, threads, |channel| {
+            channel.ascend(F::from_f64(sigma), adaptive)
+        });
+        let mut max = squares[0].clone();
+        for square in &squares[1..] {
+            max = square + &max;
+        }
+        if channel_copies > 1 {
+            max *= F::from_f64(channel_copies as f64);
+        }
+        max.mapv_inplace(|x| F::one().max(x.sqrt()));
+
+        // primal descent, then the proximal step of the data term
+        let theta = match acceleration {
+            Acceleration::Gamma => 1_f64 / (1_f64 + (2_f64 * gamma * tau)),
+            Acceleration::None | Acceleration::Adaptive => 1.0,
+        };
+        let step = Step {
+            lambda: F::from_f64(lambda),
+            tau: F::from_f64(tau),
+            sigma: F::from_f64(sigma),
+            theta: F::from_f64(theta),
+            adaptive,
+            band: data_band.map(F::from_f64),
+        };
+        let sums = in_parallel(&mut channels, threads, |channel| {
+            channel.descend(&max, &step)
+        })
+        .into_iter()
+        .fold(Sums::default(), Sums::add);
+
+        match acceleration {
+            Acceleration::None => (),
+            Acceleration::Gamma => {
+                tau *= theta;
+                sigma /= theta;
+            },
+            Acceleration::Adaptive => {
+                let primal_residual = sums.primal_residual.sqrt();
+                let dual_residual = sums.dual_residual.sqrt();
+                // the product of the steps is kept, and so is stability
+                if primal_residual > ADAPTIVE_RATIO * dual_residual {
+                    tau /= 1.0 - adaptive_change;
+                    sigma *= 1.0 - adaptive_change;
+                    adaptive_change *= ADAPTIVE_DECAY;
+                } else if primal_residual * ADAPTIVE_RATIO < dual_residual {
+                    tau *= 1.0 - adaptive_change;
+                    sigma /= 1.0 - adaptive_change;
+                    adaptive_change *= ADAPTIVE_DECAY;
+                }
+            },
+        }
+
+        let residual = sums.change.sqrt() / sums.previous.sqrt();
+        residuals.push(residual);
+        let gap = stop_on_gap
+            .map(|_| gap(&channels, lambda, channel_copies, data_band));
+        on_iteration(&Iteration {
+            number: residuals.len() as u32,
+            residual,
+            gap,
+            image: &|| assemble(&channels),
+            energy: &|| energy(&channels, lambda, channel_copies),
+        });
+        let converged = match (stop_on_gap, gap) {
+            (Some(threshold), Some(gap)) => gap < threshold,
+            _ => residual < convergence_threshold,
+        };
+        if converged || residuals.len() as u32 >= max_iter {
+            log::debug!(
+                "returned at iteration = {}; where max = {}",
+                residuals.len(),
+                max_iter
+            );
+            log::debug!(
+                "convergence = {}; where threshold = {}",
+                residual,
+                convergence_threshold
+            );
+            return Ok(Solution {
+                image: assemble(&channels),
+                residuals,
+                converged,
+                energy: energy(&channels, lambda, channel_copies),
+                gap,
+                dual: keep_dual.then(|| dual(&channels)),
+            });
+        }
+    }
+}
+
+/// State of the solve of one channel, which only meets the others in the
+/// projection of the dual variables.
+struct Channel<F> {
+    image: Array2<F>,
+    current: Array2<F>,
+    current_bar: Array2<F>,
+    dual_a: Array2<F>,
+    dual_b: Array2<F>,
+    /// Dual variables before the last ascent, for adaptive steps.
+    previous_dual: Option<(Array2<F>, Array2<F>)>,
+    /// Weight of the data term of each pixel, if not the same for all.
+    weight: Option<Array2<F>>,
+}
+
+/// Step sizes of an iteration.
+struct Step<F> {
+    lambda: F,
+    tau: F,
+    sigma: F,
+    theta: F,
+    adaptive: bool,
+    /// Farthest each sample may be from the image, if bounded.
+    band: Option<F>,
+}
+
+/// Squared norms of an iteration, summed over the channels.
+#[derive(Default)]
+struct Sums {
+    change: f64,
+    previous: f64,
+    primal_residual: f64,
+    dual_residual: f64,
+}
+
+impl Sums {
+    fn add(self, other: Self) -> Self {
+        Self {
+            change: self.change + other.change,
+            previous: self.previous + other.previous,
+            primal_residual: self.primal_residual + other.primal_residual,
+            dual_residual: self.dual_residual + other.dual_residual,
+        }
+    }
+}
+
+impl<F: Scalar> Channel<F> {
+    fn new(image: image_recovery::ndarray::ArrayView2<f64>) -> Self {
+        let image = image.mapv(F::from_f64);
+        Self {
+            current: image.clone(),
+            current_bar: image.clone(),
+            dual_a: positive_gradient(&image, 0),
+            dual_b: positive_gradient(&image, 1),
+            previous_dual: None,
+            weight: None,
+            image,
+        }
+    }
+
+    fn weighted(
+        self,
+        weight: image_recovery::ndarray::ArrayView2<f64>,
+    ) -> Self {
+        Self {
+            weight: Some(weight.mapv(F::from_f64)),
+            ..self
+        }
+    }
+
+    /// Takes the dual ascent step, returning the squared lengths of the
+    /// dual vectors of this channel, to project with.
+    fn ascend(&mut self, sigma: F, keep_previous: bool) -> Array2<F> {
+        if keep_previous {
+            self.previous_dual =
+                Some((self.dual_a.clone(), self.dual_b.clone()));
+        }
+        self.dual_a =
+            &self.dual_a + &(positive_gradient(&self.current_bar, 0) * sigma);
+        self.dual_b =
+            &self.dual_b + &(positive_gradient(&self.current_bar, 1) * sigma);
+        (&self.dual_a * &self.dual_a) + (&self.dual_b * &self.dual_b)
+    }
+
+    /// Projects the dual variables by `max`, then takes the primal step.
+    fn descend(&mut self, max: &Array2<F>, step: &Step<F>) -> Sums {
+        let Step {
+            lambda,
+            tau,
+            sigma,
+            theta,
+            adaptive,
+            band,
+        } = *step;
+        self.dual_a /= max;
+        self.dual_b /= max;
+
+        let previous = &self.current;
+        let current = previous
+            - &((negative_gradient(&self.dual_a, 0)
+                + negative_gradient(&self.dual_b, 1))
+                * tau);
+        let mut current = match &self.weight {
+            Some(weight) => {
+                let weight = weight * (tau * lambda);
+                (&current + &(&self.image * &weight)) / (weight + F::one())
+            },
+            None => {
+                (&current + &(&self.image * (tau * lambda)))
+                    / (F::one() + tau * lambda)
+            },
+        };
+        if let Some(band) = band {
+            Zip::from(&mut current)
+                .and(&self.image)
+                .for_each(|value, &f| {
+                    *value = value.max(f - band).min(f + band);
+                });
+        }
+        self.current_bar = &current + &((&current - previous) * theta);
+
+        let mut sums = Sums {
+            change: squared_norm(&(&current - previous)),
+            previous: squared_norm(previous),
+            ..Sums::default()
+        };
+        if adaptive {
+            let (previous_a, previous_b) =
+                self.previous_dual.take().expect("kept for adaptive steps");
+            let primal_change = previous - &current;
+            let change_a = &previous_a - &self.dual_a;
+            let change_b = &previous_b - &self.dual_b;
+            sums.primal_residual = squared_norm(
+                &(&primal_change / tau
+                    - (negative_gradient(&change_a, 0)
+                        + negative_gradient(&change_b, 1))),
+            );
+            sums.dual_residual = squared_norm(
+                &(&change_a / sigma - positive_gradient(&primal_change, 0)),
+            ) + squared_norm(
+                &(&change_b / sigma - positive_gradient(&primal_change, 1)),
+            );
+        }
+        self.current = current;
+        sums
+    }
+}
+
+/// Operations an iteration is made of, to time them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    /// Forward differences of the primal variable, along both axes.
+    Gradient,
+    /// Backward differences of the dual variables, summed.
+    Divergence,
+    /// Dual ascent along the gradient, and the squared lengths of the dual
+    /// vectors that the projection onto the unit ball takes.
+    DualStep,
+    /// Projection of the dual variables, descent along the divergence and
+    /// the proximal step of the data term.
+    PrimalStep,
+}
+
+impl Kernel {
+    pub const ALL: [Kernel; 4] = [
+        Kernel::Gradient,
+        Kernel::Divergence,
+        Kernel::DualStep,
+        Kernel::PrimalStep,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kernel::Gradient => "gradient",
+            Kernel::Divergence => "divergence",
+            Kernel::DualStep => "dual_step",
+            Kernel::PrimalStep => "primal_step",
+        }
+    }
+}
+
+/// Seconds taken by each of `repeats` runs of `kernel` over every channel
+/// of `image`, in `precision`, on one thread, after a first run to warm up
+/// the caches and allocator.
+pub fn time_kernel(
+    kernel: Kernel,
+    image: &Array3<f64>,
+    precision: Precision,
+    repeats: usize,
+) -> Vec<f64> {
+    match precision {
+        Precision::F32 => time::<f32>(kernel, image, repeats),
+        Precision::F64 => time::<f64>(kernel, image, repeats),
+    }
+}
+
+fn time<F: Scalar>(
+    kernel: Kernel,
+    image: &Array3<f64>,
+    repeats: usize,
+) -> Vec<f64> {
+    let mut channels: Vec<Channel<F>> =
+        image.axis_iter(Axis(2)).map(Channel::new).collect();
+    let (width, height, _) = image.dim();
+    // all within the unit ball already, so the dual variables keep their
+    // scale however many times the step is repeated
+    let max = Array2::from_elem((width, height), F::one());
+    let step = Step {
+        lambda: F::from_f64(0.03),
+        tau: F::from_f64(tau()),
+        sigma: F::from_f64(sigma()),
+        theta: F::one(),
+        adaptive: false,
+        band: None,
+    };
+    (0..=repeats)
+        .map(|_| {
+            let started = Instant::now();
+            for channel in channels.iter_mut() {
+                match kernel {
+                    Kernel::Gradient => {
+                        black_box(positive_gradient(&channel.current_bar, 0));
+                        black_box(positive_gradient(&channel.current_bar, 1));
+                    },
+                    Kernel::Divergence => {
+                        black_box(
+                            negative_gradient(&channel.dual_a, 0)
+                                + negative_gradient(&channel.dual_b, 1),
+                        );
+                    },
+                    Kernel::DualStep => {
+                        black_box(channel.ascend(step.sigma, false));
+                    },
+                    Kernel::PrimalStep => {
+                        black_box(channel.descend(&max, &step));
+                    },
+                }
+            }
+            started.elapsed().as_secs_f64()
+        })
+        .skip(1)
+        .collect()
+}
+
+/// Stacks the current iterates of the channels into an image.
+fn assemble<F: Scalar>(channels: &[Channel<F>]) -> Array3<f64> {
+    let currents: Vec<Array2<f64>> = channels
+        .iter()
+        .map(|channel| channel.current.mapv(F::into_f64))
+        .collect();
+    let views: Vec<_> = currents.iter().map(|current| current.view()).collect();
+    stack(Axis(2), &views).expect("channels have the same shape")
+}
+
+/// Dual variables of the channels, paired with the gradients along x and
+/// then y.
+fn dual<F: Scalar>(channels: &[Channel<F>]) -> Array4<f64> {
+    let (width, height) = channels[0].dual_a.dim();
+    Array4::from_shape_fn(
+        (width, height, channels.len(), 2),
+        |(x, y, c, axis)| {
+            let channel = &channels[c];
+            match axis {
+                0 => channel.dual_a[(x, y)],
+                _ => channel.dual_b[(x, y)],
+            }
+            .into_f64()
+        },
+    )
+}
+
+/// Energy of the current iterates of the channels, each of them standing
+/// for `channel_copies` identical ones.
+fn energy<F: Scalar>(
+    channels: &[Channel<F>],
+    lambda: f64,
+    channel_copies: usize,
+) -> Energy {
+    let copies = channel_copies as f64;
+    let mut data = 0.0;
+    let mut squares: Option<Array2<F>> = None;
+    for channel in channels {
+        let difference = &channel.current - &channel.image;
+        let difference = match &channel.weight {
+            Some(weight) => &difference * &difference * weight,
+            None => &difference * &difference,
+        };
+        data += difference.sum().into_f64() / 2.0 * copies;
+        let gradient_a = positive_gradient(&channel.current, 0);
+        let gradient_b = positive_gradient(&channel.current, 1);
+        let square = &gradient_a * &gradient_a + &gradient_b * &gradient_b;
+        squares = Some(match squares {
+            Some(squares) => squares + square,
+            None => square,
+        });
+    }
+    let total_variation = squares.map_or(0.0, |squares| {
+        squares
+            .iter()
+            .map(|&square| (square.into_f64() * copies).sqrt())
+            .sum()
+    });
+    Energy {
+        data,
+        total_variation,
+        total: total_variation + lambda * data,
+    }
+}
+
+/// Primal-dual gap of the current iterates of the channels, relative to
+/// their energy: the energy less the objective of the dual problem at the
+/// dual variables, which bounds from below every energy the solve could
+/// reach, so that it is at most this far (relatively) from the minimum.
+///
+/// The dual objective is `-G*(-Kᵀp)`, with `K` the gradient, `p` the dual
+/// variables (in the unit ball after their projection) and `G*` the convex
+/// conjugate of the data term, `λ/2 w (u - f)²` per sample (with `u` bound
+/// to within `band` of `f`, if it is).
+fn gap<F: Scalar>(
+    channels: &[Channel<F>],
+    lambda: f64,
+    channel_copies: usize,
+    band: Option<f64>,
+) -> f64 {
+    let primal = energy(channels, lambda, channel_copies).total;
+    let mut dual = 0.0;
+    for channel in channels {
+        // -Kᵀp, as in the primal step
+        let divergence = (negative_gradient(&channel.dual_a, 0)
+            + negative_gradient(&channel.dual_b, 1))
+        .mapv(|value| -value.into_f64());
+        let conjugate = |(&y, &f, weight): (&f64, &F, f64)| {
+            let curvature = lambda * weight;
+            // the distance from f the supremum of the conjugate is taken at
+            let distance = match band {
+                Some(band) if curvature > 0.0 => {
+                    (y / curvature).clamp(-band, band)
+                },
+                Some(band) => band * y.signum(),
+                None if curvature > 0.0 => y / curvature,
+                None if y == 0.0 => 0.0,
+                None => return f64::INFINITY,
+            };
+            y * f.into_f64() + y * distance
+                - curvature * distance * distance / 2.0
+        };
+        let conjugates: f64 = match &channel.weight {
+            Some(weight) => divergence
+                .iter()
+                .zip(&channel.image)
+                .zip(weight)
+                .map(|((y, f), &weight)| conjugate((y, f, weight.into_f64())))
+                .sum(),
+            None => divergence
+                .iter()
+                .zip(&channel.image)
+                .map(|(y, f)| conjugate((y, f, 1.0)))
+                .sum(),
+        };
+        dual -= conjugates * channel_copies as f64;
+    }
+    (primal - dual) / primal.abs().max(f64::MIN_POSITIVE)
+}
+
+/// Applies `f` to every channel, split across up to `threads` threads.
+fn in_parallel<F: Scalar, T: Send>(
+    channels: &mut [Channel<F>],
+    threads: usize,
+    f: impl Fn(&mut Channel<F>) -> T + Sync,
+) -> Vec<T> {
+    if threads <= 1 || channels.len() <= 1 {
+        return channels.iter_mut().map(f).collect();
+    }
+    let chunk_size = channels.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = channels
+            .chunks_mut(chunk_size)
+            .map(|chunk| {
+                let f = &f;
+                scope.spawn(move || chunk.iter_mut().map(f).collect::<Vec<T>>())
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("solver thread panicked"))
+            .collect()
+    })
+}
+
+/// `x[i] - x[i - 1]` along `axis`, wrapping around.
+fn positive_gradient<F: Scalar>(array: &Array2<F>, axis: usize) -> Array2<F> {
+    let len = array.len_of(Axis(axis));
+    let (rest, last) = array.view().split_at(Axis(axis), len - 1);
+    array - &concatenate(Axis(axis), &[last, rest]).expect("same shapes")
+}
+
+/// `x[i] - x[i + 1]` along `axis`, wrapping around.
+fn negative_gradient<F: Scalar>(array: &Array2<F>, axis: usize) -> Array2<F> {
+    let (first, rest) = array.view().split_at(Axis(axis), 1);
+    array - &concatenate(Axis(axis), &[rest, first]).expect("same shapes")
+}
+
+fn squared_norm<F: Scalar>(array: &Array2<F>) -> f64 {
+    (array * array).sum().into_f64()
+}
@@ -0,0 +1,154 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Preflight check of the free space on the filesystems outputs are saved
+//! to, so that a sweep without room for its outputs fails before it starts
+//! rather than partway through.
+
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::storage::{
+    self,
+    Space,
+};
+
+/// A number of bytes, given as a plain number or with a binary unit
+/// (`K`, `M`, `G` or `T`, optionally followed by `B` or `iB`, e.g. `10G`,
+/// `10GB` or `10GiB` for 10 GiB).
+#[derive(
+    Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq,
+)]
+pub struct Bytes(pub u64);
+
+const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+impl FromStr for Bytes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let trimmed = trimmed
+            .strip_suffix("iB")
+            .or_else(|| trimmed.strip_suffix('B'))
+            .unwrap_or(trimmed);
+        let (number, exponent) =
+            match trimmed.char_indices().next_back().and_then(|(end, unit)| {
+                let exponent = "KMGT".find(unit.to_ascii_uppercase())?;
+                Some((&trimmed[..end], exponent as u32 + 1))
+            }) {
+                Some(split) => split,
+                None => (trimmed, 0),
+            };
+        let number = number
+            .trim()
+            .parse::<f64>()
+            .map_err(|error| format!("invalid size `{}`: {}", s, error))?;
+        if !(number.is_finite() && number >= 0.0) {
+            return Err(format!("invalid size `{}`: cannot be negative", s));
+        }
+        Ok(Bytes((number * 1024_f64.powi(exponent as i32)) as u64))
+    }
+}
+
+impl std::fmt::Display for Bytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut value = self.0 as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        match unit {
+            0 => write!(f, "{} B", self.0),
+            _ => write!(f, "{:.1} {}", value, UNITS[unit]),
+        }
+    }
+}
+
+/// Bytes expected to be written in each folder.
+#[derive(Debug, Default)]
+pub struct Estimate {
+    folders: Vec<(PathBuf, u64)>,
+}
+
+impl Estimate {
+    pub fn add(&mut self, folder: &Path, bytes: u64) {
+        match self.folders.iter_mut().find(|(known, _)| known == folder) {
+            Some((_, total)) => *total += bytes,
+            None => self.folders.push((folder.to_path_buf(), bytes)),
+        }
+    }
+
+    /// Checks that every filesystem has room for what is to be written to
+    /// its folders while keeping `min_free` bytes free, describing the
+    /// first one that does not otherwise. Folders whose free space is not
+    /// known are not checked.
+    pub fn check(&self, min_free: u64) -> Result<(), String> {
+        let mut filesystems: Vec<(Space, &Path, u64)> = Vec::new();
+        for (folder, bytes) in &self.folders {
+            let Some(space) = storage::get().free_space(folder) else {
+                log::debug!(
+                    "free space of {} not known, not checked",
+                    folder.to_string_lossy()
+                );
+                continue;
+            };
+            match filesystems
+                .iter_mut()
+                .find(|(known, ..)| known.filesystem == space.filesystem)
+            {
+                Some((.., total)) => *total += bytes,
+                None => filesystems.push((space, folder, *bytes)),
+            }
+        }
+
+        for (space, folder, bytes) in filesystems {
+            log::debug!(
+                "{} to be written to the filesystem of {}, {} free",
+                Bytes(bytes),
+                folder.to_string_lossy(),
+                Bytes(space.free)
+            );
+            if space.free < bytes.saturating_add(min_free) {
+                return Err(format!(
+                    "not enough free space for the outputs in {}: they need \
+                     up to {}{}, but only {} is free",
+                    folder.to_string_lossy(),
+                    Bytes(bytes),
+                    match min_free {
+                        0 => String::new(),
+                        _ => format!(
+                            " and {} is to be kept free",
+                            Bytes(min_free)
+                        ),
+                    },
+                    Bytes(space.free)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-channel statistics of an image, for quick triage before choosing
+//! denoising parameters.
+
+use std::path::PathBuf;
+
+use image_recovery::image::RgbImage;
+use serde::Serialize;
+
+use crate::{
+    analysis,
+    region::Region,
+};
+
+/// Width in characters of the longest histogram bar.
+const BAR_WIDTH: usize = 40;
+
+#[derive(Serialize, Debug)]
+pub struct ImageStats {
+    pub image: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// Standard deviation of the noise, in 8-bit levels.
+    pub estimated_noise: f64,
+    /// Flat region the noise was measured in, if not estimated globally.
+    pub noise_patch: Option<Region>,
+    pub channels: Vec<ChannelStats>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChannelStats {
+    pub name: &'static str,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: u8,
+    pub max: u8,
+    /// Percentage of pixels at 0.
+    pub clipped_low: f64,
+    /// Percentage of pixels at 255.
+    pub clipped_high: f64,
+    /// Pixel counts for equally sized bins covering 0 to 255.
+    pub histogram: Vec<u64>,
+}
+
+impl ImageStats {
+    pub fn new(
+        image: PathBuf,
+        img: &RgbImage,
+        bins: usize,
+        noise_patch: Option<Region>,
+    ) -> Self {
+        let array = image_recovery::ImageArray::from(img);
+        let channels = ["red", "green", "blue"]
+            .iter()
+            .enumerate()
+            .map(|(channel, name)| {
+                let values: Vec<u8> =
+                    img.pixels().map(|pixel| pixel[channel]).collect();
+                ChannelStats::new(name, &values, bins)
+            })
+            .collect();
+
+        Self {
+            image,
+            width: img.width(),
+            height: img.height(),
+            estimated_noise: analysis::measure_noise(&array, noise_patch),
+            noise_patch,
+            channels,
+        }
+    }
+
+    /// Prints the statistics and a text histogram per channel to stdout.
+    pub fn print(&self) {
+        println!(
+            "{} ({}x{}), estimated noise: {:.3}{}",
+            self.image.to_string_lossy(),
+            self.width,
+            self.height,
+            self.estimated_noise,
+            match self.noise_patch {
+                Some(patch) => format!(" (in patch {})", patch),
+                None => String::new(),
+            }
+        );
+        println!(
+            "{:>6}  {:>8}  {:>8}  {:>4}  {:>4}  {:>8}  {:>8}",
+            "", "mean", "std dev", "min", "max", "at 0", "at 255"
+        );
+        for channel in &self.channels {
+            println!(
+                "{:>6}  {:>8.3}  {:>8.3}  {:>4}  {:>4}  {:>7.3}%  {:>7.3}%",
+                channel.name,
+                channel.mean,
+                channel.std_dev,
+                channel.min,
+                channel.max,
+                channel.clipped_low,
+                channel.clipped_high
+            );
+        }
+
+        for channel in &self.channels {
+            println!("{} histogram:", channel.name);
+            let bins = channel.histogram.len();
+            let highest = channel.histogram.iter().cloned().max().unwrap_or(0);
+            for (bin, &count) in channel.histogram.iter().enumerate() {
+                let (low, high) = bin_range(bin, bins);
+                let bar = match highest {
+                    0 => 0,
+                    _ => (count as f64 / highest as f64 * BAR_WIDTH as f64)
+                        .round() as usize,
+                };
+                println!(
+                    "  {:>3}-{:<3} {:<width$} {}",
+                    low,
+                    high,
+                    "#".repeat(bar),
+                    count,
+                    width = BAR_WIDTH
+                );
+            }
+        }
+    }
+}
+
+impl ChannelStats {
+    fn new(name: &'static str, values: &[u8], bins: usize) -> Self {
+        let n = values.len().max(1) as f64;
+        let mean = values.iter().map(|&v| v as f64).sum::<f64>() / n;
+        let variance = values
+            .iter()
+            .map(|&v| (v as f64 - mean) * (v as f64 - mean))
+            .sum::<f64>()
+            / n;
+        let mut histogram = vec![0; bins];
+        for &value in values {
+            histogram[value as usize * bins / 256] += 1;
+        }
+        let percentage = |level: u8| {
+            values.iter().filter(|&&v| v == level).count() as f64 / n * 100.0
+        };
+
+        Self {
+            name,
+            mean,
+            std_dev: variance.sqrt(),
+            min: values.iter().cloned().min().unwrap_or(0),
+            max: values.iter().cloned().max().unwrap_or(0),
+            clipped_low: percentage(u8::MIN),
+            clipped_high: percentage(u8::MAX),
+            histogram,
+        }
+    }
+}
+
+/// Inclusive range of levels falling into `bin` out of `bins`.
+fn bin_range(bin: usize, bins: usize) -> (usize, usize) {
+    let low = (bin * 256).div_ceil(bins);
+    let high = ((bin + 1) * 256).div_ceil(bins) - 1;
+    (low, high)
+}
@@ -0,0 +1,474 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Where files are read from and written to, behind a trait so that the
+//! pipeline does not depend on a filesystem: the binary installs the real
+//! one, and environments without it (or checks like `self-test`, and the
+//! `harness` for tests) may keep files in memory instead.
+
+use std::{
+    collections::HashMap,
+    io::{
+        self,
+        Cursor,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        Arc,
+        Mutex,
+        RwLock,
+    },
+};
+
+use image_recovery::image::{
+    error::{
+        LimitError,
+        LimitErrorKind,
+    },
+    io::Reader,
+    DynamicImage,
+    ImageError,
+    ImageFormat,
+    ImageResult,
+};
+
+pub trait Storage: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    /// Replaces the file at `path` with `bytes`, creating it if needed.
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()>;
+    /// Adds `bytes` to the end of the file at `path`, creating it if
+    /// needed, in a single write.
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()>;
+    fn is_file(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+    /// Creates the folder at `path` and any missing parents.
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// Removes the folder at `path` with everything in it.
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// Space left on the filesystem holding `path`, if known.
+    fn free_space(&self, path: &Path) -> Option<Space>;
+    /// Whether `path` may be accessed at all.
+    fn allows(&self, _path: &Path) -> bool {
+        true
+    }
+    /// Called before the shell command `command`, working on the files at
+    /// `paths`, is run (as by `--compare-with`), which is not run if this
+    /// fails.
+    fn before_command(
+        &self,
+        _command: &str,
+        _paths: &[&Path],
+    ) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Space left on a filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Space {
+    /// Identifies the filesystem, so that the space of folders on the same
+    /// one is only counted once.
+    pub filesystem: u64,
+    /// Bytes available to unprivileged users.
+    pub free: u64,
+}
+
+impl<S: Storage + ?Sized> Storage for Box<S> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        (**self).read(path)
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        (**self).write(path, bytes)
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        (**self).append(path, bytes)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        (**self).is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        (**self).is_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        (**self).create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        (**self).remove_dir_all(path)
+    }
+
+    fn free_space(&self, path: &Path) -> Option<Space> {
+        (**self).free_space(path)
+    }
+
+    fn allows(&self, path: &Path) -> bool {
+        (**self).allows(path)
+    }
+
+    fn before_command(&self, command: &str, paths: &[&Path]) -> io::Result<()> {
+        (**self).before_command(command, paths)
+    }
+}
+
+/// The filesystem of the operating system.
+pub struct FileSystem;
+
+impl Storage for FileSystem {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        std::fs::write(path, bytes)
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(bytes)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_dir_all(path)
+    }
+
+    #[cfg(unix)]
+    fn free_space(&self, path: &Path) -> Option<Space> {
+        use std::os::unix::{
+            ffi::OsStrExt,
+            fs::MetadataExt,
+        };
+
+        let filesystem = std::fs::metadata(path).ok()?.dev();
+        let c_path =
+            std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: `c_path` is a valid C string, and `stat` is plain data
+        // that statvfs fills in
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        Some(Space {
+            filesystem,
+            free: stat.f_bavail as u64 * stat.f_frsize as u64,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn free_space(&self, _path: &Path) -> Option<Space> {
+        None
+    }
+}
+
+/// Files kept in memory, by path. Any path without a file is taken to be a
+/// folder, so that outputs can be written anywhere.
+#[derive(Default)]
+pub struct Memory {
+    files: Mutex<HashMap<PathBuf, Vec<u8>>>,
+}
+
+impl Memory {
+    fn files(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Vec<u8>>> {
+        self.files
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// A copy of all files, by path.
+    pub fn snapshot(&self) -> HashMap<PathBuf, Vec<u8>> {
+        self.files().clone()
+    }
+}
+
+impl Storage for Memory {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files().get(path).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no file at {}", path.to_string_lossy()),
+            )
+        })
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.files().insert(path.to_path_buf(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.files()
+            .entry(path.to_path_buf())
+            .or_default()
+            .extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.files().contains_key(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        !self.is_file(path)
+    }
+
+    fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.files().retain(|file, _| !file.starts_with(path));
+        Ok(())
+    }
+
+    fn free_space(&self, _path: &Path) -> Option<Space> {
+        None
+    }
+
+    fn before_command(
+        &self,
+        _command: &str,
+        _paths: &[&Path],
+    ) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "commands cannot work on files kept in memory",
+        ))
+    }
+}
+
+/// The storage in use, forwarding to whichever one is installed at the
+/// time of each call.
+struct Installed(RwLock<Option<Arc<dyn Storage>>>);
+
+impl Installed {
+    fn current(&self) -> Arc<dyn Storage> {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+            .expect("storage is installed at startup")
+    }
+}
+
+impl Storage for Installed {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.current().read(path)
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.current().write(path, bytes)
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.current().append(path, bytes)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.current().is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.current().is_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.current().create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.current().remove_dir_all(path)
+    }
+
+    fn free_space(&self, path: &Path) -> Option<Space> {
+        self.current().free_space(path)
+    }
+
+    fn allows(&self, path: &Path) -> bool {
+        self.current().allows(path)
+    }
+
+    fn before_command(&self, command: &str, paths: &[&Path]) -> io::Result<()> {
+        self.current().before_command(command, paths)
+    }
+}
+
+static STORAGE: Installed = Installed(RwLock::new(None));
+
+/// Installs the storage the pipeline uses, once at startup.
+pub fn init(storage: Box<dyn Storage>) {
+    if replace(Some(storage.into())).is_some() {
+        panic!("storage already installed");
+    }
+}
+
+/// Installs `storage` (or none) in place of the current one, which is
+/// returned so that it can be put back.
+pub fn replace(storage: Option<Arc<dyn Storage>>) -> Option<Arc<dyn Storage>> {
+    let mut installed = STORAGE
+        .0
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    std::mem::replace(&mut installed, storage)
+}
+
+/// The storage installed with `init` (or `replace`).
+pub fn get() -> &'static dyn Storage {
+    &STORAGE
+}
+
+/// Largest images that are decoded, so that oversized (or crafted) inputs
+/// are refused rather than running out of memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_pixels: u64,
+    /// Most memory the decoder may allocate.
+    pub max_decoded_bytes: u64,
+}
+
+impl Limits {
+    pub const DEFAULT: Limits = Limits {
+        max_pixels: 100_000_000,
+        max_decoded_bytes: 1 << 30,
+    };
+
+    pub fn allows(&self, width: u32, height: u32) -> bool {
+        width as u64 * height as u64 <= self.max_pixels
+    }
+}
+
+static LIMITS: RwLock<Limits> = RwLock::new(Limits::DEFAULT);
+
+/// Sets the limits of every image decoded from now on.
+pub fn set_limits(limits: Limits) {
+    *LIMITS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = limits;
+}
+
+/// The limits set with `set_limits`.
+pub fn limits() -> Limits {
+    *LIMITS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Decodes the image at `path`, in the format of its extension if known,
+/// or of its contents otherwise, unless it is over the `limits`.
+pub fn open_image(
+    storage: &dyn Storage,
+    path: &Path,
+) -> ImageResult<DynamicImage> {
+    let bytes = storage.read(path)?;
+    #[cfg(feature = "dicom")]
+    if crate::dicom::is_dicom(&bytes) {
+        return crate::dicom::decode(&bytes, crate::dicom::options().window);
+    }
+    let (width, height) = reader(&bytes, path)?.into_dimensions()?;
+    if !limits().allows(width, height) {
+        return Err(ImageError::Limits(LimitError::from_kind(
+            LimitErrorKind::DimensionError,
+        )));
+    }
+    reader(&bytes, path)?.decode()
+}
+
+/// Width and height of the image at `path`.
+pub fn image_dimensions(
+    storage: &dyn Storage,
+    path: &Path,
+) -> ImageResult<(u32, u32)> {
+    let bytes = storage.read(path)?;
+    #[cfg(feature = "dicom")]
+    if crate::dicom::is_dicom(&bytes) {
+        return crate::dicom::dimensions(&bytes);
+    }
+    reader(&bytes, path)?.into_dimensions()
+}
+
+/// Whether the image at `path` is a JPEG, judging by its contents.
+pub fn is_jpeg(storage: &dyn Storage, path: &Path) -> io::Result<bool> {
+    let bytes = storage.read(path)?;
+    Ok(image_recovery::image::guess_format(&bytes)
+        .is_ok_and(|format| format == ImageFormat::Jpeg))
+}
+
+fn reader<'a>(
+    bytes: &'a [u8],
+    path: &Path,
+) -> ImageResult<Reader<Cursor<&'a [u8]>>> {
+    let mut reader = Reader::new(Cursor::new(bytes));
+    match ImageFormat::from_path(path) {
+        Ok(format) => reader.set_format(format),
+        Err(_) => reader = reader.with_guessed_format()?,
+    }
+    let mut decoding = image_recovery::image::io::Limits::default();
+    decoding.max_alloc = Some(limits().max_decoded_bytes);
+    reader.limits(decoding);
+    Ok(reader)
+}
+
+/// Encodes `img` in the format of the extension of `path`, and saves it
+/// there.
+pub fn save_image(
+    storage: &dyn Storage,
+    img: &DynamicImage,
+    path: &Path,
+) -> ImageResult<()> {
+    let format = ImageFormat::from_path(path)?;
+    let mut bytes = Cursor::new(Vec::new());
+    img.write_to(&mut bytes, format)?;
+    storage.write(path, bytes.get_ref())?;
+    Ok(())
+}
+
+/// Serializes `value` as pretty-printed JSON, and saves it at `path`.
+pub fn save_json(
+    storage: &dyn Storage,
+    path: &Path,
+    value: &impl serde::Serialize,
+) -> io::Result<()> {
+    storage.write(path, &serde_json::to_vec_pretty(value)?)
+}
+
+/// Loads the JSON at `path`.
+pub fn load_json<T: serde::de::DeserializeOwned>(
+    storage: &dyn Storage,
+    path: &Path,
+) -> io::Result<T> {
+    Ok(serde_json::from_slice(&storage.read(path)?)?)
+}
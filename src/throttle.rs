@@ -0,0 +1,136 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Limiting the rate at which files are read and written, so that large
+//! runs do not saturate storage shared with others.
+
+use std::{
+    io,
+    path::Path,
+    str::FromStr,
+    sync::Mutex,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use crate::{
+    space::Bytes,
+    storage::{
+        Space,
+        Storage,
+    },
+};
+
+/// A number of bytes per second, given as a size optionally followed by
+/// `/s` (e.g. `50MB/s`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate(pub Bytes);
+
+impl FromStr for Rate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let size = s.trim().strip_suffix("/s").unwrap_or(s);
+        let bytes = size.parse::<Bytes>()?;
+        if bytes.0 == 0 {
+            return Err(format!("invalid rate `{}`: cannot be zero", s));
+        }
+        Ok(Rate(bytes))
+    }
+}
+
+/// Another storage, whose reads and writes are spread out over time so
+/// that together they do not go faster than a rate.
+pub struct Throttled<S> {
+    inner: S,
+    bytes_per_second: f64,
+    /// When the transfers so far are done at the rate.
+    until: Mutex<Instant>,
+}
+
+impl<S: Storage> Throttled<S> {
+    pub fn new(inner: S, rate: Rate) -> Self {
+        Self {
+            inner,
+            bytes_per_second: rate.0 .0 as f64,
+            until: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits until a transfer of `bytes`, after all the ones before it, is
+    /// done at the rate.
+    fn wait_for(&self, bytes: usize) {
+        let duration =
+            Duration::from_secs_f64(bytes as f64 / self.bytes_per_second);
+        let until = {
+            let mut until = self
+                .until
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            // time left unused is not saved up for bursts
+            *until = (*until).max(Instant::now()) + duration;
+            *until
+        };
+        std::thread::sleep(until.saturating_duration_since(Instant::now()));
+    }
+}
+
+impl<S: Storage> Storage for Throttled<S> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let bytes = self.inner.read(path)?;
+        self.wait_for(bytes.len());
+        Ok(bytes)
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.wait_for(bytes.len());
+        self.inner.write(path, bytes)
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.wait_for(bytes.len());
+        self.inner.append(path, bytes)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.inner.is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.inner.is_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.inner.create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.inner.remove_dir_all(path)
+    }
+
+    fn free_space(&self, path: &Path) -> Option<Space> {
+        self.inner.free_space(path)
+    }
+
+    fn allows(&self, path: &Path) -> bool {
+        self.inner.allows(path)
+    }
+
+    fn before_command(&self, command: &str, paths: &[&Path]) -> io::Result<()> {
+        self.inner.before_command(command, paths)
+    }
+}
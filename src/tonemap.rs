@@ -0,0 +1,83 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Tone-mapping of high dynamic range results into displayable 8-bit
+//! previews.
+
+use image_recovery::{
+    image::{
+        Rgb,
+        RgbImage,
+    },
+    ndarray::Array3,
+};
+/// Operator used to compress linear radiance into the displayable range.
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(
+    clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ToneMap {
+    /// Reinhard, E. et al. (2002), applied on luminance to keep hues
+    Reinhard,
+    /// Filmic curve, as Narkowicz, K. (2015)'s fit of the ACES curve
+    Filmic,
+}
+
+impl ToneMap {
+    /// Tone-maps an array holding linear values on the 8-bit scale (i.e.
+    /// radiance multiplied by 255), then encodes it with the sRGB transfer
+    /// function.
+    pub fn apply(&self, array: &Array3<f64>) -> RgbImage {
+        let (width, height, channels) = array.dim();
+        RgbImage::from_fn(width as u32, height as u32, |x, y| {
+            let mut rgb = [0_f64; 3];
+            for (z, value) in rgb.iter_mut().enumerate() {
+                *value = array[[x as usize, y as usize, z % channels]] / 255.0;
+                *value = value.max(0.0);
+            }
+            let mapped = match self {
+                ToneMap::Reinhard => reinhard(rgb),
+                ToneMap::Filmic => rgb.map(filmic),
+            };
+            Rgb(mapped.map(|v| (srgb_encode(v) * 255.0).round() as u8))
+        })
+    }
+}
+
+fn reinhard(rgb: [f64; 3]) -> [f64; 3] {
+    let luminance = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+    if luminance <= 0.0 {
+        return [0.0; 3];
+    }
+    let scale = 1.0 / (1.0 + luminance);
+    rgb.map(|v| (v * scale).min(1.0))
+}
+
+fn filmic(x: f64) -> f64 {
+    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+fn srgb_encode(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
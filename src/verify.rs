@@ -0,0 +1,74 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Verification of saved outputs, re-opening them to catch corrupt writes
+//! (e.g. on flaky network storage) before the originals are discarded.
+
+use std::path::Path;
+
+use image_recovery::image::DynamicImage;
+
+use crate::storage::{
+    self,
+    Storage,
+};
+
+/// Checks that the file at `path` decodes to an image with the dimensions
+/// and color type of `expected`, and if `checksum` is set that its samples
+/// also have the same checksum, described by the error otherwise.
+pub fn check(
+    storage: &dyn Storage,
+    path: &Path,
+    expected: &DynamicImage,
+    checksum: bool,
+) -> Result<(), String> {
+    let saved = storage::open_image(storage, path)
+        .map_err(|error| format!("could not be decoded: {}", error))?;
+    if (saved.width(), saved.height()) != (expected.width(), expected.height())
+    {
+        return Err(format!(
+            "is {}x{} instead of {}x{}",
+            saved.width(),
+            saved.height(),
+            expected.width(),
+            expected.height()
+        ));
+    }
+    if saved.color() != expected.color() {
+        return Err(format!(
+            "is {:?} instead of {:?}",
+            saved.color(),
+            expected.color()
+        ));
+    }
+    if checksum {
+        let (saved, expected) =
+            (fnv1a(saved.as_bytes()), fnv1a(expected.as_bytes()));
+        if saved != expected {
+            return Err(format!(
+                "has checksum {:016x} instead of {:016x}",
+                saved, expected
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 64-bit FNV-1a hash of the samples, as reported on mismatches.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
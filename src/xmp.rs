@@ -0,0 +1,91 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Attribution embedded in each PNG output as an XMP packet (in an `iTXt`
+//! chunk), with the creator, the copyright notice and how the output was
+//! processed in the Dublin Core fields that image viewers, asset managers
+//! and `exiftool` read (and that IPTC Core maps onto).
+
+use std::{
+    io,
+    path::Path,
+};
+
+use crate::provenance;
+
+/// Keyword of the `iTXt` chunk XMP packets are stored in.
+const KEYWORD: &str = "XML:com.adobe.xmp";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attribution {
+    pub creator: Option<String>,
+    pub rights: Option<String>,
+    pub description: Option<String>,
+}
+
+impl Attribution {
+    /// The XMP packet holding the attribution.
+    pub fn packet(&self) -> String {
+        let mut fields = String::new();
+        if let Some(creator) = &self.creator {
+            fields.push_str(&format!(
+                "   <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:\
+                 creator>\n",
+                escape(creator)
+            ));
+        }
+        for (name, text) in
+            [("rights", &self.rights), ("description", &self.description)]
+        {
+            if let Some(text) = text {
+                fields.push_str(&format!(
+                    "   <dc:{0}><rdf:Alt><rdf:li \
+                     xml:lang=\"x-default\">{1}</rdf:li></rdf:Alt></dc:{0}>\n",
+                    name,
+                    escape(text)
+                ));
+            }
+        }
+        format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+             <rdf:Description rdf:about=\"\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+             {}  </rdf:Description>\n \
+             </rdf:RDF>\n\
+             </x:xmpmeta>\n\
+             <?xpacket end=\"r\"?>",
+            fields
+        )
+    }
+}
+
+/// Adds the XMP packet of `attribution` to the PNG file at `path`.
+pub fn embed(path: &Path, attribution: &Attribution) -> io::Result<()> {
+    let mut chunk = b"iTXt".to_vec();
+    chunk.extend_from_slice(KEYWORD.as_bytes());
+    // uncompressed, with no language tag or translated keyword
+    chunk.extend_from_slice(&[0, 0, 0, 0, 0]);
+    chunk.extend_from_slice(attribution.packet().as_bytes());
+    provenance::add_chunk(path, &chunk)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,45 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Fixtures shared by the integration tests.
+
+use std::io::Cursor;
+
+use image_recovery::image::{
+    ImageFormat,
+    Rgb,
+    RgbImage,
+};
+
+/// A gradient of `width` by `height` pixels, with distinct channels and a
+/// deterministic pattern of noise, as PNG.
+pub fn noisy_png(width: u32, height: u32) -> Vec<u8> {
+    let image = RgbImage::from_fn(width, height, |x, y| {
+        let noise = |seed: u32| ((x * seed + y * 11) % 7) as i32 * 6 - 18;
+        let level = |base: u32, seed: u32| {
+            (base as i32 + noise(seed)).clamp(0, 255) as u8
+        };
+        Rgb([
+            level(x * 240 / width, 3),
+            level(y * 240 / height, 5),
+            level(200 - x * 80 / width, 13),
+        ])
+    });
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .expect("image encodes");
+    bytes
+}
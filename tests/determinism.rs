@@ -0,0 +1,117 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Results depend only on the input and the settings, not on how the work
+//! is split across threads and jobs.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+};
+
+mod common;
+
+use common::noisy_png;
+use denoise_cli::harness;
+
+/// What a sweep of three lambda values solved with `split` leaves behind.
+#[derive(Debug, PartialEq)]
+struct Solved {
+    /// The outputs and their `_dual.npy` exports.
+    files: HashMap<PathBuf, Vec<u8>>,
+    /// The bits of the energies and the residual the report gives for
+    /// each lambda, in its order.
+    values: Vec<[u64; 4]>,
+}
+
+fn solve(split: &[&str]) -> Solved {
+    let mut args = vec![
+        "-i",
+        "in.png",
+        "-o",
+        "out",
+        "-s",
+        "0.05",
+        "-e",
+        "0.2",
+        "-t",
+        "3",
+        "-m",
+        "100",
+        "-c",
+        "1e-6",
+        "--max-parallelism",
+        "8",
+        "--debug-export",
+    ];
+    args.extend_from_slice(split);
+    let run =
+        harness::run(args, [(PathBuf::from("in.png"), noisy_png(20, 14))])
+            .expect("the sweep runs");
+    assert!(run.succeeded);
+    let files = run
+        .files
+        .into_iter()
+        .filter(|(path, _)| {
+            path.extension()
+                .is_some_and(|ext| ext == "png" || ext == "npy")
+        })
+        .filter(|(path, _)| path.starts_with("out"))
+        .collect();
+    let summary = run.summary.expect("the sweep writes a report");
+    let bits = |value: &serde_json::Value| {
+        value.as_f64().expect("the report gives a number").to_bits()
+    };
+    let values = summary["lambdas"]
+        .as_array()
+        .expect("the report lists the lambdas")
+        .iter()
+        .map(|lambda| {
+            let energy = &lambda["energy"];
+            [
+                bits(&energy["data"]),
+                bits(&energy["total_variation"]),
+                bits(&energy["total"]),
+                bits(&lambda["residual"]),
+            ]
+        })
+        .collect();
+    Solved { files, values }
+}
+
+#[test]
+fn outputs_do_not_depend_on_threads_or_jobs() {
+    let sequential = solve(&["--jobs", "1", "--threads-per-task", "1"]);
+    assert_eq!(sequential.files.len(), 6);
+    assert_eq!(sequential.values.len(), 3);
+    for split in [
+        ["--jobs", "1", "--threads-per-task", "3"],
+        ["--jobs", "3", "--threads-per-task", "1"],
+        ["--jobs", "2", "--threads-per-task", "2"],
+    ] {
+        assert_eq!(
+            solve(&split),
+            sequential,
+            "results differ when solved with {:?}",
+            split
+        );
+    }
+}
+
+#[test]
+fn determinism_check_passes() {
+    let checked = solve(&["--determinism-check"]);
+    assert_eq!(checked.files.len(), 6);
+}
@@ -0,0 +1,215 @@
+// Copyright (C) 2022  Lílian Ferreira de Freitas
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Runs of the command line through `harness::run`, on files in memory.
+
+use std::path::PathBuf;
+
+mod common;
+
+use common::noisy_png;
+use denoise_cli::harness;
+
+/// Arguments of a sweep of two lambda values of `in.png`, saved to `out`.
+const SWEEP: [&str; 14] = [
+    "-i", "in.png", "-o", "out", "-s", "0.1", "-e", "0.2", "-t", "2", "-m",
+    "200", "-c", "1e-4",
+];
+
+fn sweep() -> Result<harness::Run, harness::Error> {
+    harness::run(SWEEP, [(PathBuf::from("in.png"), noisy_png(16, 12))])
+}
+
+#[test]
+fn sweep_saves_an_output_per_lambda() {
+    let run = sweep().expect("the sweep runs");
+    assert!(run.succeeded);
+
+    let mut outputs: Vec<&PathBuf> = run
+        .files
+        .keys()
+        .filter(|path| path.starts_with("out"))
+        .collect();
+    outputs.sort();
+    assert_eq!(
+        outputs,
+        [
+            &PathBuf::from("out/in_lambda_=_0.1.png"),
+            &PathBuf::from("out/in_lambda_=_0.2.png"),
+        ]
+    );
+    for output in outputs {
+        let bytes = &run.files[output];
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_ne!(bytes, &noisy_png(16, 12));
+        let image = run.image(output).expect("output decodes").into_rgb8();
+        assert_eq!(image.dimensions(), (16, 12));
+    }
+    assert!(run.summary.is_some());
+
+    // the same run saves the same bytes
+    let again = sweep().expect("the sweep runs again");
+    assert_eq!(
+        run.files[&PathBuf::from("out/in_lambda_=_0.1.png")],
+        again.files[&PathBuf::from("out/in_lambda_=_0.1.png")]
+    );
+}
+
+#[test]
+fn like_takes_the_settings_not_given() {
+    let mut args = SWEEP.to_vec();
+    args.extend(["--save-params", "--deband"]);
+    let recorded =
+        harness::run(args, [(PathBuf::from("in.png"), noisy_png(16, 12))])
+            .expect("the sweep runs");
+    let sidecar = PathBuf::from("out/in_lambda_=_0.1.png.json");
+    let mut inputs = vec![(PathBuf::from("other.png"), noisy_png(16, 12))];
+    inputs.push((sidecar.clone(), recorded.files[&sidecar].clone()));
+    inputs.push((
+        PathBuf::from("out/in_lambda_=_0.1.png"),
+        recorded.files[&PathBuf::from("out/in_lambda_=_0.1.png")].clone(),
+    ));
+
+    let run = harness::run(
+        [
+            "-i",
+            "other.png",
+            "-o",
+            "again",
+            "--like",
+            "out/in_lambda_=_0.1.png",
+            "-m",
+            "3",
+            "--save-params",
+        ],
+        inputs,
+    )
+    .expect("the solve runs");
+    assert!(run.succeeded);
+    let summary = run.summary.expect("a report is saved");
+    let lambda = &summary["lambdas"][0];
+    assert_eq!(lambda["lambda"], 0.1);
+    // given on the command line
+    assert_eq!(lambda["iterations"], 3);
+    assert!(run
+        .files
+        .contains_key(&PathBuf::from("again/other_lambda_=_0.1.png")));
+
+    let params: serde_json::Value = serde_json::from_slice(
+        &run.files[&PathBuf::from("again/other_lambda_=_0.1.png.json")],
+    )
+    .expect("parameters are saved as JSON");
+    // taken from the output solved like
+    assert_eq!(params["deband"], 1.0);
+    assert_eq!(params["max_iter"], 3);
+}
+
+#[test]
+fn like_takes_no_stopping_criterion_when_one_is_given() {
+    let mut args = SWEEP[..12].to_vec();
+    args.extend(["--stop-at-delta", "1e-12", "--save-params"]);
+    let recorded =
+        harness::run(args, [(PathBuf::from("in.png"), noisy_png(16, 12))])
+            .expect("the sweep runs");
+    let output = PathBuf::from("out/in_lambda_=_0.1.png");
+    let sidecar = PathBuf::from("out/in_lambda_=_0.1.png.json");
+    let inputs = [
+        (PathBuf::from("other.png"), noisy_png(16, 12)),
+        (sidecar.clone(), recorded.files[&sidecar].clone()),
+        (output.clone(), recorded.files[&output].clone()),
+    ];
+
+    let run = harness::run(
+        [
+            "-i",
+            "other.png",
+            "-o",
+            "again",
+            "--like",
+            "out/in_lambda_=_0.1.png",
+            "-c",
+            "0.5",
+            "--save-params",
+        ],
+        inputs,
+    )
+    .expect("the solve runs");
+    assert!(run.succeeded);
+    let summary = run.summary.expect("a report is saved");
+    // stopped on the given threshold, long before the recorded delta
+    let iterations = summary["lambdas"][0]["iterations"].as_u64().unwrap();
+    assert!(iterations < 200, "ran {} iterations", iterations);
+
+    let params: serde_json::Value = serde_json::from_slice(
+        &run.files[&PathBuf::from("again/other_lambda_=_0.1.png.json")],
+    )
+    .expect("parameters are saved as JSON");
+    assert_eq!(params["convergence_threshold"], 0.5);
+    assert_eq!(params["stop_at_delta"], serde_json::Value::Null);
+    // the other group is still taken
+    assert_eq!(params["max_iter"], 200);
+}
+
+#[test]
+fn invalid_arguments_are_returned_as_errors() {
+    let mut missing = SWEEP;
+    missing[1] = "missing.png";
+    let run =
+        harness::run(missing, [(PathBuf::from("in.png"), noisy_png(16, 12))]);
+    assert!(matches!(run, Err(harness::Error::Cli(_))));
+
+    let mut reversed = SWEEP;
+    reversed.swap(5, 7);
+    let run =
+        harness::run(reversed, [(PathBuf::from("in.png"), noisy_png(16, 12))]);
+    assert!(matches!(run, Err(harness::Error::Cli(_))));
+
+    // and the harness is usable afterwards
+    assert!(sweep().expect("the sweep runs").succeeded);
+}
+
+#[test]
+fn help_and_errors_are_in_the_language_given() {
+    let message = |run: Result<harness::Run, harness::Error>| match run {
+        Err(harness::Error::Cli(error)) => error.to_string(),
+        other => panic!("expected an error of the command line: {:?}", other),
+    };
+
+    let help = message(harness::run(["--lang", "pt", "--help"], []));
+    assert!(help.contains("Uso:"), "{}", help);
+    assert!(help.contains("Caminho da imagem de entrada"), "{}", help);
+
+    let unknown = message(harness::run(["--lang=pt", "--max-ite", "4"], []));
+    assert!(unknown.starts_with("erro: argumento inesperado '--max-ite'"));
+    assert!(unknown.contains("dica: existe um argumento parecido"));
+
+    let mut missing = SWEEP;
+    missing[1] = "missing.png";
+    let invalid = message(harness::run(
+        ["--lang", "pt"].into_iter().chain(missing),
+        [(PathBuf::from("in.png"), noisy_png(16, 12))],
+    ));
+    assert!(invalid.starts_with("erro: `input_image` deve ser um arquivo"));
+    assert!(invalid.contains("Para mais informações, tente '--help'."));
+}
+
+#[test]
+fn undecodable_inputs_are_returned_as_errors() {
+    let run = harness::run(
+        SWEEP,
+        [(PathBuf::from("in.png"), b"not an image".to_vec())],
+    );
+    assert!(matches!(run, Err(harness::Error::Cli(_))));
+}